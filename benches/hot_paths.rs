@@ -0,0 +1,228 @@
+//! Benchmarks for the code every ray runs through. To compare a branch against `main`, record a
+//! baseline there and measure the branch against it:
+//!
+//! ```text
+//! git checkout main && cargo bench --bench hot_paths -- --save-baseline main
+//! git checkout my-branch && cargo bench --bench hot_paths -- --baseline main
+//! ```
+//!
+//! Reports, with the change from the baseline, end up in `target/criterion`. The SIMD backends
+//! are measured the same way, with `--features simd` or `--features simd-vec3` on the second
+//! run.
+
+use std::{hint::black_box, rc::Rc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use raytracer::internal::bvh::BvhNode;
+use raytracer::internal::color::Color;
+use raytracer::internal::environment::Gradient;
+use raytracer::internal::helpers::Generator;
+use raytracer::internal::light::LightList;
+use raytracer::internal::material::{Conductor, Dielectric, Lambertian, Material};
+use raytracer::internal::packet::{RayPacket, LANES};
+use raytracer::internal::path::PathIntegrator;
+use raytracer::{
+    Camera, Hit, HitList, Hittable, Interval, Point, Point3, Ray, Scalar, Sphere, Vec3,
+};
+
+const RENDER_WIDTH: u32 = 64;
+const RENDER_SAMPLES: u32 = 4;
+const SEED: u64 = 1;
+
+fn diffuse(albedo: Point) -> Rc<dyn Material> {
+    Rc::new(Lambertian::new(Color::from(albedo)))
+}
+
+fn vec3(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vec3");
+    let a = Vec3::from_scalars(1.0, -2.0, 3.0);
+    let b = Vec3::from_scalars(-0.5, 0.25, 4.0);
+
+    group.bench_function("unit_vector", |bench| {
+        bench.iter(|| black_box(&a).unit_vector());
+    });
+    group.bench_function("cross", |bench| {
+        bench.iter(|| black_box(&a).cross(black_box(&b)));
+    });
+    group.bench_function("dot", |bench| {
+        bench.iter(|| black_box(&a).dot(black_box(&b)));
+    });
+    group.bench_function("random_unit_vector", |bench| {
+        bench.iter(Vec3::random_unit_vector);
+    });
+
+    group.finish();
+}
+
+fn sphere_hit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sphere_hit");
+    let sphere = Sphere::new(&Point3::from_scalars(0, 0, -1), 0.5, diffuse(0.5));
+    let origin = Point3::new();
+
+    for (name, direction) in [
+        ("hit", Vec3::from_scalars(0.1, 0.1, -1)),
+        ("miss", Vec3::from_scalars(1, 1, -1)),
+    ] {
+        let ray = Ray::new(origin, direction);
+        group.bench_function(name, |bench| {
+            let mut rec = Hit::new();
+            bench.iter(|| {
+                sphere.hit(
+                    black_box(&ray),
+                    Interval::from(0.001, Point::INFINITY),
+                    &mut rec,
+                )
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// A ray down a row of `n` spheres, so every object is tested and each one it meets is closer
+/// than the last.
+fn hit_list(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hit_list");
+    let material = diffuse(0.5);
+    let ray = Ray::new(Point3::new(), Vec3::from_scalars(0, 0, 1));
+
+    for n in [1_u32, 10, 100, 1000] {
+        let mut world: HitList<dyn Hittable> = HitList::new();
+        for k in (0..n).rev() {
+            let center = Point3::from_scalars(0, 0, 2 * k + 2);
+            world.add(Rc::new(Sphere::new(&center, 0.5, material.clone())));
+        }
+
+        group.throughput(Throughput::Elements(n.into()));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &world, |bench, world| {
+            let mut rec = Hit::new();
+            bench.iter(|| {
+                world.hit(
+                    black_box(&ray),
+                    Interval::from(0.001, Point::INFINITY),
+                    &mut rec,
+                )
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Rays from the origin through a `side` by `side` grid on the plane `z = -1`, in row order, so
+/// neighbouring rays travel close together as camera rays do.
+fn grid_rays(side: u32) -> Vec<Ray> {
+    let step = 2.0 / side.to_point();
+    (0..side)
+        .flat_map(|j| (0..side).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let x = -1.0 + (i.to_point() + 0.5) * step;
+            let y = -1.0 + (j.to_point() + 0.5) * step;
+            Ray::new(Point3::new(), Vec3::from_scalars(x, y, -1))
+        })
+        .collect()
+}
+
+/// The same rays through a hierarchy over random spheres, one at a time and in packets.
+fn traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("traversal");
+    Generator::seed(SEED);
+
+    let material = diffuse(0.5);
+    let mut spheres: HitList<dyn Hittable> = HitList::new();
+    for _ in 0..200 {
+        let center = Vec3::random_interval(Interval::from(-4, 4)) - Vec3::from_scalars(0, 0, 8);
+        spheres.add(Rc::new(Sphere::new(&center, 0.3, material.clone())));
+    }
+    let world = BvhNode::new(spheres);
+
+    let rays = grid_rays(32);
+    let ray_t = Interval::from(0.001, Point::INFINITY);
+    group.throughput(Throughput::Elements(rays.len() as u64));
+
+    group.bench_function("single", |bench| {
+        let mut rec = Hit::new();
+        bench.iter(|| {
+            rays.iter()
+                .filter(|ray| world.hit(black_box(ray), ray_t, &mut rec))
+                .count()
+        });
+    });
+    group.bench_function("packet", |bench| {
+        let mut recs: [Hit; LANES] = Default::default();
+        bench.iter(|| {
+            rays.chunks(LANES)
+                .map(|rays| {
+                    let mut packet = RayPacket::new(black_box(rays), ray_t);
+                    world.hit_packet(&mut packet, &mut recs).count_ones()
+                })
+                .sum::<u32>()
+        });
+    });
+
+    group.finish();
+}
+
+/// Ground, a diffuse, a glass and a metal sphere under the sky gradient.
+fn small_scene() -> HitList<dyn Hittable> {
+    let mut world: HitList<dyn Hittable> = HitList::new();
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0, -100.5, -1),
+        100.0,
+        diffuse(0.5),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0, 0, -1.2),
+        0.5,
+        diffuse(0.7),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(-1, 0, -1),
+        0.5,
+        Rc::new(Dielectric::new(1.5, 0.0)),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(1, 0, -1),
+        0.5,
+        Rc::new(Conductor::gold(0.2)),
+    )));
+    world
+}
+
+fn camera() -> Camera {
+    Camera::builder()
+        .image_width(RENDER_WIDTH)
+        .samples_per_pixel(RENDER_SAMPLES)
+        .max_depth(10)
+        .environment(Rc::new(Gradient::default()))
+        .seed(SEED)
+        .build()
+        .unwrap()
+}
+
+/// Renders the small scene, reported in rays per second. Seeding makes every render trace the
+/// same rays, so the count from one of them holds for all.
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+
+    let world = small_scene();
+    let lights = LightList::new();
+    let (_, stats) = camera()
+        .render_image(&mut PathIntegrator, &world, &lights)
+        .unwrap();
+
+    group.throughput(Throughput::Elements(stats.rays.total()));
+    group.bench_function("small_scene", |bench| {
+        bench.iter(|| {
+            camera()
+                .render_image(&mut PathIntegrator, &world, &lights)
+                .unwrap()
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, vec3, sphere_hit, hit_list, traversal, render);
+criterion_main!(benches);
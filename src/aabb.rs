@@ -0,0 +1,125 @@
+use crate::{
+    interval::Interval,
+    packet::{self, Mask, RayPacket},
+    ray::Ray,
+    vec3::{Point, Point3},
+};
+
+/// Axis-aligned bounding box, one interval per axis.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    pub const EMPTY: Aabb = Aabb {
+        x: Interval::EMPTY,
+        y: Interval::EMPTY,
+        z: Interval::EMPTY,
+    };
+
+    /// Creates a new [`Aabb`] with `a` and `b` as opposite corners.
+    #[must_use]
+    pub fn from_points(a: &Point3, b: &Point3) -> Self {
+        let axis = |i: usize| Interval::from(a[i].min(b[i]), a[i].max(b[i]));
+
+        Aabb {
+            x: axis(0),
+            y: axis(1),
+            z: axis(2),
+        }
+    }
+
+    /// Creates a new [`Aabb`] enclosing both boxes.
+    #[must_use]
+    pub const fn from_boxes(a: &Aabb, b: &Aabb) -> Self {
+        Aabb {
+            x: Interval::enclose(&a.x, &b.x),
+            y: Interval::enclose(&a.y, &b.y),
+            z: Interval::enclose(&a.z, &b.z),
+        }
+    }
+
+    /// Creates a new [`Aabb`] covering what both boxes have in common.
+    #[must_use]
+    pub const fn overlap(a: &Aabb, b: &Aabb) -> Self {
+        Aabb {
+            x: Interval::overlap(&a.x, &b.x),
+            y: Interval::overlap(&a.y, &b.y),
+            z: Interval::overlap(&a.z, &b.z),
+        }
+    }
+
+    #[must_use]
+    pub const fn axis(&self, n: usize) -> &Interval {
+        match n {
+            1 => &self.y,
+            2 => &self.z,
+            _ => &self.x,
+        }
+    }
+
+    /// Axis along which the box is widest.
+    #[must_use]
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test for `r` entering the box within `ray_t`. Flat boxes still count as hit.
+    #[must_use]
+    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
+        for n in 0..3 {
+            let axis = self.axis(n);
+            let inverse = 1.0 / r.direction()[n];
+
+            let t0 = (axis.min - r.origin()[n]) * inverse;
+            let t1 = (axis.max - r.origin()[n]) * inverse;
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            ray_t.min = ray_t.min.max(t0);
+            ray_t.max = ray_t.max.min(t1);
+            if ray_t.max < ray_t.min {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Lanes of `packet` whose ray enters the box within its interval.
+    #[must_use]
+    pub fn hit_packet(&self, packet: &RayPacket) -> Mask {
+        packet::slab(self, packet)
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.x.min > self.x.max || self.y.min > self.y.max || self.z.min > self.z.max
+    }
+
+    /// Center and radius of a sphere enclosing the box.
+    #[must_use]
+    pub fn bounding_sphere(&self) -> (Point3, Point) {
+        if self.is_empty() {
+            return (Point3::new(), 0.0);
+        }
+
+        let min = Point3::from_scalars(self.x.min, self.y.min, self.z.min);
+        let max = Point3::from_scalars(self.x.max, self.y.max, self.z.max);
+        let center = (min + max) / 2;
+
+        (center, (max - center).lenght())
+    }
+}
@@ -0,0 +1,803 @@
+use std::rc::Rc;
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    error::Result,
+    film::Film,
+    helpers::Generator,
+    hit::Hit,
+    integrator::{Integrator, Scene},
+    interval::Interval,
+    light::Light,
+    material::Transport,
+    ray::{Ray, SHADOW_EPSILON},
+    spectrum::Channels,
+    stats::{self, RayKind},
+    vec3::{consts::PI, Point, Point3, Vec3},
+};
+
+/// Directory the per-strategy debug images are written to.
+const STRATEGY_DIR: &str = "out_img";
+
+/// Bidirectional path tracer after Veach, connecting every prefix of a camera subpath to every
+/// prefix of a light subpath and weighting the strategies with the balance heuristic.
+///
+/// Light subpaths start from the light list or the environment. Emissive surfaces can't be
+/// sampled as lights, so they're only found by camera subpaths.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Bdpt {
+    /// Writes the weighted contribution of every `(s, t)` strategy, for paths with up to this
+    /// many bounces, to an image of its own.
+    pub debug_depth: Option<u8>,
+    strategies: Vec<Film>,
+}
+
+/// What a path vertex lies on.
+#[derive(Clone)]
+enum Kind {
+    Camera,
+    /// One of the scene's lights, all of which are delta distributions.
+    Light(Rc<dyn Light>),
+    /// The environment, reached by escaping the scene or emitting from infinitely far away.
+    Environment,
+    Surface(Hit),
+}
+
+#[derive(Clone)]
+struct Vertex<S> {
+    kind: Kind,
+    p: Point3,
+    /// Geometric normal, zero for vertices that aren't on a surface.
+    normal: Vec3,
+    /// Direction towards the previous vertex of the subpath.
+    wo: Vec3,
+    /// Throughput of the subpath up to and including this vertex.
+    beta: S,
+    /// Whether the subpath scattered specularly here, so it can't be connected to.
+    delta: bool,
+    /// Area density of sampling this vertex from the previous one.
+    pdf_fwd: Point,
+    /// Area density of sampling this vertex from the next one, tracing the other way.
+    pdf_rev: Point,
+}
+
+impl<S: Copy> Vertex<S> {
+    fn new(kind: Kind, p: Point3, normal: Vec3, beta: S, pdf_fwd: Point) -> Self {
+        Vertex {
+            kind,
+            p,
+            normal,
+            wo: Vec3::new(),
+            beta,
+            delta: false,
+            pdf_fwd,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        !self.normal.near_zero()
+    }
+
+    fn is_connectible(&self) -> bool {
+        match &self.kind {
+            Kind::Camera | Kind::Environment => true,
+            Kind::Light(light) => !light.is_infinite(),
+            Kind::Surface(rec) => rec.mat.as_ref().is_some_and(|mat| !mat.is_specular()),
+        }
+    }
+
+    fn is_infinite_light(&self) -> bool {
+        match &self.kind {
+            Kind::Environment => true,
+            Kind::Light(light) => light.is_infinite(),
+            _ => false,
+        }
+    }
+
+    const fn is_delta_light(&self) -> bool {
+        matches!(self.kind, Kind::Light(_))
+    }
+
+    /// Radiance emitted from this vertex towards `v`.
+    fn le(&self, scene: &Scene, v: &Vertex<S>) -> Color {
+        let w = (v.p - self.p).unit_vector();
+        match &self.kind {
+            Kind::Environment => scene.environment.radiance(&-w),
+            Kind::Surface(rec) => rec
+                .mat
+                .as_ref()
+                .map_or(Color::new(), |mat| mat.emitted(rec)),
+            _ => Color::new(),
+        }
+    }
+
+    /// BSDF value for light scattered from `next` towards the previous vertex.
+    fn f(&self, next: &Vertex<S>) -> Color {
+        let Kind::Surface(rec) = &self.kind else {
+            return Color::new();
+        };
+
+        let wi = (next.p - self.p).unit_vector();
+        rec.mat
+            .as_ref()
+            .map_or(Color::new(), |mat| mat.eval(&self.wo, &wi, rec))
+    }
+
+    /// Converts a solid angle density of sampling `next` from here to an area density at `next`.
+    fn convert_density(&self, pdf: Point, next: &Vertex<S>) -> Point {
+        if next.is_infinite_light() {
+            return pdf;
+        }
+
+        let w = next.p - self.p;
+        let distance_squared = w.lenght_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = pdf / distance_squared;
+        if next.is_on_surface() {
+            pdf *= next.normal.dot(&(w / distance_squared.sqrt())).abs();
+        }
+        pdf
+    }
+
+    /// Area density of sampling `next` from this vertex, having arrived from `prev`.
+    fn pdf(
+        &self,
+        camera: &Camera,
+        scene: &Scene,
+        prev: Option<&Vertex<S>>,
+        next: &Vertex<S>,
+    ) -> Point {
+        let wn = next.p - self.p;
+        if wn.lenght_squared() == 0.0 {
+            return 0.0;
+        }
+
+        let pdf = match &self.kind {
+            Kind::Light(_) | Kind::Environment => return self.pdf_light(scene, next),
+            Kind::Camera => camera.pdf_direction(&wn),
+            Kind::Surface(rec) => {
+                let (Some(prev), Some(mat)) = (prev, &rec.mat) else {
+                    return 0.0;
+                };
+                let wp = (prev.p - self.p).unit_vector();
+                mat.pdf(&wp, &wn.unit_vector(), rec)
+            }
+        };
+
+        self.convert_density(pdf, next)
+    }
+
+    /// Area density at `v` of light leaving this vertex towards it.
+    fn pdf_light(&self, scene: &Scene, v: &Vertex<S>) -> Point {
+        let w = v.p - self.p;
+        let distance_squared = w.lenght_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let w = w / distance_squared.sqrt();
+
+        let mut pdf = if self.is_infinite_light() {
+            // Emitted from a disk covering the scene, so parallel rays don't spread out
+            let (_, radius) = scene.bounds.bounding_sphere();
+            1.0 / (PI * radius * radius)
+        } else if let Kind::Light(light) = &self.kind {
+            light.pdf_le(&w, &scene.bounds).1 / distance_squared
+        } else {
+            0.0
+        };
+
+        if v.is_on_surface() {
+            pdf *= v.normal.dot(&w).abs();
+        }
+        pdf
+    }
+
+    /// Density of a light subpath starting at this vertex, to then continue towards `v`.
+    fn pdf_light_origin(&self, scene: &Scene, v: &Vertex<S>) -> Point {
+        let w = (v.p - self.p).unit_vector();
+        if self.is_infinite_light() {
+            return infinite_light_density(scene, &-w);
+        }
+
+        match &self.kind {
+            Kind::Light(light) => light.pdf_le(&w, &scene.bounds).0 * scene.light_choice_pdf(),
+            _ => 0.0,
+        }
+    }
+}
+
+impl Integrator for Bdpt {
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        lambda: &mut C,
+        film: &mut Film,
+    ) {
+        let max_depth = usize::from(camera.max_depth);
+        let camera_path = Self::camera_subpath(camera, scene, i, j, lambda);
+        let light_path = Self::light_subpath(scene, lambda, max_depth);
+
+        if let Some(depth) = self.debug_depth {
+            if self.strategies.is_empty() {
+                let count = strategy_index(usize::from(depth) + 1, 0);
+                self.strategies = vec![Film::new(camera.image_width, camera.image_height()); count];
+            }
+        }
+
+        let mut radiance = C::Spectrum::default();
+        for t in 1..=camera_path.len() {
+            // Connecting to a freshly sampled light doesn't need the light subpath
+            for s in 0..=light_path.len().max(1) {
+                if s + t < 2 || (s == 1 && t == 1) || s + t - 2 > max_depth {
+                    continue;
+                }
+
+                let (value, raster) =
+                    Self::connect(camera, scene, lambda, &light_path, &camera_path, s, t);
+
+                if let Some(strategy) = self.strategies.get_mut(strategy_index(s + t - 2, s)) {
+                    match raster {
+                        Some((x, y)) => strategy.add_splat(x, y, lambda.to_film(&value)),
+                        None if t != 1 => strategy.add_sample(i, j, lambda.to_film(&value)),
+                        None => {}
+                    }
+                }
+
+                if t != 1 {
+                    radiance += value;
+                } else if let Some((x, y)) = raster {
+                    film.add_splat(x, y, lambda.to_film(&value));
+                }
+            }
+        }
+
+        film.add_sample(i, j, lambda.to_film(&radiance));
+    }
+
+    fn finish(&mut self, camera: &Camera, _film: &mut Film) -> Result<()> {
+        let Some(max_depth) = self.debug_depth else {
+            return Ok(());
+        };
+
+        for depth in 0..=usize::from(max_depth) {
+            for s in 0..=depth + 1 {
+                let t = depth + 2 - s;
+                if s == 1 && t == 1 {
+                    continue;
+                }
+
+                self.strategies[strategy_index(depth, s)].write(
+                    &format!("{STRATEGY_DIR}/bdpt_d{depth}_s{s}_t{t}.ppm"),
+                    camera.samples_per_pixel,
+                    camera.spectral,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Bdpt {
+    /// Creates a new [`Bdpt`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Bdpt {
+            debug_depth: None,
+            strategies: Vec::new(),
+        }
+    }
+
+    fn camera_subpath<C: Channels>(
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        lambda: &mut C,
+    ) -> Vec<Vertex<C::Spectrum>> {
+        let ray = camera.get_ray(i, j);
+        let beta = C::Spectrum::from(1.0);
+        let pdf = camera.pdf_direction(ray.direction());
+
+        let mut path = vec![Vertex::new(
+            Kind::Camera,
+            camera.center(),
+            Vec3::new(),
+            beta,
+            0.0,
+        )];
+        let max_depth = usize::from(camera.max_depth) + 1;
+        Self::random_walk(scene, ray, lambda, beta, pdf, max_depth, &mut path);
+
+        path
+    }
+
+    fn light_subpath<C: Channels>(
+        scene: &Scene,
+        lambda: &mut C,
+        max_depth: usize,
+    ) -> Vec<Vertex<C::Spectrum>> {
+        let mut path = Vec::new();
+
+        let u = [Generator::random_point(), Generator::random_point()];
+        let (kind, emission) = match scene.pick_light(Generator::random_point()) {
+            Some(light) => (
+                Kind::Light(light.clone()),
+                light.sample_le(u, &scene.bounds),
+            ),
+            None => (
+                Kind::Environment,
+                scene.environment_le(u, [Generator::random_point(), Generator::random_point()]),
+            ),
+        };
+
+        let Some(emission) = emission else {
+            return path;
+        };
+        if emission.pdf_position == 0.0
+            || emission.pdf_direction == 0.0
+            || emission.radiance.near_zero()
+        {
+            return path;
+        }
+
+        let choice = scene.light_choice_pdf();
+        let direction = emission.ray.direction().unit_vector();
+        let le = lambda.illuminant(&emission.radiance);
+
+        let mut vertex = Vertex::new(
+            kind,
+            *emission.ray.origin(),
+            Vec3::new(),
+            le,
+            emission.pdf_position * choice,
+        );
+        let infinite = vertex.is_infinite_light();
+        if infinite {
+            vertex.normal = direction;
+        }
+        path.push(vertex);
+
+        let beta = le / (choice * emission.pdf_position * emission.pdf_direction);
+        Self::random_walk(
+            scene,
+            emission.ray,
+            lambda,
+            beta,
+            emission.pdf_direction,
+            max_depth,
+            &mut path,
+        );
+
+        // Lights at infinity sample a direction before the disk the ray starts on, so the
+        // densities are swapped compared to lights with a position
+        if infinite {
+            if let Some(next) = path.get_mut(1) {
+                next.pdf_fwd = emission.pdf_position;
+                if next.is_on_surface() {
+                    next.pdf_fwd *= direction.dot(&next.normal).abs();
+                }
+            }
+            path[0].pdf_fwd = infinite_light_density(scene, &-direction);
+        }
+
+        path
+    }
+
+    /// Extends `path` by following `ray` and sampling the BSDF at every surface it hits.
+    fn random_walk<C: Channels>(
+        scene: &Scene,
+        ray: Ray,
+        lambda: &mut C,
+        beta: C::Spectrum,
+        pdf: Point,
+        max_depth: usize,
+        path: &mut Vec<Vertex<C::Spectrum>>,
+    ) {
+        let transport = if matches!(path[0].kind, Kind::Camera) {
+            Transport::Radiance
+        } else {
+            Transport::Importance
+        };
+
+        let (mut ray, mut beta, mut pdf_fwd) = (ray, beta, pdf);
+        let mut bounces = 0;
+
+        while bounces < max_depth {
+            let mut rec = Hit::new();
+            stats::ray(match (transport, bounces) {
+                (Transport::Radiance, 0) => RayKind::Camera,
+                (Transport::Radiance, _) => RayKind::Bounce,
+                (Transport::Importance, _) => RayKind::Light,
+            });
+            if !scene
+                .world
+                .hit(&ray, Interval::from(0.0, Point::INFINITY), &mut rec)
+            {
+                // Camera subpaths that escape end on the environment
+                if transport == Transport::Radiance {
+                    let direction = ray.direction().unit_vector();
+                    path.push(Vertex::new(
+                        Kind::Environment,
+                        *ray.origin() + direction,
+                        -direction,
+                        beta,
+                        pdf_fwd,
+                    ));
+                }
+                break;
+            }
+
+            let Some(mat) = rec.mat.clone() else {
+                break;
+            };
+            rec.wavelength = lambda.hero();
+            rec.transport = transport;
+            if mat.is_dispersive() {
+                lambda.terminate_secondary();
+            }
+
+            let wo = -ray.direction().unit_vector();
+            let mut vertex = Vertex::new(Kind::Surface(rec.clone()), rec.p, rec.normal, beta, 0.0);
+            vertex.wo = wo;
+            if let Some(prev) = path.last() {
+                vertex.pdf_fwd = prev.convert_density(pdf_fwd, &vertex);
+            }
+            path.push(vertex);
+
+            bounces += 1;
+            if bounces >= max_depth {
+                break;
+            }
+
+            let Some(bs) = mat.sample(
+                &wo,
+                &rec,
+                Generator::random_point(),
+                [Generator::random_point(), Generator::random_point()],
+            ) else {
+                break;
+            };
+            if bs.f.near_zero() {
+                break;
+            }
+
+            beta =
+                beta * lambda.reflectance(&bs.f) * (rec.normal.dot(&bs.direction).abs() / bs.pdf);
+            pdf_fwd = bs.pdf;
+            let mut pdf_rev = mat.pdf(&bs.direction, &wo, &rec);
+
+            let n = path.len();
+            if bs.specular {
+                path[n - 1].delta = true;
+                pdf_fwd = 0.0;
+                pdf_rev = 0.0;
+            }
+            path[n - 2].pdf_rev = path[n - 1].convert_density(pdf_rev, &path[n - 2]);
+
+            ray = rec.spawn_ray(&bs.direction);
+        }
+    }
+
+    /// Weighted contribution of the strategy using `s` light and `t` camera vertices, with the
+    /// raster position it lands on when the light subpath is connected straight to the camera.
+    fn connect<C: Channels>(
+        camera: &Camera,
+        scene: &Scene,
+        lambda: &C,
+        light_path: &[Vertex<C::Spectrum>],
+        camera_path: &[Vertex<C::Spectrum>],
+        s: usize,
+        t: usize,
+    ) -> (C::Spectrum, Option<(Point, Point)>) {
+        let zero = C::Spectrum::default();
+        let pt = &camera_path[t - 1];
+
+        // Camera subpaths that reached the environment can only be used whole
+        if t > 1 && s != 0 && matches!(pt.kind, Kind::Environment) {
+            return (zero, None);
+        }
+
+        let mut raster = None;
+        let mut sampled = None;
+
+        let radiance = if s == 0 {
+            pt.beta * lambda.illuminant(&pt.le(scene, &camera_path[t - 2]))
+        } else if t == 1 {
+            let qs = &light_path[s - 1];
+            let Some((vertex, position)) = Self::sample_camera_vertex(camera, qs) else {
+                return (zero, None);
+            };
+            if !qs.is_connectible() {
+                return (zero, None);
+            }
+
+            let to_camera = vertex.p - qs.p;
+            let distance = to_camera.lenght();
+            let wi = to_camera / distance;
+
+            let mut radiance = qs.beta * lambda.reflectance(&qs.f(&vertex)) * vertex.beta;
+            if qs.is_on_surface() {
+                radiance = radiance * qs.normal.dot(&wi).abs();
+            }
+            if radiance == zero || occluded(scene, qs, &wi, distance) {
+                return (zero, None);
+            }
+
+            raster = Some(position);
+            sampled = Some(vertex);
+            radiance
+        } else if s == 1 {
+            if !pt.is_connectible() {
+                return (zero, None);
+            }
+            let Some((mut vertex, wi, distance)) = Self::sample_light_vertex(scene, pt, lambda)
+            else {
+                return (zero, None);
+            };
+            vertex.pdf_fwd = vertex.pdf_light_origin(scene, pt);
+
+            let mut radiance = pt.beta * lambda.reflectance(&pt.f(&vertex)) * vertex.beta;
+            if pt.is_on_surface() {
+                radiance = radiance * pt.normal.dot(&wi).abs();
+            }
+            if radiance == zero || occluded(scene, pt, &wi, distance) {
+                return (zero, None);
+            }
+
+            sampled = Some(vertex);
+            radiance
+        } else {
+            let qs = &light_path[s - 1];
+            if !qs.is_connectible() || !pt.is_connectible() {
+                return (zero, None);
+            }
+
+            let radiance =
+                qs.beta * lambda.reflectance(&qs.f(pt)) * lambda.reflectance(&pt.f(qs)) * pt.beta;
+            if radiance == zero {
+                return (zero, None);
+            }
+            radiance * geometry(scene, qs, pt)
+        };
+
+        if radiance == zero {
+            return (zero, None);
+        }
+
+        let weight = Self::mis_weight(
+            camera,
+            scene,
+            light_path,
+            camera_path,
+            sampled.as_ref(),
+            s,
+            t,
+        );
+        (radiance * weight, raster)
+    }
+
+    /// Camera vertex seeing `qs`, with the raster position it's seen at.
+    fn sample_camera_vertex<S: Copy + From<Point>>(
+        camera: &Camera,
+        qs: &Vertex<S>,
+    ) -> Option<(Vertex<S>, (Point, Point))> {
+        let to_camera = camera.center() - qs.p;
+        let distance_squared = to_camera.lenght_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        let direction = -to_camera;
+        let position = camera.raster(&direction)?;
+        let cos_theta = camera.forward().dot(&direction.unit_vector());
+
+        // The pinhole is a single point, so the density by solid angle at `qs` only depends on
+        // the distance and the angle it's seen at
+        let pdf = distance_squared / cos_theta.abs();
+        let beta = S::from(camera.importance(&direction) / pdf);
+
+        Some((
+            Vertex::new(Kind::Camera, camera.center(), Vec3::new(), beta, 0.0),
+            position,
+        ))
+    }
+
+    /// Light vertex sampled from `pt`, with the direction and distance towards it.
+    fn sample_light_vertex<C: Channels>(
+        scene: &Scene,
+        pt: &Vertex<C::Spectrum>,
+        lambda: &C,
+    ) -> Option<(Vertex<C::Spectrum>, Vec3, Point)> {
+        let choice = scene.light_choice_pdf();
+        let (_, radius) = scene.bounds.bounding_sphere();
+
+        let (kind, direction, distance, beta) =
+            if let Some(light) = scene.pick_light(Generator::random_point()) {
+                let sample = light.sample_li(&pt.p)?;
+                let beta = lambda.illuminant(&sample.radiance) / choice;
+                (
+                    Kind::Light(light.clone()),
+                    sample.direction,
+                    sample.distance,
+                    beta,
+                )
+            } else {
+                let sample = scene
+                    .environment
+                    .sample([Generator::random_point(), Generator::random_point()])?;
+                if sample.pdf == 0.0 {
+                    return None;
+                }
+                let beta = lambda.illuminant(&sample.radiance) / (sample.pdf * choice);
+                (Kind::Environment, sample.direction, Point::INFINITY, beta)
+            };
+
+        // Lights at infinity are placed just outside the scene
+        let p = pt.p + direction * distance.min(2.0 * radius);
+        Some((
+            Vertex::new(kind, p, Vec3::new(), beta, 0.0),
+            direction,
+            distance,
+        ))
+    }
+
+    /// Balance heuristic weight of the `(s, t)` strategy against every other strategy that
+    /// could have sampled the same path.
+    fn mis_weight<S: Copy>(
+        camera: &Camera,
+        scene: &Scene,
+        light_path: &[Vertex<S>],
+        camera_path: &[Vertex<S>],
+        sampled: Option<&Vertex<S>>,
+        s: usize,
+        t: usize,
+    ) -> Point {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        // Endpoints of both subpaths, with the vertex sampled by the connection swapped in
+        let pt = match (t, sampled) {
+            (1, Some(vertex)) => vertex,
+            _ => &camera_path[t - 1],
+        };
+        let qs = match (s, sampled) {
+            (0, _) => None,
+            (1, Some(vertex)) => Some(vertex),
+            _ => Some(&light_path[s - 1]),
+        };
+        let pt_minus = (t > 1).then(|| &camera_path[t - 2]);
+        let qs_minus = (s > 1).then(|| &light_path[s - 2]);
+        let light_origin = if s == 1 { qs } else { light_path.first() };
+
+        // Forward and reverse densities with the delta flags, updated for the connection
+        let densities = |v: &Vertex<S>| (v.pdf_fwd, v.pdf_rev, v.delta);
+        let mut camera_densities: Vec<_> = camera_path[..t - 1]
+            .iter()
+            .chain(Some(pt))
+            .map(densities)
+            .collect();
+        let mut light_densities: Vec<_> = light_path[..s.saturating_sub(1)]
+            .iter()
+            .chain(qs)
+            .map(densities)
+            .collect();
+
+        // The connected vertices aren't degenerate, or the strategy would have failed
+        camera_densities[t - 1].2 = false;
+        if s > 0 {
+            light_densities[s - 1].2 = false;
+        }
+
+        camera_densities[t - 1].1 = match (qs, pt_minus) {
+            (Some(qs), _) => qs.pdf(camera, scene, qs_minus, pt),
+            (None, Some(pt_minus)) => pt.pdf_light_origin(scene, pt_minus),
+            (None, None) => 0.0,
+        };
+
+        // Emitters that no light subpath can start from are only found this way
+        if s == 0 && camera_densities[t - 1].1 == 0.0 {
+            return 1.0;
+        }
+
+        if let Some(pt_minus) = pt_minus {
+            camera_densities[t - 2].1 = match qs {
+                Some(qs) => pt.pdf(camera, scene, Some(qs), pt_minus),
+                None => pt.pdf_light(scene, pt_minus),
+            };
+        }
+        if let Some(qs) = qs {
+            light_densities[s - 1].1 = pt.pdf(camera, scene, pt_minus, qs);
+        }
+        if let (Some(qs), Some(qs_minus)) = (qs, qs_minus) {
+            light_densities[s - 2].1 = qs.pdf(camera, scene, Some(pt), qs_minus);
+        }
+
+        // Specular vertices have both densities zeroed, their ratio doesn't count
+        let remap = |pdf: Point| if pdf == 0.0 { 1.0 } else { pdf };
+        let mut sum = 0.0;
+
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            let (fwd, rev, delta) = camera_densities[i];
+            ratio *= remap(rev) / remap(fwd);
+            if !delta && !camera_densities[i - 1].2 {
+                sum += ratio;
+            }
+        }
+
+        let mut ratio = 1.0;
+        for i in (0..s).rev() {
+            let (fwd, rev, delta) = light_densities[i];
+            ratio *= remap(rev) / remap(fwd);
+            let delta_before = if i > 0 {
+                light_densities[i - 1].2
+            } else {
+                light_origin.is_some_and(Vertex::is_delta_light)
+            };
+            if !delta && !delta_before {
+                sum += ratio;
+            }
+        }
+
+        1.0 / (1.0 + sum)
+    }
+}
+
+/// Density by solid angle of a light subpath starting at infinity along `towards_light`.
+fn infinite_light_density(scene: &Scene, towards_light: &Vec3) -> Point {
+    // The directional lights are delta distributions, only the environment can be hit by chance
+    scene.light_choice_pdf() * scene.environment.pdf(towards_light)
+}
+
+/// Geometry term between two vertices, zero if they can't see each other.
+fn geometry<S: Copy>(scene: &Scene, a: &Vertex<S>, b: &Vertex<S>) -> Point {
+    let offset = a.p - b.p;
+    let distance = offset.lenght();
+    if distance == 0.0 {
+        return 0.0;
+    }
+    let w = offset / distance;
+
+    let mut g = 1.0 / (distance * distance);
+    if a.is_on_surface() {
+        g *= a.normal.dot(&w).abs();
+    }
+    if b.is_on_surface() {
+        g *= b.normal.dot(&w).abs();
+    }
+
+    if occluded(scene, b, &w, distance) {
+        0.0
+    } else {
+        g
+    }
+}
+
+fn occluded<S>(scene: &Scene, from: &Vertex<S>, direction: &Vec3, distance: Point) -> bool {
+    let ray = match &from.kind {
+        Kind::Surface(rec) => rec.spawn_ray(direction),
+        _ => Ray::new(from.p, *direction),
+    };
+
+    stats::ray(RayKind::Shadow);
+    scene.world.hit(
+        &ray,
+        Interval::from(0.0, distance * (1.0 - SHADOW_EPSILON)),
+        &mut Hit::new(),
+    )
+}
+
+/// Index of the debug image for strategy `s` among paths with `depth` bounces.
+const fn strategy_index(depth: usize, s: usize) -> usize {
+    depth * (depth + 3) / 2 + s
+}
@@ -0,0 +1,106 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hit, HitList, Hittable},
+    interval::Interval,
+    packet::{Mask, RayPacket, LANES},
+    ray::Ray,
+    stats,
+};
+
+/// Bounding volume hierarchy, splitting the objects in half along the widest axis of their
+/// bounds at every level.
+pub struct BvhNode {
+    left: Rc<dyn Hittable>,
+    right: Rc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// Creates a new [`BvhNode`] over the objects of `list`.
+    #[must_use]
+    pub fn new(list: HitList<dyn Hittable>) -> Self {
+        let mut objects = list.objects;
+        if objects.len() < 2 {
+            let left = objects
+                .pop()
+                .unwrap_or_else(|| Rc::new(HitList::<dyn Hittable>::new()));
+            let bbox = left.bounding_box();
+
+            return BvhNode {
+                left,
+                right: Rc::new(HitList::<dyn Hittable>::new()),
+                bbox,
+            };
+        }
+
+        Self::split(&mut objects)
+    }
+
+    fn split(objects: &mut [Rc<dyn Hittable>]) -> Self {
+        let bbox = objects.iter().fold(Aabb::EMPTY, |acc, obj| {
+            Aabb::from_boxes(&acc, &obj.bounding_box())
+        });
+
+        let axis = bbox.longest_axis();
+        objects.sort_by(|a, b| {
+            let a = a.bounding_box().axis(axis).min;
+            let b = b.bounding_box().axis(axis).min;
+            a.total_cmp(&b)
+        });
+
+        let (left, right) = objects.split_at_mut(objects.len() / 2);
+
+        BvhNode {
+            left: Self::subtree(left),
+            right: Self::subtree(right),
+            bbox,
+        }
+    }
+
+    fn subtree(objects: &mut [Rc<dyn Hittable>]) -> Rc<dyn Hittable> {
+        if let [object] = objects {
+            object.clone()
+        } else {
+            Rc::new(Self::split(objects))
+        }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        stats::node_visit();
+        if !self.bbox.hit(r, ray_t) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(r, ray_t, rec);
+        let max = if hit_left { rec.t } else { ray_t.max };
+        let hit_right = self.right.hit(r, Interval::from(ray_t.min, max), rec);
+
+        hit_left || hit_right
+    }
+
+    fn hit_packet(&self, packet: &mut RayPacket, recs: &mut [Hit; LANES]) -> Mask {
+        for _ in packet.lanes() {
+            stats::node_visit();
+        }
+        let entering = self.bbox.hit_packet(packet);
+        if entering == 0 {
+            return 0;
+        }
+
+        // Only the lanes entering the box go on into the children
+        let active = packet.active;
+        packet.active = entering;
+        let hits = self.left.hit_packet(packet, recs) | self.right.hit_packet(packet, recs);
+        packet.active = active;
+
+        hits
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
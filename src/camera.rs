@@ -1,70 +1,203 @@
+use std::{path::Path, rc::Rc, time::Instant};
+
 use crate::{
-    color::Color,
+    environment::{Environment, Gradient},
+    error::{Error, Result},
+    film::Film,
     helpers::Generator,
-    hit::{Hit, Hittable},
-    interval::Interval,
+    hit::Hittable,
+    image::Image,
+    integrator::{Integrator, Scene},
+    light::LightList,
     ray::Ray,
-    vec3::{Point, Point3, Vec3},
-    writer::Writer,
+    spectrum::{Rgb, SampledWavelengths},
+    stats::{self, Heatmap, RenderStats},
+    vec3::{Point, Point3, Scalar, Vec3},
 };
 
-const PATH_IMG: &str = "out_img/imagem.ppm";
+/// Name the heatmap files start with, next to the image.
+const HEATMAP: &str = "heatmap";
 
 pub struct Camera {
-    pub aspect_ratio: f64,
+    pub aspect_ratio: Point,
     pub image_width: u32,
     pub samples_per_pixel: u32,
     pub max_depth: u8,
+    /// Background seen by escaping rays, also used as a light source.
+    pub environment: Rc<dyn Environment>,
+    /// Trace a set of wavelengths per path instead of RGB, so dispersion shows up.
+    pub spectral: bool,
+    /// Also write images of where the intersection tests were spent.
+    pub heatmap: bool,
+    /// Seed for the samples, so the same settings render the same image.
+    pub seed: Option<u64>,
     image_height: u32,
-    pixel_sample_scale: Point,
     center: Point3,
+    forward: Vec3,
+    /// Area of the viewport scaled to unit distance from the center.
+    film_area: Point,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Camera {
     /// Creates a new [`Camera`].
+    #[must_use]
     pub fn new() -> Self {
         Camera {
             aspect_ratio: 1.0,
             image_width: 100,
             samples_per_pixel: 10,
             max_depth: 10,
+            environment: Rc::new(Gradient::default()),
+            spectral: false,
+            heatmap: false,
+            seed: None,
             image_height: 0,
-            pixel_sample_scale: 0.0,
             center: Point3::new(),
+            forward: Vec3::new(),
+            film_area: 0.0,
             pixel00_loc: Point3::new(),
             pixel_delta_u: Vec3::new(),
             pixel_delta_v: Vec3::new(),
         }
     }
 
-    pub fn render(mut self, world: &impl Hittable) {
-        self.initialize();
+    /// Starts a [`CameraBuilder`] from the default settings.
+    #[must_use]
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder {
+            camera: Self::new(),
+        }
+    }
 
-        let mut writer = Writer::new(PATH_IMG, self.image_width, self.image_height);
+    /// Renders the scene and writes the image to `path`, returning what the render took. The
+    /// heatmap images, when enabled, are written to the same directory.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the settings can't make an image, the world is empty or an image can't be
+    /// written.
+    pub fn render(
+        self,
+        integrator: &mut impl Integrator,
+        world: &impl Hittable,
+        lights: &LightList,
+        path: &str,
+    ) -> Result<RenderStats> {
+        let write_heatmap = self.heatmap;
+        let samples_per_pixel = self.samples_per_pixel;
+        let (image, heatmap, mut render_stats) = self.trace(integrator, world, lights)?;
 
-        for j in 0..self.image_height {
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new();
+        let start = Instant::now();
+        image.write(path)?;
+        if write_heatmap {
+            let prefix = Path::new(path).with_file_name(HEATMAP);
+            heatmap.write(&prefix.to_string_lossy(), samples_per_pixel)?;
+        }
+        render_stats.timings.write = start.elapsed();
 
-                for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += Self::ray_color(&r, self.max_depth, world);
-                }
+        Ok(render_stats)
+    }
 
-                writer.add(pixel_color * self.pixel_sample_scale);
+    /// Renders the scene into memory, returning the image as linear sRGB along with what the
+    /// render took.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the settings can't make an image or the world is empty.
+    pub fn render_image(
+        self,
+        integrator: &mut impl Integrator,
+        world: &impl Hittable,
+        lights: &LightList,
+    ) -> Result<(Image, RenderStats)> {
+        let (image, _, render_stats) = self.trace(integrator, world, lights)?;
+        Ok((image, render_stats))
+    }
+
+    fn trace(
+        mut self,
+        integrator: &mut impl Integrator,
+        world: &impl Hittable,
+        lights: &LightList,
+    ) -> Result<(Image, Heatmap, RenderStats)> {
+        self.initialize()?;
+        if let Some(seed) = self.seed {
+            Generator::seed(seed);
+        }
+        let start = Instant::now();
+        stats::take();
+        stats::take_rays();
+        let mut render_stats = RenderStats::default();
+
+        let environment = self.environment.clone();
+        let scene = Scene::new(world, lights, environment.as_ref());
+        if scene.bounds.is_empty() {
+            return Err(Error::Scene("the world has nothing in it".to_owned()));
+        }
+        let mut film = Film::new(self.image_width, self.image_height);
+        let mut heatmap = Heatmap::new(self.image_width, self.image_height);
+
+        for _ in 0..self.samples_per_pixel {
+            integrator.begin_pass(&self, &scene);
+            // Work shared by the whole pass isn't any one pixel's
+            render_stats.traversal += stats::take();
+
+            for j in 0..self.image_height {
+                for i in 0..self.image_width {
+                    if self.spectral {
+                        let mut lambda =
+                            SampledWavelengths::sample_visible(Generator::random_point());
+                        integrator.sample_pixel(&self, &scene, i, j, &mut lambda, &mut film);
+                    } else {
+                        integrator.sample_pixel(&self, &scene, i, j, &mut Rgb, &mut film);
+                    }
+                    let counts = stats::take();
+                    render_stats.traversal += counts;
+                    heatmap.add(i, j, counts);
+                }
             }
         }
 
-        writer.write();
+        integrator.finish(&self, &mut film)?;
+        let image = film.resolve(self.samples_per_pixel, self.spectral);
+        render_stats.traversal += stats::take();
+        render_stats.rays = stats::take_rays();
+        render_stats.timings.render = start.elapsed();
+
+        Ok((image, heatmap, render_stats))
+    }
+
+    #[must_use]
+    pub const fn image_height(&self) -> u32 {
+        self.image_height
+    }
+
+    #[must_use]
+    pub const fn center(&self) -> Point3 {
+        self.center
+    }
+
+    /// Direction the camera looks along, normal to the viewport.
+    #[must_use]
+    pub const fn forward(&self) -> Vec3 {
+        self.forward
     }
 
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-    fn initialize(&mut self) {
+    fn initialize(&mut self) -> Result<()> {
+        self.validate()?;
+
         // Calculate height, and ensure it is at least 1
-        self.image_height = (f64::from(self.image_width) / self.aspect_ratio)
+        self.image_height = (self.image_width.to_point() / self.aspect_ratio)
             .abs()
             .trunc() as u32;
         self.image_height = if self.image_height < 1 {
@@ -73,15 +206,13 @@ impl Camera {
             self.image_height
         };
 
-        self.pixel_sample_scale = 1.0 / Point::from(self.samples_per_pixel);
-
         self.center = Point3::new();
 
         // Determine viewport dimensions
         let focal_length = 1.0;
         let viewport_height = 2.0;
         let viewport_width =
-            viewport_height * (f64::from(self.image_width) / f64::from(self.image_height));
+            viewport_height * (self.image_width.to_point() / self.image_height.to_point());
 
         // Calculate vectors across the horizontal and down the vertical viewport edges
         let viewport_u = Vec3::from_scalars(viewport_width, 0, 0);
@@ -96,32 +227,94 @@ impl Camera {
         let viewport_upper_left = self.center - vec_focal_lenght - viewport_u / 2 - viewport_v / 2;
 
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+
+        self.forward = -vec_focal_lenght.unit_vector();
+        self.film_area = viewport_width * viewport_height / (focal_length * focal_length);
+
+        Ok(())
     }
 
-    fn ray_color(r: &Ray, dept: u8, world: &impl Hittable) -> Color {
-        if dept == 0 {
-            return Color::new();
+    /// Rejects settings that can't make an image.
+    fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(Error::InvalidParameter(message));
+
+        if !(self.aspect_ratio.is_finite() && self.aspect_ratio > 0.0) {
+            return invalid(format!(
+                "aspect_ratio must be positive, got {}",
+                self.aspect_ratio
+            ));
+        }
+        if self.image_width == 0 {
+            return invalid("image_width must be at least 1".to_owned());
+        }
+        if self.samples_per_pixel == 0 {
+            return invalid("samples_per_pixel must be at least 1".to_owned());
         }
 
-        let mut rec = Hit::new();
+        Ok(())
+    }
 
-        if world.hit(r, Interval::from(0.001, Point::INFINITY), &mut rec) {
-            let direction = rec.normal + Vec3::random_unit_vector();
-            0.5 * (Self::ray_color(&Ray::new(rec.p, direction), dept - 1, world))
-        } else {
-            let unit_direction = r.direction().unit_vector();
-            let a = 0.5 * (unit_direction.y() + 1.0);
+    /// Continuous raster position `direction` from the center passes through, if it's in view.
+    #[must_use]
+    pub fn raster(&self, direction: &Vec3) -> Option<(Point, Point)> {
+        let direction = direction.unit_vector();
+        let cos_theta = direction.dot(&self.forward);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+
+        // Intersect the viewport plane and measure from its upper left corner
+        let focal_length = (self.pixel00_loc - self.center).dot(&self.forward);
+        let on_viewport = self.center + direction * (focal_length / cos_theta);
+        let corner = self.pixel00_loc - 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+        let offset = on_viewport - corner;
+
+        let x = offset.dot(&self.pixel_delta_u) / self.pixel_delta_u.lenght_squared();
+        let y = offset.dot(&self.pixel_delta_v) / self.pixel_delta_v.lenght_squared();
+
+        let inside = (0.0..self.image_width.to_point()).contains(&x)
+            && (0.0..self.image_height.to_point()).contains(&y);
+        inside.then_some((x, y))
+    }
 
-            (1.0 - a) * Color::WHITE + a * Color::BLEND
+    /// Sensitivity of the pinhole to light arriving from `direction`, normalized so it
+    /// integrates to one over the image.
+    #[must_use]
+    pub fn importance(&self, direction: &Vec3) -> Point {
+        if self.raster(direction).is_none() {
+            return 0.0;
         }
+
+        let cos_theta = direction.unit_vector().dot(&self.forward);
+        1.0 / (self.film_area * cos_theta.powi(4))
     }
 
-    fn get_ray(&self, i: u32, j: u32) -> Ray {
+    /// Solid angle density with which [`Camera::get_ray`] generates `direction`.
+    #[must_use]
+    pub fn pdf_direction(&self, direction: &Vec3) -> Point {
+        if self.raster(direction).is_none() {
+            return 0.0;
+        }
+
+        let cos_theta = direction.unit_vector().dot(&self.forward);
+        1.0 / (self.film_area * cos_theta.powi(3))
+    }
+
+    #[must_use]
+    pub fn get_ray(&self, i: u32, j: u32) -> Ray {
         let offset = Self::sample_square();
 
-        let pixel_sample = self.pixel00_loc
-            + ((Point::from(i) + offset.x()) * self.pixel_delta_u)
-            + ((Point::from(j) + offset.y()) * self.pixel_delta_v);
+        self.ray_through(
+            i.to_point() + 0.5 + offset.x(),
+            j.to_point() + 0.5 + offset.y(),
+        )
+    }
+
+    /// Ray through the continuous raster position `(x, y)`, pixel `(i, j)` covering `[i, i + 1)`.
+    #[must_use]
+    pub fn ray_through(&self, x: Point, y: Point) -> Ray {
+        let pixel_sample =
+            self.pixel00_loc + ((x - 0.5) * self.pixel_delta_u) + ((y - 0.5) * self.pixel_delta_v);
 
         let ray_origin = self.center;
         let ray_direction = pixel_sample - ray_origin;
@@ -137,3 +330,71 @@ impl Camera {
         ])
     }
 }
+
+/// Camera settings checked all at once, for setting up a camera in a single expression.
+pub struct CameraBuilder {
+    camera: Camera,
+}
+
+impl CameraBuilder {
+    /// Width over height of the image.
+    #[must_use]
+    pub const fn aspect_ratio(mut self, aspect_ratio: Point) -> Self {
+        self.camera.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// Width of the image in pixels, the height follows from the aspect ratio.
+    #[must_use]
+    pub const fn image_width(mut self, image_width: u32) -> Self {
+        self.camera.image_width = image_width;
+        self
+    }
+
+    #[must_use]
+    pub const fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
+        self.camera.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Most segments a path may have.
+    #[must_use]
+    pub const fn max_depth(mut self, max_depth: u8) -> Self {
+        self.camera.max_depth = max_depth;
+        self
+    }
+
+    #[must_use]
+    pub fn environment(mut self, environment: Rc<dyn Environment>) -> Self {
+        self.camera.environment = environment;
+        self
+    }
+
+    #[must_use]
+    pub const fn spectral(mut self, spectral: bool) -> Self {
+        self.camera.spectral = spectral;
+        self
+    }
+
+    #[must_use]
+    pub const fn heatmap(mut self, heatmap: bool) -> Self {
+        self.camera.heatmap = heatmap;
+        self
+    }
+
+    #[must_use]
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.camera.seed = Some(seed);
+        self
+    }
+
+    /// Checks the settings and creates the [`Camera`].
+    ///
+    /// # Errors
+    ///
+    /// Fails when the aspect ratio isn't positive or the image would have no pixels or samples.
+    pub fn build(self) -> Result<Camera> {
+        self.camera.validate()?;
+        Ok(self.camera)
+    }
+}
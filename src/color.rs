@@ -1,6 +1,9 @@
 use std::fmt::Display;
 
-use crate::{interval::Interval, vec3::Vec3};
+use crate::{
+    interval::Interval,
+    vec3::{Point, Vec3},
+};
 
 pub type Color = Vec3;
 
@@ -16,13 +19,69 @@ impl Color {
     pub const BLEND: Color = Color {
         points: [0.5, 0.7, 1.0],
     };
+
+    /// Converts CIE XYZ tristimulus values to linear sRGB.
+    #[must_use]
+    pub fn from_xyz(x: Point, y: Point, z: Point) -> Color {
+        Color::from_scalars(
+            3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+            -0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z,
+            0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+        )
+    }
+
+    /// Converts linear sRGB to CIE XYZ tristimulus values.
+    #[must_use]
+    pub fn to_xyz(self) -> Vec3 {
+        Vec3::from_scalars(
+            0.412_456_4 * self.x() + 0.357_576_1 * self.y() + 0.180_437_5 * self.z(),
+            0.212_672_9 * self.x() + 0.715_152_2 * self.y() + 0.072_175_0 * self.z(),
+            0.019_333_9 * self.x() + 0.119_192_0 * self.y() + 0.950_304_1 * self.z(),
+        )
+    }
+
+    /// Relative luminance of a linear sRGB color.
+    #[must_use]
+    pub fn luminance(&self) -> Point {
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+    }
+
+    /// Colour for `t` in `[0, 1]`, running from black through blue, green and yellow to red.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    #[must_use]
+    pub fn false_colour(t: Point) -> Color {
+        const STOPS: [[Point; 3]; 5] = [
+            [0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+        ];
+
+        let position = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as Point;
+        let stop = (position as usize).min(STOPS.len() - 2);
+        let fraction = position - stop as Point;
+        let [from, to] = [STOPS[stop], STOPS[stop + 1]];
+        let colour = Color::from_scalars(
+            from[0] + (to[0] - from[0]) * fraction,
+            from[1] + (to[1] - from[1]) * fraction,
+            from[2] + (to[2] - from[2]) * fraction,
+        );
+
+        // The writer gamma encodes, undo it so the stops show as given
+        colour * colour
+    }
 }
 
 impl Display for Color {
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fn linear_to_gama(linear_component: f64) -> f64 {
+        fn linear_to_gama(linear_component: Point) -> Point {
             if linear_component > 0.0 {
                 linear_component.sqrt()
             } else {
@@ -0,0 +1,433 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    color::Color,
+    error::{Error, Result},
+    image::Image,
+    vec3::{consts::SQRT_2, Point},
+};
+
+/// Added to the reference in the denominator of the relative MSE, so black pixels don't
+/// dominate it.
+const RELATIVE_EPSILON: Point = 0.01;
+/// Standard deviation in pixels of the window SSIM compares statistics over.
+const SSIM_SIGMA: Point = 1.5;
+/// Standard deviation in pixels of the blur standing in for the eye's contrast sensitivity, so
+/// noise too fine to see at a normal viewing distance counts for less.
+const FLIP_SIGMA: Point = 1.0;
+
+/// A way of measuring how far a test image is from a reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Mse,
+    Rmse,
+    RelativeMse,
+    /// Peak signal to noise ratio in decibels, taking 1 as the peak.
+    Psnr,
+    /// Mean structural similarity of the display luminance.
+    Ssim,
+    /// Perceptual error in `[0, 1]` combining colour and edge differences, after FLIP.
+    Flip,
+}
+
+impl Metric {
+    /// Whether larger values mean the images are closer.
+    #[must_use]
+    pub const fn higher_is_better(self) -> bool {
+        matches!(self, Metric::Psnr | Metric::Ssim)
+    }
+
+    /// Whether `value` is further from a match than `threshold` allows. A value of NaN, from
+    /// NaN pixels in either image, never passes.
+    #[must_use]
+    pub fn exceeds(self, value: Point, threshold: Point) -> bool {
+        value.is_nan()
+            || if self.higher_is_better() {
+                value < threshold
+            } else {
+                value > threshold
+            }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "mse" => Ok(Metric::Mse),
+            "rmse" => Ok(Metric::Rmse),
+            "relmse" => Ok(Metric::RelativeMse),
+            "psnr" => Ok(Metric::Psnr),
+            "ssim" => Ok(Metric::Ssim),
+            "flip" => Ok(Metric::Flip),
+            _ => Err(Error::InvalidParameter(format!(
+                "unknown metric {s:?}, expected mse, rmse, relmse, psnr, ssim or flip"
+            ))),
+        }
+    }
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Metric::Mse => "MSE",
+            Metric::Rmse => "RMSE",
+            Metric::RelativeMse => "relMSE",
+            Metric::Psnr => "PSNR",
+            Metric::Ssim => "SSIM",
+            Metric::Flip => "FLIP",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Every [`Metric`] for one pair of images.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    pub mse: Point,
+    pub rmse: Point,
+    pub relative_mse: Point,
+    pub psnr: Point,
+    pub ssim: Point,
+    pub flip: Point,
+}
+
+impl Metrics {
+    #[must_use]
+    pub const fn get(&self, metric: Metric) -> Point {
+        match metric {
+            Metric::Mse => self.mse,
+            Metric::Rmse => self.rmse,
+            Metric::RelativeMse => self.relative_mse,
+            Metric::Psnr => self.psnr,
+            Metric::Ssim => self.ssim,
+            Metric::Flip => self.flip,
+        }
+    }
+}
+
+impl Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MSE                 {:>14.6e}", self.mse)?;
+        writeln!(f, "RMSE                {:>14.6e}", self.rmse)?;
+        writeln!(f, "relMSE              {:>14.6e}", self.relative_mse)?;
+        writeln!(f, "PSNR                {:>12.2}dB", self.psnr)?;
+        writeln!(f, "SSIM                {:>14.4}", self.ssim)?;
+        write!(f, "FLIP                {:>14.4}", self.flip)
+    }
+}
+
+/// Measures how far `test` is from `reference` by every [`Metric`].
+///
+/// # Errors
+///
+/// Fails when the images aren't the same size.
+#[allow(clippy::cast_precision_loss)]
+pub fn compare(test: &Image, reference: &Image) -> Result<Metrics> {
+    check_sizes(test, reference)?;
+
+    let mut squared = 0.0;
+    let mut relative = 0.0;
+    for (a, b) in test.pixels().iter().zip(reference.pixels()) {
+        for k in 0..3 {
+            let error = (a[k] - b[k]).powi(2);
+            squared += error;
+            relative += error / (b[k] * b[k] + RELATIVE_EPSILON);
+        }
+    }
+
+    let values = (test.pixels().len() * 3).max(1) as Point;
+    let mse = squared / values;
+
+    Ok(Metrics {
+        mse,
+        rmse: mse.sqrt(),
+        relative_mse: relative / values,
+        psnr: -10.0 * mse.log10(),
+        ssim: mean(&ssim_map(test, reference)),
+        flip: mean(&flip_map(test, reference)),
+    })
+}
+
+/// Per pixel FLIP error of `test` against `reference` in false colour, from black where they
+/// look the same to red where they look nothing alike.
+///
+/// # Errors
+///
+/// Fails when the images aren't the same size.
+pub fn difference_image(test: &Image, reference: &Image) -> Result<Image> {
+    check_sizes(test, reference)?;
+
+    let pixels = flip_map(test, reference)
+        .into_iter()
+        .map(Color::false_colour)
+        .collect();
+    Image::from_pixels(test.width, test.height, pixels)
+}
+
+fn check_sizes(test: &Image, reference: &Image) -> Result<()> {
+    if (test.width, test.height) == (reference.width, reference.height) {
+        return Ok(());
+    }
+
+    Err(Error::InvalidParameter(format!(
+        "can't compare a {}x{} image with a {}x{} reference",
+        test.width, test.height, reference.width, reference.height
+    )))
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn mean(values: &[Point]) -> Point {
+    values.iter().sum::<Point>() / values.len().max(1) as Point
+}
+
+/// Luminance as the writer would display it, clamped and gamma encoded.
+fn display_luminance(image: &Image) -> Vec<Point> {
+    image
+        .pixels()
+        .iter()
+        .map(|p| p.luminance().clamp(0.0, 1.0).sqrt())
+        .collect()
+}
+
+fn ssim_map(test: &Image, reference: &Image) -> Vec<Point> {
+    const C1: Point = 0.01 * 0.01;
+    const C2: Point = 0.03 * 0.03;
+
+    let (width, height) = (test.width as usize, test.height as usize);
+    let x = display_luminance(test);
+    let y = display_luminance(reference);
+    let product =
+        |a: &[Point], b: &[Point]| -> Vec<Point> { a.iter().zip(b).map(|(a, b)| a * b).collect() };
+    let blur = |plane: &[Point]| gaussian_blur(plane, width, height, SSIM_SIGMA);
+
+    let mu_x = blur(&x);
+    let mu_y = blur(&y);
+    let xx = blur(&product(&x, &x));
+    let yy = blur(&product(&y, &y));
+    let xy = blur(&product(&x, &y));
+
+    (0..x.len())
+        .map(|k| {
+            let (mx, my) = (mu_x[k], mu_y[k]);
+            let var_x = xx[k] - mx * mx;
+            let var_y = yy[k] - my * my;
+            let cov = xy[k] - mx * my;
+
+            ((2.0 * mx * my + C1) * (2.0 * cov + C2))
+                / ((mx * mx + my * my + C1) * (var_x + var_y + C2))
+        })
+        .collect()
+}
+
+/// Simplified FLIP: colour differences measured in CIELAB after a blur standing in for the
+/// eye's resolution, boosted wherever the two images have different edges.
+fn flip_map(test: &Image, reference: &Image) -> Vec<Point> {
+    let (width, height) = (test.width as usize, test.height as usize);
+    let max_difference = hyab(
+        &lab(Color::from_scalars(0, 1, 0)),
+        &lab(Color::from_scalars(0, 0, 1)),
+    );
+
+    let filtered = |image: &Image| -> Vec<[Point; 3]> {
+        let channels: Vec<Vec<Point>> = (0..3)
+            .map(|k| {
+                let plane: Vec<Point> = image.pixels().iter().map(|p| p[k]).collect();
+                gaussian_blur(&plane, width, height, FLIP_SIGMA)
+            })
+            .collect();
+        (0..channels[0].len())
+            .map(|i| {
+                lab(Color::from_scalars(
+                    channels[0][i],
+                    channels[1][i],
+                    channels[2][i],
+                ))
+            })
+            .collect()
+    };
+    let edges = |image: &Image| -> Vec<Point> {
+        let lightness: Vec<Point> = image.pixels().iter().map(|p| lab(*p)[0] / 100.0).collect();
+        gradient_magnitude(&lightness, width, height)
+    };
+
+    let (test_lab, reference_lab) = (filtered(test), filtered(reference));
+    let (test_edges, reference_edges) = (edges(test), edges(reference));
+
+    (0..test_lab.len())
+        .map(|k| {
+            // Compressed so that small colour differences still register. Clamped rather than
+            // capped with `min`, which would turn NaN pixels into a merely large error
+            let colour = (hyab(&test_lab[k], &reference_lab[k]) / max_difference)
+                .powf(0.7)
+                .clamp(0.0, 1.0);
+            let feature = ((test_edges[k] - reference_edges[k]).abs() / SQRT_2)
+                .clamp(0.0, 1.0)
+                .sqrt();
+            colour.powf(1.0 - feature)
+        })
+        .collect()
+}
+
+/// CIELAB of a linear sRGB colour clamped to the displayable range, relative to D65 white.
+fn lab(color: Color) -> [Point; 3] {
+    const WHITE: [Point; 3] = [0.950_47, 1.0, 1.088_83];
+    let f = |t: Point| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+
+    let clamped = Color::from_scalars(
+        color.x().clamp(0.0, 1.0),
+        color.y().clamp(0.0, 1.0),
+        color.z().clamp(0.0, 1.0),
+    );
+    let xyz = clamped.to_xyz();
+    let [fx, fy, fz] = [0, 1, 2].map(|k| f(xyz[k] / WHITE[k]));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Distance between two CIELAB colours that suits large differences better than Euclidean.
+fn hyab(a: &[Point; 3], b: &[Point; 3]) -> Point {
+    (a[0] - b[0]).abs() + (a[1] - b[1]).hypot(a[2] - b[2])
+}
+
+/// Sobel gradient magnitude, scaled so a unit step gives one.
+fn gradient_magnitude(plane: &[Point], width: usize, height: usize) -> Vec<Point> {
+    let mut magnitude = Vec::with_capacity(plane.len());
+    for y in 0..height {
+        let rows = [y.saturating_sub(1), y, (y + 1).min(height - 1)];
+        for x in 0..width {
+            let columns = [x.saturating_sub(1), x, (x + 1).min(width - 1)];
+            let at = |row: usize, column: usize| plane[rows[row] * width + columns[column]];
+
+            let gx =
+                (at(0, 2) + 2.0 * at(1, 2) + at(2, 2)) - (at(0, 0) + 2.0 * at(1, 0) + at(2, 0));
+            let gy =
+                (at(2, 0) + 2.0 * at(2, 1) + at(2, 2)) - (at(0, 0) + 2.0 * at(0, 1) + at(0, 2));
+            magnitude.push(gx.hypot(gy) / 4.0);
+        }
+    }
+
+    magnitude
+}
+
+/// Separable Gaussian blur, repeating the edge pixels past the borders.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn gaussian_blur(plane: &[Point], width: usize, height: usize, sigma: Point) -> Vec<Point> {
+    let radius = (3.0 * sigma).ceil() as usize;
+    let mut kernel: Vec<Point> = (0..=2 * radius)
+        .map(|k| {
+            let d = k.abs_diff(radius) as Point;
+            (-d * d / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let total: Point = kernel.iter().sum();
+    for weight in &mut kernel {
+        *weight /= total;
+    }
+
+    // Position `k` of the kernel centred on `p` along an axis `size` long
+    let tap = |p: usize, k: usize, size: usize| (p + k).saturating_sub(radius).min(size - 1);
+    let pass = |source: &[Point], horizontal: bool| -> Vec<Point> {
+        let mut blurred = vec![0.0; source.len()];
+        for y in 0..height {
+            for x in 0..width {
+                blurred[y * width + x] = kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| {
+                        let index = if horizontal {
+                            y * width + tap(x, k, width)
+                        } else {
+                            tap(y, k, height) * width + x
+                        };
+                        source[index] * weight
+                    })
+                    .sum();
+            }
+        }
+        blurred
+    };
+
+    pass(&pass(plane, true), false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn checker(width: u32, height: u32, dark: Point) -> Image {
+        let pixels = (0..height)
+            .flat_map(|j| {
+                (0..width).map(move |i| Color::from(if (i + j) % 2 == 0 { 1.0 } else { dark }))
+            })
+            .collect();
+        Image::from_pixels(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = checker(8, 6, 0.25);
+        let metrics = compare(&image, &image).unwrap();
+
+        assert!(metrics.mse < 1e-12);
+        assert!(metrics.psnr.is_infinite());
+        assert!((metrics.ssim - 1.0).abs() < 1e-9);
+        assert!(metrics.flip < 1e-12);
+    }
+
+    #[test]
+    fn errors_grow_with_the_difference() {
+        let reference = checker(8, 6, 0.25);
+        let close = compare(&checker(8, 6, 0.3), &reference).unwrap();
+        let far = compare(&checker(8, 6, 0.8), &reference).unwrap();
+
+        assert!(close.mse < far.mse);
+        assert!(close.psnr > far.psnr);
+        assert!(close.ssim > far.ssim);
+        assert!(close.flip < far.flip);
+        assert!(Metric::Psnr.exceeds(far.psnr, close.psnr));
+        assert!(Metric::Flip.exceeds(far.flip, close.flip));
+    }
+
+    #[test]
+    fn nan_pixels_fail_every_threshold() {
+        let reference = checker(8, 6, 0.25);
+        let mut test = reference.clone();
+        test.set(3, 2, Color::from(Point::NAN));
+        let metrics = compare(&test, &reference).unwrap();
+
+        for metric in [
+            Metric::Mse,
+            Metric::Rmse,
+            Metric::RelativeMse,
+            Metric::Psnr,
+            Metric::Ssim,
+            Metric::Flip,
+        ] {
+            let threshold = if metric.higher_is_better() {
+                Point::NEG_INFINITY
+            } else {
+                Point::INFINITY
+            };
+            assert!(
+                metric.exceeds(metrics.get(metric), threshold),
+                "{metric} of {} passes",
+                metrics.get(metric)
+            );
+        }
+    }
+}
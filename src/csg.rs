@@ -0,0 +1,254 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hit, Hittable},
+    interval::Interval,
+    ray::Ray,
+};
+
+/// Stretch of a ray inside a solid, between the hits where it enters and leaves.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub enter: Hit,
+    pub exit: Hit,
+}
+
+/// Closed object with an inside, which [`Csg`] can combine with others.
+pub trait Solid: Hittable {
+    /// Every stretch of the line through `r` that lies inside the solid, in order along the
+    /// ray. Boundaries behind the origin are included, so a ray starting inside still finds
+    /// the stretch it starts in.
+    fn spans(&self, r: &Ray) -> Vec<Span>;
+}
+
+/// How [`Csg`] combines its two solids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Everything inside either solid.
+    Union,
+    /// Everything inside both solids.
+    Intersection,
+    /// Everything inside the first solid and outside the second.
+    Difference,
+}
+
+impl Operation {
+    /// Whether a point inside the solids given is inside their combination.
+    const fn contains(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            Operation::Union => in_a || in_b,
+            Operation::Intersection => in_a && in_b,
+            Operation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// Constructive solid geometry, two solids combined by an [`Operation`]. Every surface keeps the
+/// material of the solid it comes from, so the hollow a difference cuts takes the material of
+/// the solid taken away.
+pub struct Csg {
+    operation: Operation,
+    a: Rc<dyn Solid>,
+    b: Rc<dyn Solid>,
+    bbox: Aabb,
+}
+
+impl Csg {
+    /// Creates a new [`Csg`] combining `a` and `b` by `operation`.
+    pub fn new(operation: Operation, a: Rc<dyn Solid>, b: Rc<dyn Solid>) -> Self {
+        let (box_a, box_b) = (a.bounding_box(), b.bounding_box());
+        let bbox = match operation {
+            Operation::Union => Aabb::from_boxes(&box_a, &box_b),
+            Operation::Intersection => Aabb::overlap(&box_a, &box_b),
+            Operation::Difference => box_a,
+        };
+
+        Csg {
+            operation,
+            a,
+            b,
+            bbox,
+        }
+    }
+
+    pub fn union(a: Rc<dyn Solid>, b: Rc<dyn Solid>) -> Self {
+        Self::new(Operation::Union, a, b)
+    }
+
+    pub fn intersection(a: Rc<dyn Solid>, b: Rc<dyn Solid>) -> Self {
+        Self::new(Operation::Intersection, a, b)
+    }
+
+    pub fn difference(a: Rc<dyn Solid>, b: Rc<dyn Solid>) -> Self {
+        Self::new(Operation::Difference, a, b)
+    }
+}
+
+impl Solid for Csg {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        if !self.bbox.hit(r, Interval::UNIVERSE) {
+            return Vec::new();
+        }
+
+        let boundaries = |spans: Vec<Span>, from_b: bool| {
+            spans
+                .into_iter()
+                .flat_map(|span| [span.enter, span.exit])
+                .map(move |rec| (rec, from_b))
+        };
+        let mut boundaries: Vec<(Hit, bool)> = boundaries(self.a.spans(r), false)
+            .chain(boundaries(self.b.spans(r), true))
+            .collect();
+        // Stable, so a span that starts and ends at once still starts first
+        boundaries.sort_by(|(a, _), (b, _)| a.t.total_cmp(&b.t));
+
+        // Every boundary crossed toggles the ray in or out of the solid it belongs to
+        let (mut in_a, mut in_b) = (false, false);
+        let mut spans = Vec::new();
+        let mut enter: Option<Hit> = None;
+        for (mut rec, from_b) in boundaries {
+            if from_b {
+                in_b = !in_b;
+            } else {
+                in_a = !in_a;
+            }
+
+            let inside = self.operation.contains(in_a, in_b);
+            match enter.take() {
+                None if inside => {
+                    // Leaving the second solid of a difference enters the result
+                    rec.front_face = true;
+                    enter = Some(rec);
+                }
+                Some(start) if !inside => {
+                    rec.front_face = false;
+                    spans.push(Span {
+                        enter: start,
+                        exit: rec,
+                    });
+                }
+                start => enter = start,
+            }
+        }
+
+        spans
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        let nearest = self
+            .spans(r)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|boundary| ray_t.surrounds(boundary.t));
+
+        match nearest {
+            Some(boundary) => {
+                *rec = boundary;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        color::Color,
+        cuboid::Cuboid,
+        helpers::Generator,
+        material::Lambertian,
+        sphere::Sphere,
+        vec3::{Point, Point3, Vec3},
+    };
+
+    #[test]
+    fn difference_exposes_the_hollow() {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let a = Rc::new(Sphere::new(&Point3::new(), 1.0, material.clone()));
+        let b = Rc::new(Sphere::new(&Point3::from_scalars(0, 0, 1), 0.5, material));
+        let csg = Csg::difference(a, b);
+
+        let r = Ray::new(Point3::from_scalars(0, 0, 5), Vec3::from_scalars(0, 0, -1));
+        let mut rec = Hit::new();
+        assert!(csg.hit(&r, Interval::from(0.0, Point::INFINITY), &mut rec));
+        assert!((rec.t - 4.5).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::from_scalars(0, 0, 1)).lenght() < 1e-6);
+
+        let spans = csg.spans(&r);
+        assert_eq!(spans.len(), 1);
+        assert!((spans[0].exit.t - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn spans_match_point_membership() {
+        Generator::seed(13);
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+
+        for operation in [
+            Operation::Union,
+            Operation::Intersection,
+            Operation::Difference,
+        ] {
+            for _ in 0..200 {
+                let center = Vec3::random_interval(Interval::from(-1, 1));
+                let radius = 0.5 + Generator::random_point();
+                let corners = [0, 1].map(|_| Vec3::random_interval(Interval::from(-1.5, 1.5)));
+                let cuboid = Aabb::from_points(&corners[0], &corners[1]);
+                let csg = Csg::new(
+                    operation,
+                    Rc::new(Sphere::new(&center, radius, material.clone())),
+                    Rc::new(Cuboid::new(&corners[0], &corners[1], material.clone())),
+                );
+
+                let inside = |p: &Point3| {
+                    let in_box = (0..3).all(|n| cuboid.axis(n).contains(p[n]));
+                    operation.contains((*p - center).lenght() < radius, in_box)
+                };
+
+                for _ in 0..20 {
+                    let origin = 6.0 * Vec3::random_unit_vector();
+                    let target = Vec3::random_interval(Interval::from(-1, 1));
+                    let r = Ray::new(origin, target - origin);
+                    let spans = csg.spans(&r);
+
+                    let mut previous = Point::NEG_INFINITY;
+                    for span in &spans {
+                        let (enter, exit) = (span.enter.t, span.exit.t);
+                        assert!(previous <= enter && enter <= exit);
+                        assert!(span.enter.front_face && !span.exit.front_face);
+                        if exit - enter > 1e-6 {
+                            assert!(inside(&r.at(0.5 * (enter + exit))));
+                        }
+                        if enter - previous > 1e-6 {
+                            let gap = if previous.is_finite() {
+                                0.5 * (previous + enter)
+                            } else {
+                                enter - 1.0
+                            };
+                            assert!(!inside(&r.at(gap)));
+                        }
+                        previous = exit;
+                    }
+                    assert!(!inside(&r.at(previous.max(0.0) + 1.0)));
+
+                    let mut rec = Hit::new();
+                    let hit = csg.hit(&r, Interval::from(0.0, Point::INFINITY), &mut rec);
+                    assert_eq!(hit, !spans.is_empty());
+                    if hit {
+                        assert!((rec.t - spans[0].enter.t).abs() < 1e-12);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    csg::{Solid, Span},
+    hit::{Hit, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::{gamma, Ray},
+    stats,
+    vec3::{Point, Point3, Vec3},
+};
+
+/// Where a ray crosses the surface of a [`Cuboid`].
+#[derive(Debug, Clone, Copy)]
+struct Crossing {
+    t: Point,
+    /// Axis the face crossed is perpendicular to.
+    axis: usize,
+    /// Whether the face is the one at the top of the axis.
+    upper: bool,
+}
+
+/// Axis-aligned box. Every face has surface coordinates running from 0 to 1 along the two
+/// axes after its own, in `x`, `y`, `z` order.
+#[derive(Debug, Clone)]
+pub struct Cuboid {
+    bbox: Aabb,
+    mat: Rc<dyn Material>,
+}
+
+impl Cuboid {
+    /// Creates a new [`Cuboid`] with `a` and `b` as opposite corners.
+    pub fn new(a: &Point3, b: &Point3, mat: Rc<dyn Material>) -> Self {
+        Cuboid {
+            bbox: Aabb::from_points(a, b),
+            mat,
+        }
+    }
+
+    /// Where the line through `r` enters and leaves the box, by the slab test of [`Aabb::hit`].
+    fn crossings(&self, r: &Ray) -> Option<[Crossing; 2]> {
+        let mut enter = Crossing {
+            t: Point::NEG_INFINITY,
+            axis: 0,
+            upper: false,
+        };
+        let mut exit = Crossing {
+            t: Point::INFINITY,
+            axis: 0,
+            upper: true,
+        };
+
+        for n in 0..3 {
+            let axis = self.bbox.axis(n);
+            let inverse = 1.0 / r.direction()[n];
+            let t0 = (axis.min - r.origin()[n]) * inverse;
+            let t1 = (axis.max - r.origin()[n]) * inverse;
+
+            // Rays along the axis enter at the bottom face
+            let rising = inverse > 0.0;
+            let (near, far) = if rising { (t0, t1) } else { (t1, t0) };
+            if near > enter.t {
+                enter = Crossing {
+                    t: near,
+                    axis: n,
+                    upper: !rising,
+                };
+            }
+            if far < exit.t {
+                exit = Crossing {
+                    t: far,
+                    axis: n,
+                    upper: rising,
+                };
+            }
+        }
+
+        (enter.t < exit.t && enter.t.is_finite() && exit.t.is_finite()).then_some([enter, exit])
+    }
+
+    /// Fills in `rec` for `r` crossing the box surface at `crossing`.
+    fn record(&self, r: &Ray, crossing: Crossing, rec: &mut Hit) {
+        let Crossing { t, axis, upper } = crossing;
+        let face = self.bbox.axis(axis);
+
+        // The point is put exactly on the face, leaving error only across it
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.p.points[axis] = if upper { face.max } else { face.min };
+        rec.error = gamma(5) * (r.origin().abs() + (t * *r.direction()).abs());
+        rec.error.points[axis] = 0.0;
+
+        let mut outward_normal = Vec3::new();
+        outward_normal.points[axis] = if upper { 1.0 } else { -1.0 };
+        rec.set_face_normal(r, &outward_normal);
+
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let coordinate = |n: usize| {
+            let extent = self.bbox.axis(n);
+            (rec.p[n] - extent.min) / extent.size()
+        };
+        (rec.u, rec.v) = (coordinate(u_axis), coordinate(v_axis));
+        rec.tangent = Vec3::new();
+        rec.tangent.points[u_axis] = 1.0;
+        rec.barycentric = None;
+        rec.mat = Some(self.mat.clone());
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        stats::primitive_test();
+        let Some(crossings) = self.crossings(r) else {
+            return false;
+        };
+
+        match crossings.into_iter().find(|c| ray_t.surrounds(c.t)) {
+            Some(crossing) => {
+                self.record(r, crossing, rec);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+impl Solid for Cuboid {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        stats::primitive_test();
+        let Some([enter, exit]) = self.crossings(r) else {
+            return Vec::new();
+        };
+
+        let mut span = Span {
+            enter: Hit::new(),
+            exit: Hit::new(),
+        };
+        self.record(r, enter, &mut span.enter);
+        self.record(r, exit, &mut span.exit);
+
+        vec![span]
+    }
+}
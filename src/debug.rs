@@ -0,0 +1,117 @@
+use crate::{
+    camera::Camera,
+    color::Color,
+    film::Film,
+    helpers::Generator,
+    hit::Hit,
+    integrator::{Integrator, Scene},
+    interval::Interval,
+    onb::Onb,
+    ray::Ray,
+    sampling::cosine_hemisphere,
+    spectrum::Channels,
+    stats::{self, RayKind},
+    vec3::Point,
+};
+
+/// What a [`DebugIntegrator`] shows at the first surface each camera ray hits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugMode {
+    /// Fraction of a cosine weighted hemisphere left open within `distance`.
+    AmbientOcclusion { distance: Point },
+    /// Shading normal facing the camera, mapped from `[-1, 1]` to `[0, 1]`.
+    Normals,
+    /// Distance along the view direction, white up close and black from `far` on.
+    Depth { far: Point },
+    /// Triangle edges thinner than `width` in barycentric terms, over a facing ratio shade.
+    Wireframe { width: Point },
+    /// Material color without any lighting.
+    Albedo,
+}
+
+/// Quick previews for laying out scenes, tracing no more than the camera ray and, for ambient
+/// occlusion, one shadow ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugIntegrator {
+    pub mode: DebugMode,
+}
+
+impl Integrator for DebugIntegrator {
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        _lambda: &mut C,
+        film: &mut Film,
+    ) {
+        let r = camera.get_ray(i, j);
+        let mut rec = Hit::new();
+        stats::ray(RayKind::Camera);
+
+        let color = if scene
+            .world
+            .hit(&r, Interval::from(0.0, Point::INFINITY), &mut rec)
+        {
+            self.shade(camera, scene, &r, &rec)
+        } else {
+            Color::new()
+        };
+
+        // Previews are plain RGB, spectral films store XYZ
+        let value = if camera.spectral {
+            color.to_xyz()
+        } else {
+            color
+        };
+        film.add_sample(i, j, value);
+    }
+}
+
+impl DebugIntegrator {
+    /// Creates a new [`DebugIntegrator`].
+    #[must_use]
+    pub const fn new(mode: DebugMode) -> Self {
+        DebugIntegrator { mode }
+    }
+
+    fn shade(&self, camera: &Camera, scene: &Scene, r: &Ray, rec: &Hit) -> Color {
+        match self.mode {
+            DebugMode::AmbientOcclusion { distance } => {
+                let local =
+                    cosine_hemisphere([Generator::random_point(), Generator::random_point()]);
+                let direction = Onb::new(&rec.normal).transform(&local);
+                stats::ray(RayKind::Shadow);
+
+                let occluded = scene.world.hit(
+                    &rec.spawn_ray(&direction),
+                    Interval::from(0.0, distance),
+                    &mut Hit::new(),
+                );
+                Color::from(if occluded { 0.0 } else { 1.0 })
+            }
+            DebugMode::Normals => (rec.normal + Color::from(1.0)) * 0.5,
+            DebugMode::Depth { far } => {
+                let depth = (rec.p - camera.center()).dot(&camera.forward());
+                Color::from((1.0 - depth / far).clamp(0.0, 1.0))
+            }
+            DebugMode::Wireframe { width } => {
+                let facing = rec.normal.dot(&r.direction().unit_vector()).abs();
+                let edge = rec
+                    .barycentric
+                    .is_some_and(|b| b.x().min(b.y()).min(b.z()) < width);
+
+                if edge {
+                    Color::new()
+                } else {
+                    Color::from(0.2 + 0.8 * facing)
+                }
+            }
+            DebugMode::Albedo => rec
+                .mat
+                .as_ref()
+                .map_or_else(Color::new, |mat| mat.albedo(rec)),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    color::Color,
+    error::Result,
+    helpers::degrees_to_radians,
+    reader::HdrImage,
+    sampling::{uniform_sphere, Distribution2D, UNIFORM_SPHERE_PDF},
+    vec3::{consts::PI, Point, Vec3},
+};
+
+/// Direction sampled towards the environment, along with the radiance arriving from it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EnvSample {
+    pub direction: Vec3,
+    pub radiance: Color,
+    pub pdf: Point,
+}
+
+/// Radiance arriving from infinitely far away, seen by rays that escape the scene.
+pub trait Environment {
+    fn radiance(&self, direction: &Vec3) -> Color;
+
+    /// Samples a direction proportionally to the incoming light, `None` if the environment
+    /// can't be sampled explicitly.
+    fn sample(&self, _u: [Point; 2]) -> Option<EnvSample> {
+        None
+    }
+
+    /// Solid angle density with which [`Environment::sample`] returns `direction`.
+    fn pdf(&self, _direction: &Vec3) -> Point {
+        0.0
+    }
+}
+
+/// Vertical blend between two colors, the classic sky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gradient {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient {
+            bottom: Color::WHITE,
+            top: Color::BLEND,
+        }
+    }
+}
+
+impl Environment for Gradient {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let unit_direction = direction.unit_vector();
+        let a = 0.5 * (unit_direction.y() + 1.0);
+
+        (1.0 - a) * self.bottom + a * self.top
+    }
+
+    fn sample(&self, u: [Point; 2]) -> Option<EnvSample> {
+        let direction = uniform_sphere(u);
+
+        Some(EnvSample {
+            direction,
+            radiance: self.radiance(&direction),
+            pdf: UNIFORM_SPHERE_PDF,
+        })
+    }
+
+    fn pdf(&self, _direction: &Vec3) -> Point {
+        UNIFORM_SPHERE_PDF
+    }
+}
+
+/// Equirectangular environment map, importance sampled by luminance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    /// Rotation around the vertical axis, in degrees.
+    pub rotation: Point,
+    pub intensity: Point,
+    image: HdrImage,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn new(image: HdrImage) -> Self {
+        // Rows near the poles cover less solid angle, weight them accordingly
+        let func: Vec<Point> = image
+            .pixels
+            .chunks_exact(image.width)
+            .enumerate()
+            .flat_map(|(y, row)| {
+                let sin_theta = (PI * (y as Point + 0.5) / image.height as Point).sin();
+                row.iter().map(move |c| c.luminance() * sin_theta)
+            })
+            .collect();
+
+        EnvironmentMap {
+            rotation: 0.0,
+            intensity: 1.0,
+            distribution: Distribution2D::new(&func, image.width, image.height),
+            image,
+        }
+    }
+
+    /// Loads an `.hdr` or `.pfm` environment map.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read or decoded.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Self::new(HdrImage::open(path)?))
+    }
+
+    /// Maps a direction to equirectangular coordinates in `[0, 1)^2`.
+    fn direction_to_uv(&self, direction: &Vec3) -> [Point; 2] {
+        let d = direction.unit_vector();
+        let theta = d.y().clamp(-1.0, 1.0).acos();
+        let phi = d.z().atan2(d.x()) + degrees_to_radians(self.rotation);
+
+        [(phi / (2.0 * PI)).rem_euclid(1.0), theta / PI]
+    }
+
+    fn uv_to_direction(&self, uv: [Point; 2]) -> Vec3 {
+        let theta = uv[1] * PI;
+        let phi = uv[0] * 2.0 * PI - degrees_to_radians(self.rotation);
+
+        Vec3::from_scalars(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn lookup(&self, uv: [Point; 2]) -> Color {
+        let x = ((uv[0] * self.image.width as Point) as usize).min(self.image.width - 1);
+        let y = ((uv[1] * self.image.height as Point) as usize).min(self.image.height - 1);
+
+        self.image.get(x, y) * self.intensity
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        self.lookup(self.direction_to_uv(direction))
+    }
+
+    fn sample(&self, u: [Point; 2]) -> Option<EnvSample> {
+        let (uv, map_pdf) = self.distribution.sample_continuous(u);
+        let sin_theta = (uv[1] * PI).sin();
+        if map_pdf == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+
+        Some(EnvSample {
+            direction: self.uv_to_direction(uv),
+            radiance: self.lookup(uv),
+            pdf: map_pdf / (2.0 * PI * PI * sin_theta),
+        })
+    }
+
+    fn pdf(&self, direction: &Vec3) -> Point {
+        let uv = self.direction_to_uv(direction);
+        let sin_theta = (uv[1] * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+    }
+}
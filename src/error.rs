@@ -0,0 +1,58 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Everything that can go wrong reading a scene, rendering it or writing the result.
+#[derive(Debug)]
+pub enum Error {
+    /// A file that couldn't be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// A file whose contents couldn't be understood.
+    Decode(String),
+    /// An image that couldn't be put into its output format.
+    Encode(String),
+    /// A scene description that doesn't make sense.
+    Scene(String),
+    /// A setting outside the range it has to be in.
+    InvalidParameter(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Decode(message) => write!(f, "could not decode: {message}"),
+            Error::Encode(message) => write!(f, "could not encode: {message}"),
+            Error::Scene(message) => write!(f, "invalid scene: {message}"),
+            Error::InvalidParameter(message) => write!(f, "invalid parameter: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Error for `source` failing to read or write the file at `path`.
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.as_ref().to_owned(),
+            source,
+        }
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Error::Encode("formatting failed".to_owned())
+    }
+}
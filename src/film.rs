@@ -0,0 +1,87 @@
+use crate::{
+    color::Color,
+    error::Result,
+    image::Image,
+    vec3::{Point, Scalar, Vec3},
+};
+
+/// Accumulates the samples taken through each pixel, along with the splats that light paths
+/// leave on whichever pixel they reach. Values are linear sRGB, or CIE XYZ for spectral renders.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Vec3>,
+    splats: Vec<Vec3>,
+}
+
+impl Film {
+    /// Creates a new black [`Film`].
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = width as usize * height as usize;
+
+        Film {
+            width,
+            height,
+            pixels: vec![Vec3::new(); size],
+            splats: vec![Vec3::new(); size],
+        }
+    }
+
+    pub fn add_sample(&mut self, i: u32, j: u32, value: Vec3) {
+        let index = self.index(i, j);
+        self.pixels[index] += value;
+    }
+
+    /// Adds `value` to the pixel containing the continuous raster position `(x, y)`.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn add_splat(&mut self, x: Point, y: Point, value: Vec3) {
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+
+        let (i, j) = (x as u32, y as u32);
+        if i < self.width && j < self.height {
+            let index = self.index(i, j);
+            self.splats[index] += value;
+        }
+    }
+
+    /// Averages the samples and splats over `samples_per_pixel`, converting from XYZ when `xyz`
+    /// is set. Every pixel is weighted by its sample count.
+    #[must_use]
+    pub fn resolve(&self, samples_per_pixel: u32, xyz: bool) -> Image {
+        let scale = 1.0 / samples_per_pixel.to_point();
+        let mut image = Image::new(self.width, self.height);
+
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let index = self.index(i, j);
+                let value = (self.pixels[index] + self.splats[index]) * scale;
+                let color = if xyz {
+                    Color::from_xyz(value.x(), value.y(), value.z())
+                } else {
+                    value
+                };
+                image.set(i, j, color);
+                image.set_weight(i, j, samples_per_pixel.to_point());
+            }
+        }
+
+        image
+    }
+
+    /// Writes the image averaged over `samples_per_pixel`, converting from XYZ when `xyz` is set.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file or its directory can't be written.
+    pub fn write(&self, path: &str, samples_per_pixel: u32, xyz: bool) -> Result<()> {
+        self.resolve(samples_per_pixel, xyz).write(path)
+    }
+
+    fn index(&self, i: u32, j: u32) -> usize {
+        j as usize * self.width as usize + i as usize
+    }
+}
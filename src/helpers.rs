@@ -1,46 +1,63 @@
-use std::f64::consts::PI;
+use std::cell::RefCell;
 
-use rand::{distributions::Uniform, prelude::Distribution, thread_rng, Rng};
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
 
-use crate::{interval::Interval, vec3::Point};
+use crate::{
+    interval::Interval,
+    vec3::{consts::PI, Point},
+};
 
-#[allow(dead_code)]
+#[must_use]
 pub fn degrees_to_radians(degrees: Point) -> Point {
     degrees * PI / 180.0
 }
 
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
 #[derive(Default)]
 pub struct Generator {}
 
 impl Generator {
+    /// Restarts the numbers drawn on this thread from `seed`, so what follows can be repeated.
+    pub fn seed(seed: u64) {
+        RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    }
+
     #[inline]
+    #[must_use]
     pub fn random_point() -> Point {
-        thread_rng().gen_range(0.0..1.0)
+        RNG.with(|rng| rng.borrow_mut().gen_range(0.0..1.0))
     }
 
     #[inline]
+    #[must_use]
     pub fn random_points(amount: usize) -> Vec<Point> {
         let interval = Interval::from(0, 1);
         Self::random_points_interval(interval, amount)
     }
 
-    #[allow(dead_code)]
     #[inline]
+    #[must_use]
     pub fn random_point_interval(interval: Interval) -> Point {
-        thread_rng().clone().gen_range(interval.min..interval.max)
+        RNG.with(|rng| rng.borrow_mut().gen_range(interval.min..interval.max))
     }
 
     #[inline]
+    #[must_use]
     pub fn random_points_interval(interval: Interval, amount: usize) -> Vec<Point> {
         let between = Uniform::from(interval.min..interval.max);
 
-        let mut rng = thread_rng();
-        let mut buf: Vec<Point> = Vec::with_capacity(amount);
+        RNG.with(|rng| {
+            let mut rng = rng.borrow_mut();
+            let mut buf: Vec<Point> = Vec::with_capacity(amount);
 
-        for _ in 0..amount {
-            buf.push(between.sample(&mut rng));
-        }
+            for _ in 0..amount {
+                buf.push(between.sample(&mut *rng));
+            }
 
-        buf
+            buf
+        })
     }
 }
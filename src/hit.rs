@@ -1,26 +1,54 @@
-use std::rc::Rc;
+use std::{cmp::Ordering, rc::Rc};
 
 use crate::{
+    aabb::Aabb,
     interval::Interval,
+    material::{Material, Transport},
+    onb::Onb,
+    packet::{Mask, RayPacket, LANES},
     ray::Ray,
     vec3::{Point, Point3, Vec3},
 };
 
-#[derive(Debug, Default, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Default, Clone)]
 pub struct Hit {
     pub p: Point3,
+    /// Bound on the rounding error in each coordinate of `p`, which rays leaving the surface
+    /// are started clear of.
+    pub error: Vec3,
     pub normal: Vec3,
+    /// Direction of increasing `u` along the surface, zero where it isn't defined.
+    pub tangent: Vec3,
+    pub mat: Option<Rc<dyn Material>>,
     pub t: Point,
+    /// Surface coordinates of the hit, used for texture lookups.
+    pub u: Point,
+    pub v: Point,
+    /// Weights of the vertices when a triangle was hit, for wireframe display.
+    pub barycentric: Option<Vec3>,
     pub front_face: bool,
+    /// Wavelength in nanometers the path is traced at, for materials with dispersion.
+    pub wavelength: Option<Point>,
+    /// What the path being traced carries, importance when it started at a light.
+    pub transport: Transport,
 }
 
 impl Hit {
+    #[must_use]
     pub const fn new() -> Self {
         Hit {
             p: Point3::new(),
+            error: Vec3::new(),
             normal: Vec3::new(),
+            tangent: Vec3::new(),
+            mat: None,
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            barycentric: None,
             front_face: false,
+            wavelength: None,
+            transport: Transport::Radiance,
         }
     }
 
@@ -32,23 +60,88 @@ impl Hit {
             -(*outward_normal)
         };
     }
+
+    /// Ray leaving the hit point along `direction`, started clear of the surface.
+    #[must_use]
+    pub fn spawn_ray(&self, direction: &Vec3) -> Ray {
+        Ray::spawn(&self.p, &self.error, &self.normal, direction)
+    }
+
+    /// Local frame around the shading normal, used to evaluate materials.
+    #[must_use]
+    pub fn shading_frame(&self) -> Onb {
+        Onb::from_tangent(&self.normal, &self.tangent)
+    }
 }
 
 pub trait Hittable {
     fn hit(&self, _r: &Ray, _ray_t: Interval, _rec: &mut Hit) -> bool {
         false
     }
+
+    /// Intersects the active rays of `packet` with the object. Lanes that hit have their record
+    /// filled in and their interval closed at the hit, and are returned. Objects without a
+    /// packet test of their own trace the lanes one at a time.
+    ///
+    /// The integrators trace one ray at a time and never call this. It is for callers tracing
+    /// coherent rays of their own, like the traversal benchmark.
+    fn hit_packet(&self, packet: &mut RayPacket, recs: &mut [Hit; LANES]) -> Mask {
+        let mut hits = 0;
+        for lane in packet.lanes() {
+            if self.hit(&packet.ray(lane), packet.interval(lane), &mut recs[lane]) {
+                packet.t_max[lane] = recs[lane].t;
+                hits |= 1 << lane;
+            }
+        }
+
+        hits
+    }
+
+    /// Box enclosing everything the object can be hit at.
+    fn bounding_box(&self) -> Aabb {
+        Aabb::EMPTY
+    }
+}
+
+impl PartialEq for Hit {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+/// Compares field by field like a derive would, the material by its address.
+impl PartialOrd for Hit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let key = |rec: &Self| {
+            (
+                rec.p,
+                rec.error,
+                rec.normal,
+                rec.tangent,
+                rec.mat.as_ref().map(|mat| Rc::as_ptr(mat).cast::<()>()),
+                rec.t,
+                rec.u,
+                rec.v,
+                rec.barycentric,
+                rec.front_face,
+                rec.wavelength,
+                rec.transport,
+            )
+        };
+        key(self).partial_cmp(&key(other))
+    }
 }
 
 impl Hittable for Hit {}
 
 #[derive(Default, Debug, Clone, PartialEq, PartialOrd)]
-pub struct HitList<T: Hittable> {
+pub struct HitList<T: Hittable + ?Sized> {
     pub rec: Hit,
     pub objects: Vec<Rc<T>>,
 }
 
-impl<T: Hittable> HitList<T> {
+impl<T: Hittable + ?Sized> HitList<T> {
+    #[must_use]
     pub const fn new() -> Self {
         HitList {
             rec: Hit::new(),
@@ -56,7 +149,6 @@ impl<T: Hittable> HitList<T> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn from(object: Rc<T>) -> Self {
         HitList {
             rec: Hit::new(),
@@ -64,7 +156,6 @@ impl<T: Hittable> HitList<T> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.objects.clear();
     }
@@ -74,7 +165,7 @@ impl<T: Hittable> HitList<T> {
     }
 }
 
-impl<T: Hittable> Hittable for HitList<T> {
+impl<T: Hittable + ?Sized> Hittable for HitList<T> {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
@@ -90,4 +181,16 @@ impl<T: Hittable> Hittable for HitList<T> {
 
         hit_anything
     }
+
+    fn hit_packet(&self, packet: &mut RayPacket, recs: &mut [Hit; LANES]) -> Mask {
+        self.objects
+            .iter()
+            .fold(0, |hits, obj| hits | obj.hit_packet(packet, recs))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::EMPTY, |acc, obj| {
+            Aabb::from_boxes(&acc, &obj.bounding_box())
+        })
+    }
 }
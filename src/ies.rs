@@ -0,0 +1,200 @@
+use std::fs;
+
+use crate::{
+    error::{Error, Result},
+    vec3::Point,
+};
+
+/// Photometric web read from an IESNA LM-63 file, normalized so its brightest direction is 1.
+///
+/// Angles follow type C photometry: vertical angles are measured from the light's axis and
+/// horizontal angles around it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IesProfile {
+    vertical: Vec<Point>,
+    horizontal: Vec<Point>,
+    /// Candela values, one row of vertical samples per horizontal angle.
+    candela: Vec<Point>,
+}
+
+impl IesProfile {
+    /// Reads an IES LM-63 photometric file.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read or isn't a valid profile.
+    pub fn open(path: &str) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path).map_err(|source| Error::io(path, source))?)
+    }
+
+    /// Parses the text of an IES LM-63 photometric file.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the text isn't a valid profile.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find(|line| line.starts_with("TILT="))
+            .ok_or_else(|| invalid("missing TILT line"))?;
+
+        let mut numbers = lines.flat_map(str::split_whitespace).map(|token| {
+            token
+                .parse::<Point>()
+                .map_err(|_| invalid("invalid number"))
+        });
+        let mut next = || {
+            numbers
+                .next()
+                .unwrap_or_else(|| Err(invalid("unexpected end of file")))
+        };
+
+        if tilt == "TILT=INCLUDE" {
+            // Lamp to luminaire geometry, then the tilt angles and their multipliers
+            next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        } else if tilt != "TILT=NONE" {
+            return Err(invalid("external TILT files are not supported"));
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let n_vertical = next()? as usize;
+        let n_horizontal = next()? as usize;
+        // Photometric type, units and luminous opening dimensions, then ballast and wattage
+        for _ in 0..8 {
+            next()?;
+        }
+
+        if n_vertical == 0 || n_horizontal == 0 {
+            return Err(invalid("profile has no angles"));
+        }
+
+        let vertical = (0..n_vertical)
+            .map(|_| next())
+            .collect::<Result<Vec<_>>>()?;
+        let horizontal = (0..n_horizontal)
+            .map(|_| next())
+            .collect::<Result<Vec<_>>>()?;
+        let mut candela = (0..n_vertical * n_horizontal)
+            .map(|_| next().map(|c| c * multiplier))
+            .collect::<Result<Vec<_>>>()?;
+
+        let max = candela.iter().copied().fold(0.0, Point::max);
+        if max > 0.0 {
+            for c in &mut candela {
+                *c /= max;
+            }
+        }
+
+        Ok(IesProfile {
+            vertical,
+            horizontal,
+            candela,
+        })
+    }
+
+    /// Relative intensity towards the vertical angle `theta` and horizontal angle `phi`, in degrees.
+    #[must_use]
+    pub fn eval(&self, theta: Point, phi: Point) -> Point {
+        let Some((v0, v1, tv)) = Self::bracket(&self.vertical, theta) else {
+            return 0.0;
+        };
+
+        // Profiles only store the part of the web their symmetry doesn't repeat
+        let first = self.horizontal[0];
+        let last = self.horizontal[self.horizontal.len() - 1];
+        let is = |angle: Point, degrees: Point| (angle - degrees).abs() < 1e-3;
+        let mut phi = phi.rem_euclid(360.0);
+        if is(first, 0.0) && (is(last, 90.0) || is(last, 180.0)) && phi > 180.0 {
+            // Symmetric about the 0-180 plane
+            phi = 360.0 - phi;
+        }
+        if is(first, 0.0) && is(last, 90.0) && phi > 90.0 {
+            // Symmetric in each quadrant
+            phi = 180.0 - phi;
+        }
+        if is(first, 90.0) && is(last, 270.0) && !(90.0..=270.0).contains(&phi) {
+            // Symmetric about the 90-270 plane
+            phi = (180.0 - phi).rem_euclid(360.0);
+        }
+
+        // Directions the profile doesn't describe get no light
+        let Some((h0, h1, th)) = Self::bracket(&self.horizontal, phi) else {
+            return 0.0;
+        };
+
+        let n = self.vertical.len();
+        let at = |h: usize, v: usize| self.candela[h * n + v];
+        let row0 = at(h0, v0) * (1.0 - tv) + at(h0, v1) * tv;
+        let row1 = at(h1, v0) * (1.0 - tv) + at(h1, v1) * tv;
+
+        row0 * (1.0 - th) + row1 * th
+    }
+
+    /// Indices of the samples around `x` and the interpolation factor between them.
+    fn bracket(angles: &[Point], x: Point) -> Option<(usize, usize, Point)> {
+        if angles.len() == 1 {
+            return Some((0, 0, 0.0));
+        }
+        if x < angles[0] || x > angles[angles.len() - 1] {
+            return None;
+        }
+
+        let i = angles
+            .partition_point(|&a| a <= x)
+            .clamp(1, angles.len() - 1);
+        let (a0, a1) = (angles[i - 1], angles[i]);
+        let t = if a1 > a0 { (x - a0) / (a1 - a0) } else { 0.0 };
+
+        Some((i - 1, i, t))
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Decode(message.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A profile with a single vertical angle and the given horizontal angles and candelas.
+    fn profile(horizontal: &[Point], candela: &[Point]) -> IesProfile {
+        IesProfile {
+            vertical: vec![0.0],
+            horizontal: horizontal.to_vec(),
+            candela: candela.to_vec(),
+        }
+    }
+
+    #[test]
+    fn symmetric_profiles_mirror_the_stored_planes() {
+        let quadrant = profile(&[0.0, 90.0], &[1.0, 0.5]);
+        for phi in [30.0, 150.0, 210.0, 330.0] {
+            assert!((quadrant.eval(0.0, phi) - 5.0 / 6.0).abs() < 1e-6, "{phi}");
+        }
+
+        let bilateral = profile(&[0.0, 90.0, 180.0], &[1.0, 0.5, 0.0]);
+        assert!((bilateral.eval(0.0, 135.0) - 0.25).abs() < 1e-6);
+        assert!((bilateral.eval(0.0, 225.0) - 0.25).abs() < 1e-6);
+
+        let back = profile(&[90.0, 180.0, 270.0], &[1.0, 0.5, 0.0]);
+        assert!((back.eval(0.0, 45.0) - 0.75).abs() < 1e-6);
+        assert!((back.eval(0.0, 315.0) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn angles_outside_the_profile_are_dark() {
+        let partial = profile(&[0.0, 45.0, 120.0], &[1.0, 1.0, 1.0]);
+        assert!(partial.eval(0.0, 200.0).abs() < 1e-6);
+        assert!((partial.eval(0.0, 60.0) - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{
+    color::Color,
+    error::{Error, Result},
+    reader::HdrImage,
+    vec3::{Point, Scalar},
+    writer::Writer,
+};
+
+/// One of the values stored for every pixel of an [`Image`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+/// Picture held in memory as linear sRGB with coverage, row by row from the top. Every pixel
+/// also carries the total weight of the samples that were averaged into it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Color>,
+    alpha: Vec<Point>,
+    weights: Vec<Point>,
+}
+
+impl Image {
+    /// Creates a new opaque black [`Image`], every pixel weighted once.
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = width as usize * height as usize;
+
+        Image {
+            width,
+            height,
+            pixels: vec![Color::new(); size],
+            alpha: vec![1.0; size],
+            weights: vec![1.0; size],
+        }
+    }
+
+    /// Creates an opaque [`Image`] from its pixels, row by row from the top.
+    ///
+    /// # Errors
+    ///
+    /// Fails when there isn't exactly one pixel for each position.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Color>) -> Result<Self> {
+        let size = width as usize * height as usize;
+        if pixels.len() != size {
+            return Err(Error::InvalidParameter(format!(
+                "a {width}x{height} image needs {size} pixels, got {}",
+                pixels.len()
+            )));
+        }
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+            alpha: vec![1.0; size],
+            weights: vec![1.0; size],
+        })
+    }
+
+    /// Loads a Radiance `.hdr`, a `.pfm` or a `.ppm` image.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read or decoded, or is too large to address.
+    pub fn open(path: &str) -> Result<Self> {
+        let image = HdrImage::open(path)?;
+        let dimension = |value: usize| {
+            u32::try_from(value).map_err(|_| Error::Decode(format!("{path} is too large")))
+        };
+
+        Self::from_pixels(
+            dimension(image.width)?,
+            dimension(image.height)?,
+            image.pixels,
+        )
+    }
+
+    #[must_use]
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.pixels[index] = color;
+    }
+
+    /// Fraction of the pixel covered, from transparent at 0 to opaque at 1.
+    #[must_use]
+    pub fn alpha(&self, x: u32, y: u32) -> Point {
+        self.alpha[self.index(x, y)]
+    }
+
+    pub fn set_alpha(&mut self, x: u32, y: u32, alpha: Point) {
+        let index = self.index(x, y);
+        self.alpha[index] = alpha;
+    }
+
+    /// Total weight of the samples averaged into the pixel.
+    #[must_use]
+    pub fn weight(&self, x: u32, y: u32) -> Point {
+        self.weights[self.index(x, y)]
+    }
+
+    pub fn set_weight(&mut self, x: u32, y: u32, weight: Point) {
+        let index = self.index(x, y);
+        self.weights[index] = weight;
+    }
+
+    /// Every pixel, row by row from the top.
+    #[must_use]
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    /// Writes the image to `path`, as a float map for `.pfm` and a gamma encoded PPM otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file or its directory can't be written.
+    pub fn write(&self, path: &str) -> Result<()> {
+        Writer::new(path).write(self)
+    }
+
+    /// The `width` by `height` region whose upper left corner is at `(x, y)`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the region isn't entirely inside the image.
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Image> {
+        let fits = |start: u32, size: u32, limit: u32| {
+            start.checked_add(size).is_some_and(|end| end <= limit)
+        };
+        if !fits(x, width, self.width) || !fits(y, height, self.height) {
+            return Err(Error::InvalidParameter(format!(
+                "a {width}x{height} crop at ({x}, {y}) doesn't fit in a {}x{} image",
+                self.width, self.height
+            )));
+        }
+
+        let mut cropped = Image::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                cropped.copy_from(i, j, self, self.index(x + i, y + j));
+            }
+        }
+
+        Ok(cropped)
+    }
+
+    /// Resamples the image to `width` by `height`, every new pixel averaging the area of the old
+    /// image it covers.
+    ///
+    /// # Errors
+    ///
+    /// Fails when either dimension is zero.
+    pub fn resize(&self, width: u32, height: u32) -> Result<Image> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidParameter(format!(
+                "can't resize to {width}x{height}"
+            )));
+        }
+
+        let columns: Vec<_> = (0..width)
+            .map(|i| footprint(i, self.width, width))
+            .collect();
+        let rows: Vec<_> = (0..height)
+            .map(|j| footprint(j, self.height, height))
+            .collect();
+
+        let mut resized = Image::new(width, height);
+        for (j, row) in (0..height).zip(&rows) {
+            for (i, column) in (0..width).zip(&columns) {
+                let mut color = Color::new();
+                let mut alpha = 0.0;
+                let mut weight = 0.0;
+
+                for &(y, wy) in row {
+                    for &(x, wx) in column {
+                        let index = self.index(x, y);
+                        let w = wx * wy;
+                        color += self.pixels[index] * w;
+                        alpha += self.alpha[index] * w;
+                        weight += self.weights[index] * w;
+                    }
+                }
+
+                let index = resized.index(i, j);
+                resized.pixels[index] = color;
+                resized.alpha[index] = alpha;
+                resized.weights[index] = weight;
+            }
+        }
+
+        Ok(resized)
+    }
+
+    /// Mirrors the image left to right.
+    pub fn flip_horizontal(&mut self) {
+        let width = self.width as usize;
+        if width == 0 {
+            return;
+        }
+        for row in self.pixels.chunks_exact_mut(width) {
+            row.reverse();
+        }
+        for row in self.alpha.chunks_exact_mut(width) {
+            row.reverse();
+        }
+        for row in self.weights.chunks_exact_mut(width) {
+            row.reverse();
+        }
+    }
+
+    /// Mirrors the image top to bottom.
+    pub fn flip_vertical(&mut self) {
+        let width = self.width as usize;
+        if width == 0 {
+            return;
+        }
+        flip_rows(&mut self.pixels, width);
+        flip_rows(&mut self.alpha, width);
+        flip_rows(&mut self.weights, width);
+    }
+
+    /// Adds `other` on top of the image, summing colours and weights. Coverage adds up to fully
+    /// opaque, as in the plus compositing operator.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the images aren't the same size.
+    pub fn add(&mut self, other: &Image) -> Result<()> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(Error::InvalidParameter(format!(
+                "can't add a {}x{} image to a {}x{} one",
+                other.width, other.height, self.width, self.height
+            )));
+        }
+
+        for (pixel, value) in self.pixels.iter_mut().zip(&other.pixels) {
+            *pixel += *value;
+        }
+        for (alpha, value) in self.alpha.iter_mut().zip(&other.alpha) {
+            *alpha = (*alpha + value).min(1.0);
+        }
+        for (weight, value) in self.weights.iter_mut().zip(&other.weights) {
+            *weight += value;
+        }
+
+        Ok(())
+    }
+
+    /// Multiplies every colour by `factor`, leaving coverage and weights alone.
+    pub fn scale(&mut self, factor: Point) {
+        for pixel in &mut self.pixels {
+            *pixel *= factor;
+        }
+    }
+
+    /// Greyscale image of one channel, with the coverage and weights of this one.
+    #[must_use]
+    pub fn channel(&self, channel: Channel) -> Image {
+        self.map(|color, alpha| match channel {
+            Channel::Red => color.x(),
+            Channel::Green => color.y(),
+            Channel::Blue => color.z(),
+            Channel::Alpha => alpha,
+        })
+    }
+
+    /// Greyscale image of the relative luminance, with the coverage and weights of this one.
+    #[must_use]
+    pub fn luminance(&self) -> Image {
+        self.map(|color, _| color.luminance())
+    }
+
+    fn map(&self, value: impl Fn(&Color, Point) -> Point) -> Image {
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(&self.alpha)
+            .map(|(color, alpha)| Color::from(value(color, *alpha)))
+            .collect();
+
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alpha: self.alpha.clone(),
+            weights: self.weights.clone(),
+        }
+    }
+
+    fn copy_from(&mut self, x: u32, y: u32, other: &Image, index: usize) {
+        let to = self.index(x, y);
+        self.pixels[to] = other.pixels[index];
+        self.alpha[to] = other.alpha[index];
+        self.weights[to] = other.weights[index];
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+/// Old pixels along one axis covered by new pixel `k` when `from` pixels become `to`, each with
+/// the fraction of the new pixel it makes up.
+fn footprint(k: u32, from: u32, to: u32) -> Vec<(u32, Point)> {
+    let scale = from.to_point() / to.to_point();
+    let start = k.to_point() * scale;
+    let end = start + scale;
+
+    (0..from)
+        .filter_map(|p| {
+            let overlap = end.min(p.to_point() + 1.0) - start.max(p.to_point());
+            (overlap > 0.0).then_some((p, overlap / scale))
+        })
+        .collect()
+}
+
+fn flip_rows<T>(values: &mut [T], width: usize) {
+    let height = values.len() / width;
+    for j in 0..height / 2 {
+        let (top, bottom) = values.split_at_mut((height - 1 - j) * width);
+        top[j * width..(j + 1) * width].swap_with_slice(&mut bottom[..width]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> Image {
+        let pixels = (0..height)
+            .flat_map(|j| (0..width).map(move |i| Color::from_scalars(i, j, 1)))
+            .collect();
+        Image::from_pixels(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn crop_keeps_the_region() {
+        let image = gradient(4, 3);
+        let cropped = image.crop(1, 1, 2, 2).unwrap();
+
+        assert_eq!(cropped.get(0, 0), image.get(1, 1));
+        assert_eq!(cropped.get(1, 1), image.get(2, 2));
+        assert!(image.crop(3, 0, 2, 1).is_err());
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn resize_preserves_the_mean() {
+        let image = gradient(6, 4);
+        let resized = image.resize(4, 3).unwrap();
+
+        let mean = |image: &Image| {
+            image.pixels().iter().fold(Color::new(), |acc, p| acc + *p)
+                / image.pixels().len() as Point
+        };
+        let difference = mean(&image) - mean(&resized);
+        assert!(difference.lenght() < 1e-12);
+    }
+
+    #[test]
+    fn flips_are_their_own_inverse() {
+        let image = gradient(3, 5);
+        let mut flipped = image.clone();
+
+        flipped.flip_vertical();
+        assert_eq!(flipped.get(2, 0), image.get(2, 4));
+        flipped.flip_horizontal();
+        assert_eq!(flipped.get(0, 0), image.get(2, 4));
+
+        flipped.flip_vertical();
+        flipped.flip_horizontal();
+        assert_eq!(flipped, image);
+    }
+
+    #[test]
+    fn pfm_round_trips() {
+        let image = gradient(3, 2);
+        let decoded = HdrImage::decode_pfm(&Writer::encode_pfm(&image)).unwrap();
+
+        assert_eq!((decoded.width, decoded.height), (3, 2));
+        assert_eq!(decoded.pixels, image.pixels());
+    }
+}
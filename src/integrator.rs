@@ -0,0 +1,109 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    camera::Camera,
+    environment::Environment,
+    error::Result,
+    film::Film,
+    hit::Hittable,
+    light::{EmissionSample, Light, LightList},
+    onb::Onb,
+    ray::Ray,
+    sampling::uniform_disk_concentric,
+    spectrum::Channels,
+    vec3::{consts::PI, Point},
+};
+
+/// Everything an integrator needs to know about the scene besides the camera.
+pub struct Scene<'a> {
+    pub world: &'a dyn Hittable,
+    pub lights: &'a LightList,
+    pub environment: &'a dyn Environment,
+    /// Bounds of the world, used to emit light from infinitely far away.
+    pub bounds: Aabb,
+}
+
+impl<'a> Scene<'a> {
+    /// Creates a new [`Scene`].
+    pub fn new(
+        world: &'a dyn Hittable,
+        lights: &'a LightList,
+        environment: &'a dyn Environment,
+    ) -> Self {
+        Scene {
+            world,
+            lights,
+            environment,
+            bounds: world.bounding_box(),
+        }
+    }
+
+    /// Probability of picking any one light, the environment included.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn light_choice_pdf(&self) -> Point {
+        1.0 / (self.lights.lights.len() + 1) as Point
+    }
+
+    /// Picks a light uniformly, or the environment when `None` is returned.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    #[must_use]
+    pub fn pick_light(&self, u: Point) -> Option<&Rc<dyn Light>> {
+        let count = self.lights.lights.len() + 1;
+        let index = ((u * count as Point) as usize).min(count - 1);
+        self.lights.lights.get(index)
+    }
+
+    /// Samples a ray from the environment into the scene, starting on a disk that covers it.
+    #[must_use]
+    pub fn environment_le(&self, u: [Point; 2], u_disk: [Point; 2]) -> Option<EmissionSample> {
+        let sample = self.environment.sample(u)?;
+        let (center, radius) = self.bounds.bounding_sphere();
+        if radius == 0.0 {
+            return None;
+        }
+
+        let frame = Onb::new(&sample.direction);
+        let [x, y] = uniform_disk_concentric(u_disk);
+        let origin = center + radius * (sample.direction + x * frame.u() + y * frame.v());
+
+        Some(EmissionSample {
+            ray: Ray::new(origin, -sample.direction),
+            radiance: sample.radiance,
+            pdf_position: 1.0 / (PI * radius * radius),
+            pdf_direction: sample.pdf,
+        })
+    }
+}
+
+/// Light transport algorithm estimating the image one pixel sample at a time.
+pub trait Integrator {
+    /// Called before every pass over the image, each pass taking one sample per pixel.
+    fn begin_pass(&mut self, _camera: &Camera, _scene: &Scene) {}
+
+    /// Traces one sample through pixel `(i, j)`, adding its estimate and any splats on other
+    /// pixels to `film`.
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        lambda: &mut C,
+        film: &mut Film,
+    );
+
+    /// Called once every pixel has been sampled, before `film` is written.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the integrator can't complete the image.
+    fn finish(&mut self, _camera: &Camera, _film: &mut Film) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::vec3::Point;
+use crate::vec3::{Point, Scalar};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Interval {
@@ -14,6 +14,7 @@ impl Interval {
     };
 
     /// Creates a new [`Interval`] with an empty range.
+    #[must_use]
     pub const fn new() -> Self {
         Interval {
             min: Point::INFINITY,
@@ -24,26 +25,49 @@ impl Interval {
     /// .Creates a new [`Interval`] with the range provided.
     pub fn from<T>(min: T, max: T) -> Self
     where
-        T: Into<Point> + Copy,
+        T: Scalar,
     {
         Interval {
-            min: min.into(),
-            max: max.into(),
+            min: min.to_point(),
+            max: max.to_point(),
         }
     }
 
+    /// Creates a new [`Interval`] tightly enclosing both `a` and `b`.
+    #[must_use]
+    pub const fn enclose(a: &Interval, b: &Interval) -> Self {
+        Interval {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
+
+    /// Creates a new [`Interval`] covering what `a` and `b` have in common, empty when they
+    /// don't overlap.
+    #[must_use]
+    pub const fn overlap(a: &Interval, b: &Interval) -> Self {
+        Interval {
+            min: a.min.max(b.min),
+            max: a.max.min(b.max),
+        }
+    }
+
+    #[must_use]
     pub const fn size(&self) -> Point {
         self.max - self.min
     }
 
+    #[must_use]
     pub const fn contains(&self, x: Point) -> bool {
         self.min <= x && x <= self.max
     }
 
+    #[must_use]
     pub const fn surrounds(&self, x: Point) -> bool {
         self.min < x && x < self.max
     }
 
+    #[must_use]
     pub const fn clamp(&self, x: Point) -> Point {
         if x < self.min {
             self.min
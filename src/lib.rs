@@ -0,0 +1,137 @@
+//! Physically based renderer: path tracing and the bidirectional, photon and Metropolis
+//! integrators built on it, with spectral rendering, image based lighting and the scene
+//! primitives they trace.
+//!
+//! The types most programs need are re-exported here. Build a [`Camera`], add objects to a
+//! [`HitList`] and render it to an in-memory [`Image`] or straight to disk. The rest of the
+//! renderer is reachable through [`internal`], without any promise of stability.
+
+// Constants are written to double precision and round to the nearest single, and in single
+// precision a few small types fall under the size clippy passes by value
+#![cfg_attr(
+    feature = "f32",
+    allow(
+        clippy::excessive_precision,
+        clippy::trivially_copy_pass_by_ref,
+        clippy::unnecessary_cast
+    )
+)]
+
+mod aabb;
+mod bdpt;
+mod bvh;
+mod camera;
+mod color;
+mod compare;
+mod csg;
+mod cuboid;
+mod debug;
+mod environment;
+mod error;
+mod film;
+mod helpers;
+mod hit;
+mod ies;
+mod image;
+mod integrator;
+mod interval;
+mod light;
+mod material;
+mod microfacet;
+mod mlt;
+mod onb;
+mod packet;
+mod path;
+mod photon;
+mod principled;
+mod quadric;
+mod ray;
+mod reader;
+mod sampler;
+mod sampling;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+mod sky;
+mod spectrum;
+mod sphere;
+mod sppm;
+mod stats;
+mod texture;
+mod torus;
+mod triangle;
+// The vector tests compare exact values, written before the pedantic lints were enforced. Test
+// builds only, so the rest of the module is still linted in the normal build
+#[cfg_attr(test, allow(clippy::float_cmp, clippy::unreadable_literal))]
+mod vec3;
+mod writer;
+
+pub use camera::{Camera, CameraBuilder};
+pub use error::{Error, Result};
+pub use hit::{Hit, HitList, Hittable};
+pub use image::{Channel, Image};
+pub use interval::Interval;
+pub use ray::Ray;
+pub use sphere::Sphere;
+pub use vec3::{Point, Point3, Scalar, Vec3};
+pub use writer::Writer;
+
+/// Everything else the renderer is built from, one module per module of the crate, for the
+/// binary, the tests and the benchmarks.
+///
+/// Unstable: anything in here may change or go away in any release.
+pub mod internal {
+    macro_rules! reexport {
+        ($($module:ident),* $(,)?) => {
+            $(pub mod $module {
+                pub use crate::$module::*;
+            })*
+        };
+    }
+
+    reexport!(
+        aabb,
+        bdpt,
+        bvh,
+        camera,
+        color,
+        compare,
+        csg,
+        cuboid,
+        debug,
+        environment,
+        error,
+        film,
+        helpers,
+        hit,
+        ies,
+        image,
+        integrator,
+        interval,
+        light,
+        material,
+        microfacet,
+        mlt,
+        onb,
+        packet,
+        path,
+        photon,
+        principled,
+        quadric,
+        ray,
+        reader,
+        sampler,
+        sampling,
+        sky,
+        spectrum,
+        sphere,
+        sppm,
+        stats,
+        texture,
+        torus,
+        triangle,
+        vec3,
+        writer
+    );
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    reexport!(simd);
+}
@@ -0,0 +1,272 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    ies::IesProfile,
+    onb::Onb,
+    ray::Ray,
+    sampling::{
+        uniform_cone, uniform_cone_pdf, uniform_disk_concentric, uniform_sphere, UNIFORM_SPHERE_PDF,
+    },
+    vec3::{consts::PI, Point, Point3, Vec3},
+};
+
+/// Light arriving at a point from a sampled position on a light source.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Unit direction from the receiving point towards the light.
+    pub direction: Vec3,
+    pub radiance: Color,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: Point,
+}
+
+/// Ray leaving a light source, sampled to start a path from the light.
+pub struct EmissionSample {
+    pub ray: Ray,
+    /// Radiance for area emitters, intensity or irradiance for the delta lights.
+    pub radiance: Color,
+    /// Density of the ray origin, by area or one for a fixed position.
+    pub pdf_position: Point,
+    /// Density of the ray direction, by solid angle or one for a fixed direction.
+    pub pdf_direction: Point,
+}
+
+/// Light source that isn't part of the geometry, described by a delta distribution.
+pub trait Light {
+    fn sample_li(&self, p: &Point3) -> Option<LightSample>;
+
+    /// Samples a ray leaving the light. Lights at infinity emit from a disk covering `bounds`.
+    fn sample_le(&self, u: [Point; 2], bounds: &Aabb) -> Option<EmissionSample>;
+
+    /// Position and direction densities with which [`Light::sample_le`] emits along `direction`.
+    fn pdf_le(&self, direction: &Vec3, bounds: &Aabb) -> (Point, Point);
+
+    /// True for lights infinitely far away, which only fix a direction.
+    fn is_infinite(&self) -> bool {
+        false
+    }
+}
+
+/// Light emitted equally in all directions from a single point.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+}
+
+impl PointLight {
+    /// Creates a new [`PointLight`] at `position` with radiant `intensity`.
+    #[must_use]
+    pub const fn new(position: Point3, intensity: Color) -> Self {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.lenght_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction: to_light.unit_vector(),
+            radiance: self.intensity / distance_squared,
+            distance: distance_squared.sqrt(),
+        })
+    }
+
+    fn sample_le(&self, u: [Point; 2], _bounds: &Aabb) -> Option<EmissionSample> {
+        Some(EmissionSample {
+            ray: Ray::new(self.position, uniform_sphere(u)),
+            radiance: self.intensity,
+            pdf_position: 1.0,
+            pdf_direction: UNIFORM_SPHERE_PDF,
+        })
+    }
+
+    fn pdf_le(&self, _direction: &Vec3, _bounds: &Aabb) -> (Point, Point) {
+        (0.0, UNIFORM_SPHERE_PDF)
+    }
+}
+
+/// Point light restricted to a cone, fading out between the falloff start and the total width.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotLight {
+    pub position: Point3,
+    pub intensity: Color,
+    frame: Onb,
+    cos_total_width: Point,
+    cos_falloff_start: Point,
+    profile: Option<Rc<IesProfile>>,
+}
+
+impl SpotLight {
+    /// Creates a new [`SpotLight`] pointing along `direction`, with the cone angles given in degrees.
+    #[must_use]
+    pub fn new(
+        position: Point3,
+        direction: &Vec3,
+        intensity: Color,
+        total_width: Point,
+        falloff_start: Point,
+    ) -> Self {
+        SpotLight {
+            position,
+            intensity,
+            frame: Onb::new(direction),
+            cos_total_width: total_width.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+            profile: None,
+        }
+    }
+
+    /// Shapes the emission with a photometric profile, its vertical angles measured from `direction`.
+    pub fn set_profile(&mut self, profile: Rc<IesProfile>) {
+        self.profile = Some(profile);
+    }
+
+    fn falloff(&self, w: &Vec3) -> Point {
+        let local = self.frame.to_local(w);
+        let cos_theta = local.z();
+
+        let mut falloff = smooth_step(cos_theta, self.cos_total_width, self.cos_falloff_start);
+        if let Some(profile) = &self.profile {
+            let theta = cos_theta.clamp(-1.0, 1.0).acos();
+            let phi = local.y().atan2(local.x()).rem_euclid(2.0 * PI);
+            falloff *= profile.eval(theta.to_degrees(), phi.to_degrees());
+        }
+
+        falloff
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.lenght_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+
+        let direction = to_light.unit_vector();
+        let falloff = self.falloff(&-direction);
+        if falloff == 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            radiance: self.intensity * falloff / distance_squared,
+            distance: distance_squared.sqrt(),
+        })
+    }
+
+    fn sample_le(&self, u: [Point; 2], _bounds: &Aabb) -> Option<EmissionSample> {
+        let direction = self.frame.transform(&uniform_cone(u, self.cos_total_width));
+
+        Some(EmissionSample {
+            ray: Ray::new(self.position, direction),
+            radiance: self.intensity * self.falloff(&direction),
+            pdf_position: 1.0,
+            pdf_direction: uniform_cone_pdf(self.cos_total_width),
+        })
+    }
+
+    fn pdf_le(&self, direction: &Vec3, _bounds: &Aabb) -> (Point, Point) {
+        let cos_theta = self.frame.w().dot(&direction.unit_vector());
+        if cos_theta >= self.cos_total_width {
+            (0.0, uniform_cone_pdf(self.cos_total_width))
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
+
+/// Light arriving from a single direction everywhere in the scene, like a distant sun.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction the light travels in.
+    pub direction: Vec3,
+    pub irradiance: Color,
+}
+
+impl DirectionalLight {
+    /// Creates a new [`DirectionalLight`] travelling along `direction`.
+    #[must_use]
+    pub fn new(direction: &Vec3, irradiance: Color) -> Self {
+        DirectionalLight {
+            direction: direction.unit_vector(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _p: &Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            radiance: self.irradiance,
+            distance: Point::INFINITY,
+        })
+    }
+
+    fn sample_le(&self, u: [Point; 2], bounds: &Aabb) -> Option<EmissionSample> {
+        let (center, radius) = bounds.bounding_sphere();
+        if radius == 0.0 {
+            return None;
+        }
+
+        // Start on a disk behind the scene, facing along the light
+        let frame = Onb::new(&self.direction);
+        let [x, y] = uniform_disk_concentric(u);
+        let origin = center + radius * (x * frame.u() + y * frame.v() - self.direction);
+
+        Some(EmissionSample {
+            ray: Ray::new(origin, self.direction),
+            radiance: self.irradiance,
+            pdf_position: 1.0 / (PI * radius * radius),
+            pdf_direction: 1.0,
+        })
+    }
+
+    fn pdf_le(&self, _direction: &Vec3, bounds: &Aabb) -> (Point, Point) {
+        let (_, radius) = bounds.bounding_sphere();
+        (1.0 / (PI * radius * radius), 0.0)
+    }
+
+    fn is_infinite(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct LightList {
+    pub lights: Vec<Rc<dyn Light>>,
+}
+
+impl LightList {
+    #[must_use]
+    pub const fn new() -> Self {
+        LightList { lights: Vec::new() }
+    }
+
+    pub fn add(&mut self, light: Rc<dyn Light>) {
+        self.lights.push(light);
+    }
+}
+
+fn smooth_step(x: Point, a: Point, b: Point) -> Point {
+    if a >= b {
+        return if x < a { 0.0 } else { 1.0 };
+    }
+
+    let t = ((x - a) / (b - a)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
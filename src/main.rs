@@ -1,37 +1,286 @@
-// My crates
-mod camera;
-mod color;
-mod helpers;
-mod hit;
-mod interval;
-mod ray;
-mod sphere;
-mod vec3;
-mod writer;
-
-use std::rc::Rc;
-
-use camera::Camera;
-use hit::HitList;
-use sphere::Sphere;
-use vec3::Point3;
+use std::{env, fs, process, rc::Rc, str::FromStr, time::Instant};
+
+use raytracer::internal::bdpt::Bdpt;
+use raytracer::internal::bvh::BvhNode;
+use raytracer::internal::color::Color;
+use raytracer::internal::compare::{self, Metric};
+use raytracer::internal::debug::{DebugIntegrator, DebugMode};
+use raytracer::internal::environment::{Environment, EnvironmentMap};
+use raytracer::internal::helpers::degrees_to_radians;
+use raytracer::internal::light::LightList;
+use raytracer::internal::material::{Conductor, Dielectric, Lambertian};
+use raytracer::internal::mlt::Mlt;
+use raytracer::internal::path::PathIntegrator;
+use raytracer::internal::photon::PhotonMapper;
+use raytracer::internal::principled::Principled;
+use raytracer::internal::sky::SunSky;
+use raytracer::internal::spectrum::Ior;
+use raytracer::internal::sppm::Sppm;
+use raytracer::internal::texture::SolidColor;
+use raytracer::internal::triangle::Triangle;
+use raytracer::{Camera, Error, HitList, Hittable, Image, Point, Point3, Result, Sphere, Vec3};
+
+const PATH_IMG: &str = "out_img/imagem.ppm";
+const PATH_DIFF: &str = "out_img/diff.ppm";
 
 fn main() {
-    let mut world = HitList::new();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let result = if args.first().is_some_and(|a| a == "compare") {
+        args.remove(0);
+        compare_images(args)
+    } else {
+        run(args).map(|()| true)
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("error: {error}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run(mut args: Vec<String>) -> Result<()> {
+    let start = Instant::now();
+    let world = world();
+    let lights = LightList::new();
+    let mut load = start.elapsed();
+
+    let start = Instant::now();
+    let world = BvhNode::new(world);
+    let build = start.elapsed();
+
+    let mut cam = Camera::builder()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(1280)
+        .samples_per_pixel(100)
+        .max_depth(10);
+
+    // `--spectral` traces wavelengths instead of RGB
+    if let Some(i) = args.iter().position(|a| a == "--spectral") {
+        args.remove(i);
+        cam = cam.spectral(true);
+    }
+
+    // `--heatmap` also writes where the intersection tests went
+    if let Some(i) = args.iter().position(|a| a == "--heatmap") {
+        args.remove(i);
+        cam = cam.heatmap(true);
+    }
+
+    // `--stats-json=<path>` saves the statistics printed after the render
+    let stats_json = args
+        .iter()
+        .position(|a| a.starts_with("--stats-json="))
+        .map(|i| args.remove(i)["--stats-json=".len()..].to_owned());
+
+    // `--bdpt`, `--photons`, `--sppm` or `--mlt` replace the path tracer, `--strategies=<depth>`
+    // also writes an image per BDPT strategy and `--seed=<n>` makes the render repeatable
+    let integrator = ["--bdpt", "--photons", "--sppm", "--mlt"]
+        .into_iter()
+        .find(|flag| args.iter().any(|a| a == flag));
+    args.retain(|a| Some(a.as_str()) != integrator);
+
+    let mut bdpt = Bdpt::new();
+    if let Some(i) = args.iter().position(|a| a.starts_with("--strategies=")) {
+        let depth = parse(&args.remove(i)["--strategies=".len()..], "strategy depth")?;
+        bdpt.debug_depth = Some(depth);
+    }
+
+    let debug = debug_integrator(&mut args)?;
+
+    if let Some(i) = args.iter().position(|a| a.starts_with("--seed=")) {
+        cam = cam.seed(parse(&args.remove(i)["--seed=".len()..], "seed")?);
+    }
+
+    let start = Instant::now();
+    if let Some(environment) = background(args)? {
+        cam = cam.environment(environment);
+    }
+    load += start.elapsed();
+
+    let cam = cam.build()?;
+    let mut stats = if let Some(mut debug) = debug {
+        cam.render(&mut debug, &world, &lights, PATH_IMG)
+    } else {
+        match integrator {
+            Some("--bdpt") => cam.render(&mut bdpt, &world, &lights, PATH_IMG),
+            Some("--photons") => cam.render(&mut PhotonMapper::new(), &world, &lights, PATH_IMG),
+            Some("--sppm") => cam.render(&mut Sppm::new(), &world, &lights, PATH_IMG),
+            Some("--mlt") => cam.render(&mut Mlt::new(), &world, &lights, PATH_IMG),
+            _ if bdpt.debug_depth.is_some() => cam.render(&mut bdpt, &world, &lights, PATH_IMG),
+            _ => cam.render(&mut PathIntegrator, &world, &lights, PATH_IMG),
+        }
+    }?;
+
+    stats.timings.load = load;
+    stats.timings.build = build;
+    println!("{stats}");
+    if let Some(path) = stats_json {
+        fs::write(&path, stats.to_json()).map_err(|source| Error::io(&path, source))?;
+    }
+
+    Ok(())
+}
+
+/// Spheres of a few materials on a large ground sphere, with a small tetrahedron in front.
+fn world() -> HitList<dyn Hittable> {
+    let diffuse = Rc::new(Lambertian::new(Color::from(0.5)));
+
+    let mut coated = Principled::new();
+    coated.base_color = Rc::new(SolidColor::new(Color::from_scalars(0.1, 0.2, 0.5)));
+    coated.roughness = Rc::new(SolidColor::new(Color::from(0.6)));
+    coated.clearcoat = Rc::new(SolidColor::new(Color::from(1.0)));
+
+    let mut world: HitList<dyn Hittable> = HitList::new();
     world.add(Rc::new(Sphere::new(
         &Point3::from_scalars(0, 0, -1),
         0.50000,
+        Rc::new(coated),
     )));
     world.add(Rc::new(Sphere::new(
         &Point3::from_scalars(0, -100.5, -1),
         100.0,
+        diffuse,
     )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(-1, 0, -1),
+        0.5,
+        Rc::new(Dielectric::dispersive(Ior::BK7, 0.0)),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(1, 0, -1),
+        0.5,
+        Rc::new(Conductor::gold(0.3)),
+    )));
+
+    let clay = Rc::new(Lambertian::new(Color::from_scalars(0.7, 0.35, 0.2)));
+    let apex = Point3::from_scalars(0.5, -0.22, -0.57);
+    let base = [
+        Point3::from_scalars(0.38, -0.5, -0.5),
+        Point3::from_scalars(0.62, -0.5, -0.5),
+        Point3::from_scalars(0.5, -0.5, -0.7),
+    ];
+    for k in 0..3 {
+        world.add(Rc::new(Triangle::new(
+            &base[k],
+            &base[(k + 1) % 3],
+            &apex,
+            clay.clone(),
+        )));
+    }
+    world.add(Rc::new(Triangle::new(&base[0], &base[2], &base[1], clay)));
+
+    world
+}
+
+/// Optional background, either a daylight sky or an equirectangular environment map:
+/// `sky [elevation] [azimuth] [turbidity]` or `<path.hdr|path.pfm> [rotation] [intensity]`
+fn background(args: Vec<String>) -> Result<Option<Rc<dyn Environment>>> {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        Some("sky") => {
+            let elevation = degrees_to_radians(parse_or(args.next(), "elevation", 45.0)?);
+            let azimuth = degrees_to_radians(parse_or(args.next(), "azimuth", 0.0)?);
+            let turbidity = parse_or(args.next(), "turbidity", 3.0)?;
+
+            let sun_direction = Vec3::from_scalars(
+                elevation.cos() * azimuth.sin(),
+                elevation.sin(),
+                -elevation.cos() * azimuth.cos(),
+            );
+            Ok(Some(Rc::new(SunSky::new(
+                &sun_direction,
+                turbidity,
+                Color::from(0.3),
+                0.53,
+            ))))
+        }
+        Some(path) => {
+            let mut map = EnvironmentMap::open(path)?;
+            map.rotation = parse_or(args.next(), "rotation", 0.0)?;
+            map.intensity = parse_or(args.next(), "intensity", 1.0)?;
+            Ok(Some(Rc::new(map)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Takes `--debug=<mode>` out of `args`, previewing the scene with `ao[:distance]`, `normals`,
+/// `depth[:far]`, `wireframe` or `albedo` instead of rendering it.
+fn debug_integrator(args: &mut Vec<String>) -> Result<Option<DebugIntegrator>> {
+    let Some(i) = args.iter().position(|a| a.starts_with("--debug=")) else {
+        return Ok(None);
+    };
+    let flag = args.remove(i);
+    let setting = &flag["--debug=".len()..];
+    let (mode, value) = setting
+        .split_once(':')
+        .map_or((setting, None), |(m, v)| (m, Some(v.to_owned())));
+    let value = |default: Point| parse_or(value.clone(), "debug value", default);
+
+    let mode = match mode {
+        "ao" => DebugMode::AmbientOcclusion {
+            distance: value(0.5)?,
+        },
+        "normals" => DebugMode::Normals,
+        "depth" => DebugMode::Depth { far: value(2.0)? },
+        "wireframe" => DebugMode::Wireframe { width: 0.03 },
+        "albedo" => DebugMode::Albedo,
+        _ => {
+            return Err(Error::InvalidParameter(format!(
+                "unknown debug mode {mode}"
+            )))
+        }
+    };
 
-    let mut cam = Camera::new();
-    cam.aspect_ratio = 16.0 / 9.0;
-    cam.image_width = 1280;
-    cam.samples_per_pixel = 100;
-    cam.max_depth = 10;
+    Ok(Some(DebugIntegrator::new(mode)))
+}
+
+/// `compare <test> <reference>` prints every error metric and writes the per pixel FLIP error
+/// in false colour to `--diff=<path>`. With `--threshold=<value>` it fails when `--metric=<name>`,
+/// FLIP by default, is past the threshold.
+fn compare_images(mut args: Vec<String>) -> Result<bool> {
+    let metric = take_value(&mut args, "--metric=").map_or(Ok(Metric::Flip), |m| m.parse())?;
+    let threshold: Option<Point> = take_value(&mut args, "--threshold=")
+        .map(|t| parse(&t, "threshold"))
+        .transpose()?;
+    let diff = take_value(&mut args, "--diff=").unwrap_or_else(|| PATH_DIFF.to_owned());
+
+    let [test, reference] = <[String; 2]>::try_from(args).map_err(|_| {
+        Error::InvalidParameter("compare takes a test and a reference image".to_owned())
+    })?;
+    let test = Image::open(&test)?;
+    let reference = Image::open(&reference)?;
+
+    let metrics = compare::compare(&test, &reference)?;
+    println!("{metrics}");
+    compare::difference_image(&test, &reference)?.write(&diff)?;
+
+    let value = metrics.get(metric);
+    if threshold.is_some_and(|threshold| metric.exceeds(value, threshold)) {
+        eprintln!("{metric} of {value} is past the threshold");
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Removes the first argument starting with `prefix`, returning what follows it.
+fn take_value(args: &mut Vec<String>, prefix: &str) -> Option<String> {
+    let i = args.iter().position(|a| a.starts_with(prefix))?;
+    Some(args.remove(i)[prefix.len()..].to_owned())
+}
+
+fn parse<T: FromStr>(value: &str, name: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidParameter(format!("invalid {name} {value:?}")))
+}
 
-    cam.render(&world);
+/// Parses `value` when it was given, falling back to `default`.
+fn parse_or<T: FromStr>(value: Option<String>, name: &str, default: T) -> Result<T> {
+    value.map_or(Ok(default), |v| parse(&v, name))
 }
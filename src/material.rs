@@ -0,0 +1,528 @@
+use std::fmt::Debug;
+
+use crate::{
+    color::Color,
+    hit::Hit,
+    microfacet::{
+        cos_theta, fresnel_complex, fresnel_dielectric, reflect, refract, same_hemisphere,
+        TrowbridgeReitz,
+    },
+    sampling::{cosine_hemisphere, cosine_hemisphere_pdf},
+    spectrum::Ior,
+    vec3::{consts::PI, Point, Vec3},
+};
+
+/// Incident direction sampled from a BSDF.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BsdfSample {
+    /// Unit direction the light arrives from, in world space.
+    pub direction: Vec3,
+    /// BSDF value for the sampled pair of directions, without the cosine term.
+    pub f: Color,
+    /// Solid angle density of the sample, or the discrete probability of a specular lobe.
+    pub pdf: Point,
+    pub specular: bool,
+}
+
+/// What a path carries. Refraction compresses radiance into a smaller solid angle, but not the
+/// importance traced from the camera side of a light subpath.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum Transport {
+    #[default]
+    Radiance,
+    Importance,
+}
+
+/// Describes how light scatters at a surface. Directions point away from the surface.
+pub trait Material: Debug {
+    /// Samples an incident direction for light leaving towards `wo`.
+    /// `uc` picks between lobes and `u` samples the chosen one.
+    fn sample(&self, wo: &Vec3, rec: &Hit, uc: Point, u: [Point; 2]) -> Option<BsdfSample>;
+
+    /// BSDF value for light arriving from `wi` and leaving towards `wo`.
+    fn eval(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Color;
+
+    /// Density with which [`Material::sample`] returns `wi`.
+    fn pdf(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Point;
+
+    /// Radiance emitted by the surface itself.
+    fn emitted(&self, _rec: &Hit) -> Color {
+        Color::new()
+    }
+
+    /// Overall color of the surface, for previews that skip light transport.
+    fn albedo(&self, _rec: &Hit) -> Color {
+        Color::from(1.0)
+    }
+
+    /// True when the material only scatters into discrete directions, so it can't be lit explicitly.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// True when the scattered direction depends on the wavelength the path is traced at.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+}
+
+/// Ideal diffuse reflector.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Lambertian {
+    pub albedo: Color,
+}
+
+impl Lambertian {
+    #[must_use]
+    pub const fn new(albedo: Color) -> Self {
+        Lambertian { albedo }
+    }
+}
+
+impl Material for Lambertian {
+    fn albedo(&self, _rec: &Hit) -> Color {
+        self.albedo
+    }
+
+    fn sample(&self, wo: &Vec3, rec: &Hit, _uc: Point, u: [Point; 2]) -> Option<BsdfSample> {
+        let frame = rec.shading_frame();
+        let mut wi = cosine_hemisphere(u);
+        if cos_theta(&frame.to_local(wo)) < 0.0 {
+            wi = -wi;
+        }
+
+        let pdf = cosine_hemisphere_pdf(wi.z().abs());
+        if pdf == 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction: frame.transform(&wi),
+            f: self.albedo / PI,
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Color {
+        let frame = rec.shading_frame();
+        if same_hemisphere(&frame.to_local(wo), &frame.to_local(wi)) {
+            self.albedo / PI
+        } else {
+            Color::new()
+        }
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Point {
+        let frame = rec.shading_frame();
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        if same_hemisphere(&wo, &wi) {
+            cosine_hemisphere_pdf(wi.z().abs())
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Metal described by its complex index of refraction, with GGX microfacet roughness.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    /// Creates a new [`Conductor`] with separate roughness along the surface tangent and bitangent.
+    #[must_use]
+    pub fn new(eta: Color, k: Color, roughness_u: Point, roughness_v: Point) -> Self {
+        Conductor {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::new(roughness_u, roughness_v),
+        }
+    }
+
+    #[must_use]
+    pub fn gold(roughness: Point) -> Self {
+        Self::new(
+            Color::from_scalars(0.143, 0.374, 1.442),
+            Color::from_scalars(3.983, 2.385, 1.603),
+            roughness,
+            roughness,
+        )
+    }
+
+    #[must_use]
+    pub fn copper(roughness: Point) -> Self {
+        Self::new(
+            Color::from_scalars(0.200, 0.924, 1.102),
+            Color::from_scalars(3.912, 2.452, 2.142),
+            roughness,
+            roughness,
+        )
+    }
+
+    #[must_use]
+    pub fn aluminium(roughness: Point) -> Self {
+        Self::new(
+            Color::from_scalars(1.657, 0.880, 0.521),
+            Color::from_scalars(9.224, 6.270, 4.837),
+            roughness,
+            roughness,
+        )
+    }
+
+    #[must_use]
+    pub fn silver(roughness: Point) -> Self {
+        Self::new(
+            Color::from_scalars(0.155, 0.117, 0.138),
+            Color::from_scalars(4.828, 3.122, 2.147),
+            roughness,
+            roughness,
+        )
+    }
+
+    fn fresnel(&self, cos_theta_i: Point) -> Color {
+        Color::from_scalars(
+            fresnel_complex(cos_theta_i, self.eta.x(), self.k.x()),
+            fresnel_complex(cos_theta_i, self.eta.y(), self.k.y()),
+            fresnel_complex(cos_theta_i, self.eta.z(), self.k.z()),
+        )
+    }
+
+    /// Microfacet reflection for a pair of local directions in the same hemisphere.
+    fn eval_local(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        let cos_theta_o = cos_theta(wo).abs();
+        let cos_theta_i = cos_theta(wi).abs();
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+            return Color::new();
+        }
+
+        let wm = *wi + *wo;
+        if wm.near_zero() {
+            return Color::new();
+        }
+        let wm = wm.unit_vector();
+
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(wo, wi);
+        self.fresnel(wo.dot(&wm).abs()) * d * g / (4.0 * cos_theta_i * cos_theta_o)
+    }
+}
+
+impl Material for Conductor {
+    /// Reflectance at normal incidence.
+    fn albedo(&self, _rec: &Hit) -> Color {
+        Color::from_scalars(
+            fresnel_complex(1.0, self.eta.x(), self.k.x()),
+            fresnel_complex(1.0, self.eta.y(), self.k.y()),
+            fresnel_complex(1.0, self.eta.z(), self.k.z()),
+        )
+    }
+
+    fn sample(&self, wo: &Vec3, rec: &Hit, _uc: Point, u: [Point; 2]) -> Option<BsdfSample> {
+        let frame = rec.shading_frame();
+        let wo_local = frame.to_local(wo);
+
+        if self.distribution.effectively_smooth() {
+            let wi = Vec3::from_scalars(-wo_local.x(), -wo_local.y(), wo_local.z());
+            let cos_theta_i = cos_theta(&wi).abs();
+
+            return Some(BsdfSample {
+                direction: frame.transform(&wi),
+                f: self.fresnel(cos_theta_i) / cos_theta_i,
+                pdf: 1.0,
+                specular: true,
+            });
+        }
+
+        if cos_theta(&wo_local) == 0.0 {
+            return None;
+        }
+
+        let wm = self.distribution.sample_wm(&wo_local, u);
+        let wi = reflect(&wo_local, &wm);
+        if !same_hemisphere(&wo_local, &wi) {
+            return None;
+        }
+
+        let pdf = self.distribution.visible_d(&wo_local, &wm) / (4.0 * wo_local.dot(&wm).abs());
+
+        Some(BsdfSample {
+            direction: frame.transform(&wi),
+            f: self.eval_local(&wo_local, &wi),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Color {
+        let frame = rec.shading_frame();
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        if !same_hemisphere(&wo, &wi) || self.distribution.effectively_smooth() {
+            return Color::new();
+        }
+
+        self.eval_local(&wo, &wi)
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Point {
+        let frame = rec.shading_frame();
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        if !same_hemisphere(&wo, &wi) || self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return 0.0;
+        }
+        let mut wm = wm.unit_vector();
+        if wm.z() < 0.0 {
+            wm = -wm;
+        }
+
+        self.distribution.visible_d(&wo, &wm) / (4.0 * wo.dot(&wm).abs())
+    }
+
+    fn is_specular(&self) -> bool {
+        self.distribution.effectively_smooth()
+    }
+}
+
+/// Glass-like interface that reflects and transmits, smooth or with GGX microfacet roughness.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Dielectric {
+    /// Index of refraction inside the surface relative to the outside.
+    pub ior: Ior,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Dielectric {
+    #[must_use]
+    pub fn new(ior: Point, roughness: Point) -> Self {
+        Self::dispersive(Ior::Constant(ior), roughness)
+    }
+
+    /// Creates a new [`Dielectric`] whose index of refraction varies with the wavelength.
+    #[must_use]
+    pub fn dispersive(ior: Ior, roughness: Point) -> Self {
+        Dielectric {
+            ior,
+            distribution: TrowbridgeReitz::new(roughness, roughness),
+        }
+    }
+
+    /// Relative index of refraction seen from the side the ray hit, at the wavelength it carries.
+    fn eta(&self, rec: &Hit) -> Point {
+        let ior = self.ior.at(rec.wavelength);
+        if rec.front_face {
+            ior
+        } else {
+            1.0 / ior
+        }
+    }
+
+    fn is_smooth(&self) -> bool {
+        self.ior == Ior::Constant(1.0) || self.distribution.effectively_smooth()
+    }
+
+    /// Reflection and transmission probabilities for an interface with reflectance `r`.
+    fn lobe_probabilities(r: Point) -> (Point, Point) {
+        let t = 1.0 - r;
+        (r / (r + t), t / (r + t))
+    }
+
+    fn sample_smooth(
+        wo: &Vec3,
+        eta: Point,
+        uc: Point,
+        transport: Transport,
+    ) -> Option<(Vec3, Color, Point)> {
+        let r = fresnel_dielectric(cos_theta(wo), eta);
+        let (pr, pt) = Self::lobe_probabilities(r);
+
+        if uc < pr {
+            let wi = Vec3::from_scalars(-wo.x(), -wo.y(), wo.z());
+            let f = Color::from(r / cos_theta(&wi).abs());
+            return Some((wi, f, pr));
+        }
+
+        let (wi, etap) = refract(wo, &Vec3::from_scalars(0, 0, 1), eta)?;
+        let mut f = Color::from((1.0 - r) / cos_theta(&wi).abs());
+        if transport == Transport::Radiance {
+            // Radiance is compressed into a smaller solid angle entering a denser medium
+            f = f / (etap * etap);
+        }
+        Some((wi, f, pt))
+    }
+
+    /// Samples the rough interface in the local frame, returning the direction with its value and density.
+    #[must_use]
+    pub fn sample_rough(
+        &self,
+        wo: &Vec3,
+        eta: Point,
+        uc: Point,
+        u: [Point; 2],
+        transport: Transport,
+    ) -> Option<(Vec3, Color, Point)> {
+        let wm = self.distribution.sample_wm(wo, u);
+        let r = fresnel_dielectric(wo.dot(&wm), eta);
+        let (pr, _) = Self::lobe_probabilities(r);
+
+        let wi = if uc < pr {
+            let wi = reflect(wo, &wm);
+            if !same_hemisphere(wo, &wi) {
+                return None;
+            }
+            wi
+        } else {
+            let (wi, _) = refract(wo, &wm, eta)?;
+            if same_hemisphere(wo, &wi) || wi.z() == 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        Some((
+            wi,
+            self.eval_local(wo, &wi, eta, transport),
+            self.pdf_local(wo, &wi, eta),
+        ))
+    }
+
+    /// Generalized half vector for a pair of local directions, facing `+z`.
+    fn half_vector(wo: &Vec3, wi: &Vec3, eta: Point) -> Option<(Vec3, Point, bool)> {
+        let cos_theta_o = cos_theta(wo);
+        let cos_theta_i = cos_theta(wi);
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+            return None;
+        }
+
+        let reflect = cos_theta_i * cos_theta_o > 0.0;
+        let etap = match (reflect, cos_theta_o > 0.0) {
+            (true, _) => 1.0,
+            (false, true) => eta,
+            (false, false) => 1.0 / eta,
+        };
+
+        let wm = *wi * etap + *wo;
+        if wm.near_zero() {
+            return None;
+        }
+        let mut wm = wm.unit_vector();
+        if wm.z() < 0.0 {
+            wm = -wm;
+        }
+
+        // Discard back-facing microfacets
+        if wm.dot(wi) * cos_theta_i < 0.0 || wm.dot(wo) * cos_theta_o < 0.0 {
+            return None;
+        }
+
+        Some((wm, etap, reflect))
+    }
+
+    /// BSDF value for a pair of local directions, `eta` being the relative index seen from `wo`.
+    #[must_use]
+    pub fn eval_local(&self, wo: &Vec3, wi: &Vec3, eta: Point, transport: Transport) -> Color {
+        let Some((wm, etap, reflect)) = Self::half_vector(wo, wi, eta) else {
+            return Color::new();
+        };
+
+        let cos_theta_o = cos_theta(wo);
+        let cos_theta_i = cos_theta(wi);
+        let f = fresnel_dielectric(wo.dot(&wm), eta);
+        let d = self.distribution.d(&wm);
+        let g = self.distribution.g(wo, wi);
+
+        if reflect {
+            Color::from(d * g * f / (4.0 * cos_theta_i * cos_theta_o).abs())
+        } else {
+            let denom = (wi.dot(&wm) + wo.dot(&wm) / etap).powi(2) * cos_theta_i * cos_theta_o;
+            let ft = d * (1.0 - f) * g * (wi.dot(&wm) * wo.dot(&wm) / denom).abs();
+            match transport {
+                Transport::Radiance => Color::from(ft / (etap * etap)),
+                Transport::Importance => Color::from(ft),
+            }
+        }
+    }
+
+    /// Density with which [`Dielectric::sample_rough`] returns `wi`.
+    #[must_use]
+    pub fn pdf_local(&self, wo: &Vec3, wi: &Vec3, eta: Point) -> Point {
+        let Some((wm, etap, reflect)) = Self::half_vector(wo, wi, eta) else {
+            return 0.0;
+        };
+
+        let r = fresnel_dielectric(wo.dot(&wm), eta);
+        let (pr, pt) = Self::lobe_probabilities(r);
+
+        if reflect {
+            self.distribution.visible_d(wo, &wm) / (4.0 * wo.dot(&wm).abs()) * pr
+        } else {
+            let denom = (wi.dot(&wm) + wo.dot(&wm) / etap).powi(2);
+            let dwm_dwi = wi.dot(&wm).abs() / denom;
+            self.distribution.visible_d(wo, &wm) * dwm_dwi * pt
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn sample(&self, wo: &Vec3, rec: &Hit, uc: Point, u: [Point; 2]) -> Option<BsdfSample> {
+        let frame = rec.shading_frame();
+        let wo_local = frame.to_local(wo);
+        let eta = self.eta(rec);
+
+        let specular = self.is_smooth();
+        let (wi, f, pdf) = if specular {
+            Self::sample_smooth(&wo_local, eta, uc, rec.transport)?
+        } else {
+            self.sample_rough(&wo_local, eta, uc, u, rec.transport)?
+        };
+
+        if pdf == 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction: frame.transform(&wi),
+            f,
+            pdf,
+            specular,
+        })
+    }
+
+    fn eval(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Color {
+        if self.is_smooth() {
+            return Color::new();
+        }
+
+        let frame = rec.shading_frame();
+        self.eval_local(
+            &frame.to_local(wo),
+            &frame.to_local(wi),
+            self.eta(rec),
+            rec.transport,
+        )
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Point {
+        if self.is_smooth() {
+            return 0.0;
+        }
+
+        let frame = rec.shading_frame();
+        self.pdf_local(&frame.to_local(wo), &frame.to_local(wi), self.eta(rec))
+    }
+
+    fn is_specular(&self) -> bool {
+        self.is_smooth()
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.ior.is_dispersive()
+    }
+}
@@ -0,0 +1,302 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::{
+    sampling::uniform_disk_polar,
+    vec3::{consts::PI, Point, Vec3},
+};
+
+// Directions below are expressed in the local shading frame, with the normal along `+z`.
+
+#[must_use]
+pub fn cos_theta(w: &Vec3) -> Point {
+    w.z()
+}
+
+#[must_use]
+pub fn same_hemisphere(w: &Vec3, wp: &Vec3) -> bool {
+    w.z() * wp.z() > 0.0
+}
+
+/// Mirrors `wo` around the normal `n`.
+#[must_use]
+pub fn reflect(wo: &Vec3, n: &Vec3) -> Vec3 {
+    -*wo + 2.0 * wo.dot(n) * *n
+}
+
+/// Refracts `wi` through a surface with normal `n` and relative index of refraction `eta`,
+/// returning the transmitted direction and the index ratio it actually crossed.
+#[must_use]
+pub fn refract(wi: &Vec3, n: &Vec3, eta: Point) -> Option<(Vec3, Point)> {
+    let mut cos_theta_i = n.dot(wi);
+    let (mut eta, mut n) = (eta, *n);
+
+    // Potentially flip the interface orientation
+    if cos_theta_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_theta_i = -cos_theta_i;
+        n = -n;
+    }
+
+    let sin2_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0);
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let wt = -*wi / eta + (cos_theta_i / eta - cos_theta_t) * n;
+
+    Some((wt, eta))
+}
+
+/// Unpolarized Fresnel reflectance of a dielectric interface.
+#[must_use]
+pub fn fresnel_dielectric(cos_theta_i: Point, eta: Point) -> Point {
+    let mut cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    let mut eta = eta;
+    if cos_theta_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_theta_i = -cos_theta_i;
+    }
+
+    let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).max(0.0).sqrt();
+
+    let r_parl = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perp = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+
+    Point::midpoint(r_parl * r_parl, r_perp * r_perp)
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index of refraction `eta + i k`.
+#[must_use]
+pub fn fresnel_complex(cos_theta_i: Point, eta: Point, k: Point) -> Point {
+    let cos_theta_i = Complex::from(cos_theta_i.clamp(0.0, 1.0));
+    let eta = Complex { re: eta, im: k };
+
+    let sin2_theta_i = Complex::from(1.0) - cos_theta_i * cos_theta_i;
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    let cos_theta_t = (Complex::from(1.0) - sin2_theta_t).sqrt();
+
+    let r_parl = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perp = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+
+    Point::midpoint(r_parl.norm(), r_perp.norm())
+}
+
+/// Anisotropic Trowbridge-Reitz (GGX) microfacet distribution with Smith masking-shadowing.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: Point,
+    pub alpha_y: Point,
+}
+
+impl TrowbridgeReitz {
+    /// Creates a new [`TrowbridgeReitz`] from perceptual roughness along each tangent direction.
+    #[must_use]
+    pub fn new(roughness_x: Point, roughness_y: Point) -> Self {
+        TrowbridgeReitz {
+            alpha_x: Self::roughness_to_alpha(roughness_x),
+            alpha_y: Self::roughness_to_alpha(roughness_y),
+        }
+    }
+
+    #[must_use]
+    pub fn roughness_to_alpha(roughness: Point) -> Point {
+        roughness.clamp(0.0, 1.0).powi(2)
+    }
+
+    /// Distributions this narrow are treated as perfect specular surfaces.
+    #[must_use]
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// Differential area of microfacets oriented along `wm`.
+    #[must_use]
+    pub fn d(&self, wm: &Vec3) -> Point {
+        let cos2_theta = wm.z() * wm.z();
+        let cos4_theta = cos2_theta * cos2_theta;
+        if cos4_theta < 1e-16 {
+            return 0.0;
+        }
+
+        let tan2_theta = (1.0 - cos2_theta).max(0.0) / cos2_theta;
+        let (cos_phi, sin_phi) = Self::cos_sin_phi(wm);
+        let e = tan2_theta * ((cos_phi / self.alpha_x).powi(2) + (sin_phi / self.alpha_y).powi(2));
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e).powi(2))
+    }
+
+    /// Smith's auxiliary function, the invisible masked microfacet area per visible area.
+    #[must_use]
+    pub fn lambda(&self, w: &Vec3) -> Point {
+        let cos2_theta = w.z() * w.z();
+        if cos2_theta == 0.0 {
+            return Point::INFINITY;
+        }
+
+        let tan2_theta = (1.0 - cos2_theta).max(0.0) / cos2_theta;
+        let (cos_phi, sin_phi) = Self::cos_sin_phi(w);
+        let alpha2 = (cos_phi * self.alpha_x).powi(2) + (sin_phi * self.alpha_y).powi(2);
+
+        ((1.0 + alpha2 * tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    #[must_use]
+    pub fn g1(&self, w: &Vec3) -> Point {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated masking-shadowing for a pair of directions.
+    #[must_use]
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> Point {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Distribution of microfacet normals visible from `w`.
+    #[must_use]
+    pub fn visible_d(&self, w: &Vec3, wm: &Vec3) -> Point {
+        let cos_theta = cos_theta(w).abs();
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+
+        self.g1(w) / cos_theta * self.d(wm) * w.dot(wm).abs()
+    }
+
+    /// Samples a visible microfacet normal from `w`, following Heitz,
+    /// "Sampling the GGX Distribution of Visible Normals".
+    #[allow(clippy::many_single_char_names)]
+    #[must_use]
+    pub fn sample_wm(&self, w: &Vec3, u: [Point; 2]) -> Vec3 {
+        // Transform w to the hemispherical configuration
+        let mut wh =
+            Vec3::from_scalars(self.alpha_x * w.x(), self.alpha_y * w.y(), w.z()).unit_vector();
+        if wh.z() < 0.0 {
+            wh = -wh;
+        }
+
+        // Orthonormal basis for visible normal sampling
+        let t1 = if wh.z() < 0.99999 {
+            Vec3::from_scalars(0, 0, 1).cross(&wh).unit_vector()
+        } else {
+            Vec3::from_scalars(1, 0, 0)
+        };
+        let t2 = wh.cross(&t1);
+
+        // Uniformly distributed points on the disk, warped to the visible half
+        let p = uniform_disk_polar(u);
+        let h = (1.0 - p[0] * p[0]).sqrt();
+        let s = Point::midpoint(1.0, wh.z());
+        let p = [p[0], (1.0 - s) * h + s * p[1]];
+
+        // Reproject onto the hemisphere and back to the ellipsoid configuration
+        let pz = (1.0 - p[0] * p[0] - p[1] * p[1]).max(0.0).sqrt();
+        let nh = p[0] * t1 + p[1] * t2 + pz * wh;
+
+        Vec3::from_scalars(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(1e-6),
+        )
+        .unit_vector()
+    }
+
+    fn cos_sin_phi(w: &Vec3) -> (Point, Point) {
+        let sin_theta = (w.x() * w.x() + w.y() * w.y()).sqrt();
+        if sin_theta == 0.0 {
+            (1.0, 0.0)
+        } else {
+            (
+                (w.x() / sin_theta).clamp(-1.0, 1.0),
+                (w.y() / sin_theta).clamp(-1.0, 1.0),
+            )
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Complex {
+    re: Point,
+    im: Point,
+}
+
+impl Complex {
+    fn norm(self) -> Point {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(self) -> Complex {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Complex::from(0.0);
+        }
+
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0.0 {
+            Complex { re: t1, im: t2 }
+        } else {
+            Complex {
+                re: t2.abs(),
+                im: t1.copysign(self.im),
+            }
+        }
+    }
+}
+
+impl From<Point> for Complex {
+    fn from(re: Point) -> Self {
+        Complex { re, im: 0.0 }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Self::Output {
+        Complex {
+            re: self.re + rhs.re,
+            im: self.im + rhs.im,
+        }
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Self::Output {
+        Complex {
+            re: self.re - rhs.re,
+            im: self.im - rhs.im,
+        }
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Self::Output {
+        Complex {
+            re: self.re * rhs.re - self.im * rhs.im,
+            im: self.re * rhs.im + self.im * rhs.re,
+        }
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Complex) -> Self::Output {
+        let scale = 1.0 / rhs.norm();
+        Complex {
+            re: scale * (self.re * rhs.re + self.im * rhs.im),
+            im: scale * (self.im * rhs.re - self.re * rhs.im),
+        }
+    }
+}
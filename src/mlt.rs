@@ -0,0 +1,346 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    camera::Camera,
+    film::Film,
+    integrator::{Integrator, Scene},
+    path::PathIntegrator,
+    sampler::Sampler,
+    sampling::Distribution1D,
+    spectrum::Channels,
+    vec3::{consts::PI, Point, Scalar, Vec3},
+};
+
+/// Primary sample space Metropolis light transport after Kelemen et al. Paths are traced by the
+/// path tracer from a vector of uniform numbers, and Markov chains explore that vector with
+/// small perturbations and fresh large steps, so bright but hard to find paths get revisited.
+#[derive(Debug, Clone)]
+pub struct Mlt {
+    /// Paths traced to estimate the image brightness and pick the chains' starting points.
+    pub bootstrap_samples: usize,
+    /// Chains run in turns, each one takes a share of the mutations.
+    pub chains: usize,
+    /// Chance that a mutation draws a whole new path instead of perturbing the current one.
+    pub large_step_probability: Point,
+    /// Standard deviation of a small step in primary sample space.
+    pub sigma: Point,
+    /// Average image brightness, the bootstrap's estimate of the chains' normalization.
+    brightness: Point,
+    /// Seeds every chain, taken from the camera so the same seed renders the same image.
+    seed: u64,
+    states: Vec<Chain>,
+    next: usize,
+}
+
+/// One Markov chain and the path it currently sits on.
+#[derive(Debug, Clone)]
+struct Chain {
+    sampler: MltSampler,
+    current: PathSample,
+}
+
+/// Contribution of the path traced from a primary sample vector.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PathSample {
+    x: Point,
+    y: Point,
+    /// Film space value, linear sRGB or CIE XYZ.
+    value: Vec3,
+    /// Scalar the chain's density is proportional to, the value's luminance.
+    importance: Point,
+}
+
+impl Default for Mlt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integrator for Mlt {
+    /// Advances one of the chains by a mutation and splats both the proposal and the current
+    /// path, weighted by the acceptance probability. The pixel being asked for doesn't matter,
+    /// only that the camera asks for samples per pixel times mutations per pixel.
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        _i: u32,
+        _j: u32,
+        _lambda: &mut C,
+        film: &mut Film,
+    ) {
+        if self.states.is_empty() {
+            self.bootstrap::<C>(camera, scene);
+        }
+        if self.brightness == 0.0 {
+            return;
+        }
+
+        let brightness = self.brightness;
+        let index = self.next;
+        self.next = (index + 1) % self.states.len();
+        let chain = &mut self.states[index];
+
+        chain.sampler.start_iteration();
+        let proposed = evaluate::<C>(camera, scene, &mut chain.sampler);
+        let current = chain.current;
+
+        let accept = if current.importance > 0.0 {
+            (proposed.importance / current.importance).min(1.0)
+        } else {
+            1.0
+        };
+
+        // Expected values of both outcomes, scaled so the film's average over the mutations
+        // per pixel comes out as radiance
+        if accept > 0.0 {
+            let weight = accept * brightness / proposed.importance;
+            film.add_splat(proposed.x, proposed.y, proposed.value * weight);
+        }
+        if accept < 1.0 {
+            let weight = (1.0 - accept) * brightness / current.importance;
+            film.add_splat(current.x, current.y, current.value * weight);
+        }
+
+        if chain.sampler.rng.gen::<Point>() < accept {
+            chain.current = proposed;
+            chain.sampler.accept();
+        } else {
+            chain.sampler.reject();
+        }
+    }
+}
+
+impl Mlt {
+    /// Creates a new [`Mlt`].
+    #[must_use]
+    pub fn new() -> Self {
+        Mlt {
+            bootstrap_samples: 100_000,
+            chains: 1000,
+            large_step_probability: 0.3,
+            sigma: 0.01,
+            brightness: 0.0,
+            seed: 0,
+            states: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Traces the bootstrap paths, takes their mean importance as the normalization and starts
+    /// each chain on one of them, picked in proportion to its importance.
+    #[allow(clippy::cast_precision_loss)]
+    fn bootstrap<C: Channels>(&mut self, camera: &Camera, scene: &Scene) {
+        self.seed = camera.seed.unwrap_or_else(rand::random);
+        let weights: Vec<Point> = (0..self.bootstrap_samples)
+            .map(|index| {
+                let mut sampler = self.sampler(index);
+                evaluate::<C>(camera, scene, &mut sampler).importance
+            })
+            .collect();
+
+        let distribution = Distribution1D::new(&weights);
+        self.brightness = distribution.func_int();
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.states = (0..self.chains.max(1))
+            .map(|_| {
+                // Replaying the bootstrap sampler puts the chain on the same path
+                let (_, _, index) = distribution.sample_continuous(rng.gen());
+                let mut sampler = self.sampler(index);
+                let current = evaluate::<C>(camera, scene, &mut sampler);
+
+                Chain { sampler, current }
+            })
+            .collect();
+    }
+
+    fn sampler(&self, index: usize) -> MltSampler {
+        MltSampler::new(
+            self.seed.wrapping_add(index as u64 + 1),
+            self.sigma,
+            self.large_step_probability,
+        )
+    }
+}
+
+/// Traces the path a primary sample vector describes: a film position, the wavelengths and
+/// then whatever the path tracer asks for.
+fn evaluate<C: Channels>(camera: &Camera, scene: &Scene, sampler: &mut MltSampler) -> PathSample {
+    let [u_film, v_film] = sampler.get_2d();
+    let x = u_film * camera.image_width.to_point();
+    let y = v_film * camera.image_height().to_point();
+
+    let mut lambda = C::from_sample(sampler.get_1d());
+    let r = camera.ray_through(x, y);
+    let radiance = PathIntegrator::radiance(&r, camera.max_depth, scene, &mut lambda, sampler);
+
+    let value = lambda.to_film(&radiance);
+    let importance = if camera.spectral {
+        value.y()
+    } else {
+        value.luminance()
+    };
+
+    PathSample {
+        x,
+        y,
+        value,
+        // Negative or broken estimates can't be a density
+        importance: if importance.is_finite() {
+            importance.max(0.0)
+        } else {
+            0.0
+        },
+    }
+}
+
+/// One coordinate of the primary sample vector, updated lazily when it's next read.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PrimarySample {
+    value: Point,
+    /// Iteration the value was last brought up to date in.
+    last_modified: u64,
+    value_backup: Point,
+    modify_backup: u64,
+}
+
+/// Hands out the primary sample vector of a chain, mutating each coordinate the first time it
+/// is read in an iteration. Coordinates a path didn't read catch up on the mutations they missed
+/// when a later path reads them.
+#[derive(Debug, Clone)]
+pub struct MltSampler {
+    rng: StdRng,
+    sigma: Point,
+    large_step_probability: Point,
+    samples: Vec<PrimarySample>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    index: usize,
+}
+
+impl MltSampler {
+    /// Creates a new [`MltSampler`], its first iteration drawing every coordinate afresh.
+    #[must_use]
+    pub fn new(seed: u64, sigma: Point, large_step_probability: Point) -> Self {
+        MltSampler {
+            rng: StdRng::seed_from_u64(seed),
+            sigma,
+            large_step_probability,
+            samples: Vec::new(),
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            index: 0,
+        }
+    }
+
+    /// Starts a new proposal, deciding whether it is a small or a large step.
+    pub fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.gen::<Point>() < self.large_step_probability;
+        self.index = 0;
+    }
+
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    /// Restores the coordinates the proposal changed.
+    pub fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.last_modified == self.iteration {
+                sample.value = sample.value_backup;
+                sample.last_modified = sample.modify_backup;
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    /// Brings coordinate `index` up to date with the current iteration.
+    #[allow(clippy::cast_precision_loss)]
+    fn ensure_ready(&mut self, index: usize) {
+        if index >= self.samples.len() {
+            self.samples.resize(index + 1, PrimarySample::default());
+        }
+        let sample = &mut self.samples[index];
+
+        // A large step the coordinate missed makes the small steps before it irrelevant
+        if sample.last_modified < self.last_large_step {
+            sample.value = self.rng.gen();
+            sample.last_modified = self.last_large_step;
+        }
+
+        sample.value_backup = sample.value;
+        sample.modify_backup = sample.last_modified;
+
+        if self.large_step {
+            sample.value = self.rng.gen();
+        } else {
+            // The small steps since the last update add up to one with a wider spread
+            let steps = (self.iteration - sample.last_modified) as Point;
+            let sigma = self.sigma * steps.sqrt();
+            sample.value += standard_normal(&mut self.rng) * sigma;
+            sample.value -= sample.value.floor();
+        }
+        sample.last_modified = self.iteration;
+    }
+}
+
+impl Sampler for MltSampler {
+    fn get_1d(&mut self) -> Point {
+        self.ensure_ready(self.index);
+        self.index += 1;
+        self.samples[self.index - 1].value
+    }
+}
+
+/// Normally distributed number through the Box-Muller transform.
+fn standard_normal(rng: &mut StdRng) -> Point {
+    let u1 = 1.0 - rng.gen::<Point>();
+    let u2 = rng.gen::<Point>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejected_steps_restore_the_vector() {
+        let mut sampler = MltSampler::new(7, 0.01, 0.3);
+        let first: Vec<Point> = (0..8).map(|_| sampler.get_1d()).collect();
+
+        for _ in 0..20 {
+            sampler.start_iteration();
+            for _ in 0..8 {
+                let u = sampler.get_1d();
+                assert!((0.0..1.0).contains(&u));
+            }
+            sampler.reject();
+        }
+
+        // Every proposal was rolled back, so the vector is where it started
+        let values: Vec<Point> = sampler.samples.iter().map(|s| s.value).collect();
+        assert_eq!(values, first);
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let draw = || {
+            let mut sampler = MltSampler::new(42, 0.01, 0.3);
+            let mut numbers = Vec::new();
+            for _ in 0..10 {
+                sampler.start_iteration();
+                numbers.extend((0..5).map(|_| sampler.get_1d()));
+                sampler.accept();
+            }
+            numbers
+        };
+
+        assert_eq!(draw(), draw());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::vec3::{Point, Vec3};
+
+/// Orthonormal basis built around a direction, used to move between local and world frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    /// Creates a new [`Onb`] whose `w` axis points along `n`.
+    #[allow(clippy::many_single_char_names)]
+    #[must_use]
+    pub fn new(n: &Vec3) -> Self {
+        let w = n.unit_vector();
+        // Duff et al., "Building an Orthonormal Basis, Revisited"
+        let sign = Point::copysign(1.0, w.z());
+        let a = -1.0 / (sign + w.z());
+        let b = w.x() * w.y() * a;
+
+        let u = Vec3::from_scalars(1.0 + sign * w.x() * w.x() * a, sign * b, -sign * w.x());
+        let v = Vec3::from_scalars(b, sign + w.y() * w.y() * a, -w.y());
+
+        Onb { axis: [u, v, w] }
+    }
+
+    /// Creates a new [`Onb`] around `n` with its `u` axis aligned to `tangent` where possible.
+    #[must_use]
+    pub fn from_tangent(n: &Vec3, tangent: &Vec3) -> Self {
+        let w = n.unit_vector();
+        let u = *tangent - w * w.dot(tangent);
+        if u.near_zero() {
+            return Self::new(n);
+        }
+
+        let u = u.unit_vector();
+        Onb {
+            axis: [u, w.cross(&u), w],
+        }
+    }
+
+    #[must_use]
+    pub const fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    #[must_use]
+    pub const fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    #[must_use]
+    pub const fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    /// Transforms a vector from the local frame to world space.
+    #[must_use]
+    pub fn transform(&self, local: &Vec3) -> Vec3 {
+        local.x() * self.u() + local.y() * self.v() + local.z() * self.w()
+    }
+
+    /// Transforms a vector from world space to the local frame.
+    #[must_use]
+    pub fn to_local(self, world: &Vec3) -> Vec3 {
+        Vec3::from_scalars(
+            world.dot(&self.u()),
+            world.dot(&self.v()),
+            world.dot(&self.w()),
+        )
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{
+    aabb::Aabb,
+    interval::Interval,
+    ray::Ray,
+    vec3::{Point, Point3},
+};
+
+/// Rays in a [`RayPacket`].
+pub const LANES: usize = 4;
+
+/// One bit per lane of a [`RayPacket`], lane `k` in bit `k`.
+pub type Mask = u8;
+
+/// Mask with every lane of a full packet set.
+pub const ALL: Mask = (1 << LANES) - 1;
+
+/// Rays traced together, stored a coordinate at a time so all of them can be tested at once.
+/// Every lane searches its own interval, narrowed as hits are found, and only the lanes in
+/// `active` take part. Rendering doesn't use packets, see
+/// [`Hittable::hit_packet`](crate::hit::Hittable::hit_packet).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayPacket {
+    pub origin: [[Point; LANES]; 3],
+    pub direction: [[Point; LANES]; 3],
+    /// Reciprocal of the direction, for the slab test.
+    pub inverse: [[Point; LANES]; 3],
+    pub t_min: [Point; LANES],
+    pub t_max: [Point; LANES],
+    pub active: Mask,
+}
+
+impl RayPacket {
+    /// Creates a new [`RayPacket`] of up to [`LANES`] rays, each searched within `ray_t`. Lanes
+    /// past the last ray are left inactive.
+    #[must_use]
+    pub fn new(rays: &[Ray], ray_t: Interval) -> Self {
+        let mut packet = RayPacket {
+            origin: [[0.0; LANES]; 3],
+            direction: [[0.0; LANES]; 3],
+            inverse: [[0.0; LANES]; 3],
+            t_min: [ray_t.min; LANES],
+            t_max: [ray_t.max; LANES],
+            active: 0,
+        };
+
+        for (lane, ray) in rays.iter().take(LANES).enumerate() {
+            for axis in 0..3 {
+                packet.origin[axis][lane] = ray.origin()[axis];
+                packet.direction[axis][lane] = ray.direction()[axis];
+                packet.inverse[axis][lane] = 1.0 / ray.direction()[axis];
+            }
+            packet.active |= 1 << lane;
+        }
+
+        packet
+    }
+
+    /// The ray in `lane`.
+    #[must_use]
+    pub fn ray(&self, lane: usize) -> Ray {
+        let coordinate = |values: &[[Point; LANES]; 3]| {
+            Point3::from_scalars(values[0][lane], values[1][lane], values[2][lane])
+        };
+
+        Ray::new(coordinate(&self.origin), coordinate(&self.direction))
+    }
+
+    /// Interval the ray in `lane` is still searched over.
+    #[must_use]
+    pub fn interval(&self, lane: usize) -> Interval {
+        Interval::from(self.t_min[lane], self.t_max[lane])
+    }
+
+    /// Active lanes, in order.
+    pub fn lanes(&self) -> impl Iterator<Item = usize> {
+        let active = self.active;
+        (0..LANES).filter(move |lane| active & (1 << lane) != 0)
+    }
+}
+
+/// Lanes of `packet` whose ray enters `bbox` within its interval, by the slab test of
+/// [`Aabb::hit`].
+#[must_use]
+pub fn slab(bbox: &Aabb, packet: &RayPacket) -> Mask {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if crate::simd::avx() {
+        return crate::simd::slab(bbox, packet);
+    }
+
+    slab_lanes(bbox, packet)
+}
+
+fn slab_lanes(bbox: &Aabb, packet: &RayPacket) -> Mask {
+    let mut mask = 0;
+    for lane in packet.lanes() {
+        let (mut t_min, mut t_max) = (packet.t_min[lane], packet.t_max[lane]);
+        for n in 0..3 {
+            let axis = bbox.axis(n);
+            let t0 = (axis.min - packet.origin[n][lane]) * packet.inverse[n][lane];
+            let t1 = (axis.max - packet.origin[n][lane]) * packet.inverse[n][lane];
+
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        if t_min <= t_max {
+            mask |= 1 << lane;
+        }
+    }
+
+    mask
+}
+
+/// Nearest distance along each lane of `packet` at which its ray meets the sphere, strictly
+/// inside the lane's interval, with the lanes that meet it at all. Solves the same quadratic as
+/// [`Sphere::hit`](crate::sphere::Sphere).
+#[must_use]
+pub fn sphere(center: &Point3, radius: Point, packet: &RayPacket) -> ([Point; LANES], Mask) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if crate::simd::avx() {
+        return crate::simd::sphere(center, radius, packet);
+    }
+
+    sphere_lanes(center, radius, packet)
+}
+
+fn sphere_lanes(center: &Point3, radius: Point, packet: &RayPacket) -> ([Point; LANES], Mask) {
+    let mut roots = [0.0; LANES];
+    let mut mask = 0;
+    for lane in packet.lanes() {
+        let oc = [0, 1, 2].map(|n| center[n] - packet.origin[n][lane]);
+        let d = [0, 1, 2].map(|n| packet.direction[n][lane]);
+
+        let a = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+        let h = d[0] * oc[0] + d[1] * oc[1] + d[2] * oc[2];
+        let c = oc[0] * oc[0] + oc[1] * oc[1] + oc[2] * oc[2] - radius * radius;
+
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            continue;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let ray_t = packet.interval(lane);
+        for root in [(h - sqrtd) / a, (h + sqrtd) / a] {
+            if ray_t.surrounds(root) {
+                roots[lane] = root;
+                mask |= 1 << lane;
+                break;
+            }
+        }
+    }
+
+    (roots, mask)
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{
+        bvh::BvhNode,
+        color::Color,
+        helpers::Generator,
+        hit::{Hit, HitList, Hittable},
+        material::Lambertian,
+        sphere::Sphere,
+        vec3::Vec3,
+    };
+
+    fn spheres(count: usize) -> HitList<dyn Hittable> {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let mut world: HitList<dyn Hittable> = HitList::new();
+        for _ in 0..count {
+            let center = Vec3::random_interval(Interval::from(-4, 4));
+            let radius = 0.2 + Generator::random_point();
+            world.add(Rc::new(Sphere::new(&center, radius, material.clone())));
+        }
+        world
+    }
+
+    fn packets(count: usize) -> Vec<Vec<Ray>> {
+        (0..count)
+            .map(|_| {
+                (0..LANES)
+                    .map(|_| {
+                        Ray::new(
+                            Vec3::random_interval(Interval::from(-6, 6)),
+                            Vec3::random_unit_vector(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Traces every packet both ways through `world` and checks the lanes agree with the rays
+    /// traced one at a time, returning how many hit.
+    fn check_against_scalar(world: &dyn Hittable, packets: &[Vec<Ray>]) -> usize {
+        let ray_t = Interval::from(0.001, Point::INFINITY);
+        let mut hits = 0;
+
+        for rays in packets {
+            let mut packet = RayPacket::new(rays, ray_t);
+            let mut recs: [Hit; LANES] = Default::default();
+            let mask = world.hit_packet(&mut packet, &mut recs);
+
+            for (lane, ray) in rays.iter().enumerate() {
+                let mut rec = Hit::new();
+                let hit = world.hit(ray, ray_t, &mut rec);
+
+                assert_eq!(mask & (1 << lane) != 0, hit, "lane {lane} of {rays:?}");
+                if hit {
+                    assert!((recs[lane].t - rec.t).abs() < 1e-9);
+                    assert!((recs[lane].normal - rec.normal).lenght() < 1e-9);
+                    assert!((packet.t_max[lane] - rec.t).abs() < 1e-9);
+                    hits += 1;
+                }
+            }
+        }
+
+        hits
+    }
+
+    #[test]
+    fn packets_match_single_rays() {
+        Generator::seed(7);
+        let world = spheres(40);
+        let packets = packets(500);
+
+        let hits = check_against_scalar(&world, &packets);
+        assert!(hits > 100, "only {hits} rays hit");
+        assert_eq!(check_against_scalar(&BvhNode::new(world), &packets), hits);
+    }
+
+    #[test]
+    fn partial_packets_leave_lanes_alone() {
+        Generator::seed(11);
+        let world = BvhNode::new(spheres(20));
+        let rays = &packets(1)[0][..LANES - 1];
+
+        let mut packet = RayPacket::new(rays, Interval::from(0.001, Point::INFINITY));
+        let mut recs: [Hit; LANES] = Default::default();
+        let mask = world.hit_packet(&mut packet, &mut recs);
+
+        assert_eq!(packet.active, ALL >> 1);
+        assert_eq!(mask & (1 << (LANES - 1)), 0);
+        check_against_scalar(&world, &[rays.to_vec()]);
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn avx_kernels_match_portable_ones() {
+        if !crate::simd::avx() {
+            return;
+        }
+
+        Generator::seed(3);
+        let ray_t = Interval::from(0.001, Point::INFINITY);
+        for rays in packets(500) {
+            let packet = RayPacket::new(&rays, ray_t);
+            let center = Vec3::random_interval(Interval::from(-4, 4));
+            let bbox = Aabb::from_points(&(center - 1.0), &(center + 1.5));
+
+            assert_eq!(
+                crate::simd::slab(&bbox, &packet),
+                slab_lanes(&bbox, &packet)
+            );
+
+            let (roots, mask) = crate::simd::sphere(&center, 1.0, &packet);
+            let (expected_roots, expected_mask) = sphere_lanes(&center, 1.0, &packet);
+            assert_eq!(mask, expected_mask);
+            for lane in 0..LANES {
+                if mask & (1 << lane) != 0 {
+                    assert!((roots[lane] - expected_roots[lane]).abs() < 1e-12);
+                }
+            }
+        }
+    }
+}
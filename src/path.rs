@@ -0,0 +1,231 @@
+use crate::{
+    camera::Camera,
+    film::Film,
+    helpers::Generator,
+    hit::Hit,
+    integrator::{Integrator, Scene},
+    interval::Interval,
+    material::Material,
+    ray::{Ray, SHADOW_EPSILON},
+    sampler::Sampler,
+    sampling::power_heuristic,
+    spectrum::Channels,
+    stats::{self, RayKind},
+    vec3::{Point, Vec3},
+};
+
+/// Bounces after which paths are terminated at random by Russian roulette.
+const ROULETTE_DEPTH: u8 = 3;
+
+/// Unidirectional path tracer, lighting each bounce from the environment and the light list.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PathIntegrator;
+
+impl Integrator for PathIntegrator {
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        lambda: &mut C,
+        film: &mut Film,
+    ) {
+        let r = camera.get_ray(i, j);
+        let radiance = Self::radiance(&r, camera.max_depth, scene, lambda, &mut Generator {});
+        film.add_sample(i, j, lambda.to_film(&radiance));
+    }
+}
+
+impl PathIntegrator {
+    /// Radiance arriving along `r` over at most `depth` segments, with every random decision
+    /// drawn from `sampler`. Paths past [`ROULETTE_DEPTH`] bounces survive with a probability
+    /// that follows their throughput, so deep paths cost little without biasing the result.
+    pub fn radiance<C: Channels>(
+        r: &Ray,
+        depth: u8,
+        scene: &Scene,
+        lambda: &mut C,
+        sampler: &mut impl Sampler,
+    ) -> C::Spectrum {
+        let mut color = C::Spectrum::default();
+        let mut beta = C::Spectrum::from(1.0);
+        let mut ray = Ray::new(*r.origin(), *r.direction());
+        // Density with which the last bounce sampled `ray`, to weight light hit by chance
+        // against the explicit environment samples
+        let mut bsdf_pdf: Option<Point> = None;
+
+        for bounce in 0..depth {
+            let mut rec = Hit::new();
+            stats::ray(if bounce == 0 {
+                RayKind::Camera
+            } else {
+                RayKind::Bounce
+            });
+
+            if !scene
+                .world
+                .hit(&ray, Interval::from(0.0, Point::INFINITY), &mut rec)
+            {
+                let environment = scene.environment;
+                let radiance = lambda.illuminant(&environment.radiance(ray.direction()));
+
+                color += match bsdf_pdf {
+                    Some(pdf) if environment.pdf(ray.direction()) > 0.0 => {
+                        beta * radiance * power_heuristic(pdf, environment.pdf(ray.direction()))
+                    }
+                    _ => beta * radiance,
+                };
+                break;
+            }
+
+            let Some(mat) = rec.mat.clone() else {
+                break;
+            };
+            let wo = -ray.direction().unit_vector();
+
+            rec.wavelength = lambda.hero();
+            if mat.is_dispersive() {
+                lambda.terminate_secondary();
+            }
+
+            color += beta * lambda.illuminant(&mat.emitted(&rec));
+            if !mat.is_specular() {
+                color += beta
+                    * Self::sample_environment(&rec, &wo, mat.as_ref(), scene, lambda, sampler);
+                color += beta * Self::sample_lights(&rec, &wo, mat.as_ref(), scene, lambda);
+            }
+
+            let Some(bs) = mat.sample(&wo, &rec, sampler.get_1d(), sampler.get_2d()) else {
+                break;
+            };
+
+            beta =
+                beta * lambda.reflectance(&bs.f) * (rec.normal.dot(&bs.direction).abs() / bs.pdf);
+            bsdf_pdf = (!bs.specular).then_some(bs.pdf);
+            ray = rec.spawn_ray(&bs.direction);
+
+            if bounce + 1 >= ROULETTE_DEPTH {
+                let survival = lambda.max_value(&beta).min(1.0);
+                if sampler.get_1d() >= survival {
+                    break;
+                }
+                beta = beta / survival;
+            }
+        }
+
+        color
+    }
+
+    /// Light reaching `rec` in one bounce, from the lights, the environment and any emissive
+    /// surface the BSDF happens to sample.
+    pub fn direct_lighting<C: Channels>(
+        rec: &Hit,
+        wo: &Vec3,
+        mat: &dyn Material,
+        scene: &Scene,
+        lambda: &C,
+        sampler: &mut impl Sampler,
+    ) -> C::Spectrum {
+        let mut color = Self::sample_environment(rec, wo, mat, scene, lambda, sampler);
+        color += Self::sample_lights(rec, wo, mat, scene, lambda);
+
+        let Some(bs) = mat.sample(wo, rec, sampler.get_1d(), sampler.get_2d()) else {
+            return color;
+        };
+        let weight = lambda.reflectance(&bs.f) * (rec.normal.dot(&bs.direction).abs() / bs.pdf);
+
+        let r = rec.spawn_ray(&bs.direction);
+        let mut hit = Hit::new();
+        stats::ray(RayKind::Shadow);
+        if scene
+            .world
+            .hit(&r, Interval::from(0.0, Point::INFINITY), &mut hit)
+        {
+            if let Some(emitter) = &hit.mat {
+                color += weight * lambda.illuminant(&emitter.emitted(&hit));
+            }
+        } else {
+            let environment = scene.environment;
+            let radiance = lambda.illuminant(&environment.radiance(&bs.direction));
+            let env_pdf = environment.pdf(&bs.direction);
+
+            color += if bs.specular || env_pdf == 0.0 {
+                weight * radiance
+            } else {
+                weight * radiance * power_heuristic(bs.pdf, env_pdf)
+            };
+        }
+
+        color
+    }
+
+    /// Direct light from an explicitly sampled environment direction.
+    fn sample_environment<C: Channels>(
+        rec: &Hit,
+        wo: &Vec3,
+        mat: &dyn Material,
+        scene: &Scene,
+        lambda: &C,
+        sampler: &mut impl Sampler,
+    ) -> C::Spectrum {
+        let Some(light) = scene.environment.sample(sampler.get_2d()) else {
+            return C::Spectrum::default();
+        };
+
+        let f = mat.eval(wo, &light.direction, rec) * rec.normal.dot(&light.direction).abs();
+        if f.near_zero() {
+            return C::Spectrum::default();
+        }
+
+        let shadow = rec.spawn_ray(&light.direction);
+        stats::ray(RayKind::Shadow);
+        if scene.world.hit(
+            &shadow,
+            Interval::from(0.0, Point::INFINITY),
+            &mut Hit::new(),
+        ) {
+            return C::Spectrum::default();
+        }
+
+        let weight = power_heuristic(light.pdf, mat.pdf(wo, &light.direction, rec));
+
+        lambda.reflectance(&f) * lambda.illuminant(&light.radiance) * (weight / light.pdf)
+    }
+
+    /// Direct light from every light in the list that isn't in shadow.
+    fn sample_lights<C: Channels>(
+        rec: &Hit,
+        wo: &Vec3,
+        mat: &dyn Material,
+        scene: &Scene,
+        lambda: &C,
+    ) -> C::Spectrum {
+        let mut color = C::Spectrum::default();
+
+        for light in &scene.lights.lights {
+            let Some(sample) = light.sample_li(&rec.p) else {
+                continue;
+            };
+
+            let f = mat.eval(wo, &sample.direction, rec) * rec.normal.dot(&sample.direction).abs();
+            if f.near_zero() {
+                continue;
+            }
+
+            let shadow = rec.spawn_ray(&sample.direction);
+            stats::ray(RayKind::Shadow);
+            if scene.world.hit(
+                &shadow,
+                Interval::from(0.0, sample.distance * (1.0 - SHADOW_EPSILON)),
+                &mut Hit::new(),
+            ) {
+                continue;
+            }
+
+            color += lambda.reflectance(&f) * lambda.illuminant(&sample.radiance);
+        }
+
+        color
+    }
+}
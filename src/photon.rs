@@ -0,0 +1,500 @@
+use std::rc::Rc;
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    error::{Error, Result},
+    film::Film,
+    helpers::Generator,
+    hit::Hit,
+    integrator::{Integrator, Scene},
+    interval::Interval,
+    material::{Material, Transport},
+    path::PathIntegrator,
+    spectrum::Channels,
+    stats::{self, RayKind},
+    vec3::{consts::PI, Point, Point3, Vec3},
+};
+
+/// Light flux left on a surface by a path traced from the lights.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Photon {
+    pub position: Point3,
+    /// Unit direction the photon arrived from.
+    pub direction: Vec3,
+    pub power: Color,
+    /// Axis the kd-tree splits on at this photon.
+    axis: usize,
+}
+
+/// Photons stored as a balanced kd-tree, each node at the median of its slice.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+}
+
+impl PhotonMap {
+    /// Creates a new [`PhotonMap`] by reordering `photons` into a kd-tree.
+    #[must_use]
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        Self::build(&mut photons);
+        PhotonMap { photons }
+    }
+
+    fn build(photons: &mut [Photon]) {
+        if photons.len() < 2 {
+            return;
+        }
+
+        // Split along the axis the photons spread the most on
+        let (min, max) = photons.iter().fold(
+            (Vec3::from(Point::INFINITY), Vec3::from(Point::NEG_INFINITY)),
+            |(min, max), photon| {
+                (
+                    Vec3::from_scalars(
+                        min.x().min(photon.position.x()),
+                        min.y().min(photon.position.y()),
+                        min.z().min(photon.position.z()),
+                    ),
+                    Vec3::from_scalars(
+                        max.x().max(photon.position.x()),
+                        max.y().max(photon.position.y()),
+                        max.z().max(photon.position.z()),
+                    ),
+                )
+            },
+        );
+        let extent = max - min;
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap_or(0);
+
+        let mid = photons.len() / 2;
+        photons.select_nth_unstable_by(mid, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+        photons[mid].axis = axis;
+
+        let (left, right) = photons.split_at_mut(mid);
+        Self::build(left);
+        Self::build(&mut right[1..]);
+    }
+
+    /// Calls `f` with every photon within `radius` of `p`.
+    pub fn for_each_within(&self, p: &Point3, radius: Point, mut f: impl FnMut(&Photon)) {
+        Self::within(&self.photons, p, radius * radius, &mut f);
+    }
+
+    fn within(photons: &[Photon], p: &Point3, radius_squared: Point, f: &mut impl FnMut(&Photon)) {
+        if photons.is_empty() {
+            return;
+        }
+
+        let mid = photons.len() / 2;
+        let photon = &photons[mid];
+        if (photon.position - *p).lenght_squared() <= radius_squared {
+            f(photon);
+        }
+
+        let delta = p[photon.axis] - photon.position[photon.axis];
+        let (near, far) = if delta < 0.0 {
+            (&photons[..mid], &photons[mid + 1..])
+        } else {
+            (&photons[mid + 1..], &photons[..mid])
+        };
+
+        Self::within(near, p, radius_squared, f);
+        if delta * delta <= radius_squared {
+            Self::within(far, p, radius_squared, f);
+        }
+    }
+
+    /// The `k` photons nearest to `p` within `max_radius`, closest first, along with the squared
+    /// radius of the disk the estimate covers.
+    #[must_use]
+    pub fn nearest(&self, p: &Point3, k: usize, max_radius: Point) -> (Vec<&Photon>, Point) {
+        let mut found = Vec::with_capacity(k + 1);
+        let mut radius_squared = max_radius * max_radius;
+        Self::search(&self.photons, p, k, &mut radius_squared, &mut found);
+
+        let photons = found.into_iter().map(|(_, photon)| photon).collect();
+        (photons, radius_squared)
+    }
+
+    fn search<'a>(
+        photons: &'a [Photon],
+        p: &Point3,
+        k: usize,
+        radius_squared: &mut Point,
+        found: &mut Vec<(Point, &'a Photon)>,
+    ) {
+        if photons.is_empty() || k == 0 {
+            return;
+        }
+
+        let mid = photons.len() / 2;
+        let photon = &photons[mid];
+        let delta = p[photon.axis] - photon.position[photon.axis];
+        let (near, far) = if delta < 0.0 {
+            (&photons[..mid], &photons[mid + 1..])
+        } else {
+            (&photons[mid + 1..], &photons[..mid])
+        };
+
+        Self::search(near, p, k, radius_squared, found);
+
+        let distance_squared = (photon.position - *p).lenght_squared();
+        if distance_squared <= *radius_squared {
+            let index = found.partition_point(|&(d, _)| d <= distance_squared);
+            found.insert(index, (distance_squared, photon));
+            found.truncate(k);
+
+            // Once full, only photons closer than the furthest one found can matter
+            if found.len() == k {
+                *radius_squared = found[k - 1].0;
+            }
+        }
+
+        if delta * delta <= *radius_squared {
+            Self::search(far, p, k, radius_squared, found);
+        }
+    }
+}
+
+/// First non-specular surface seen through a pixel, where photons are gathered.
+pub struct VisiblePoint<S> {
+    pub rec: Hit,
+    pub mat: Rc<dyn Material>,
+    pub wo: Vec3,
+    /// Throughput of the camera path reaching the point.
+    pub beta: S,
+}
+
+impl<S: Copy> VisiblePoint<S> {
+    /// Reflected flux of `photon` towards the camera, still to be divided by the gather area.
+    pub fn flux(&self, photon: &Photon) -> Color {
+        self.mat.eval(&self.wo, &photon.direction, &self.rec) * photon.power
+    }
+
+    /// True when the surface emits light itself, which no photon carries.
+    pub fn is_emissive(&self) -> bool {
+        !self.mat.emitted(&self.rec).near_zero()
+    }
+}
+
+/// Follows a camera ray through pixel `(i, j)` and any specular bounces, to the first surface
+/// photons can be gathered on. Returns the light found on the way, direct lighting at that
+/// surface included, along with the surface.
+pub fn trace_visible_point<C: Channels>(
+    camera: &Camera,
+    scene: &Scene,
+    i: u32,
+    j: u32,
+    lambda: &mut C,
+) -> (C::Spectrum, Option<VisiblePoint<C::Spectrum>>) {
+    let mut ray = camera.get_ray(i, j);
+    let mut beta = C::Spectrum::from(1.0);
+    let mut radiance = C::Spectrum::default();
+
+    for bounce in 0..camera.max_depth {
+        let mut rec = Hit::new();
+        stats::ray(if bounce == 0 {
+            RayKind::Camera
+        } else {
+            RayKind::Bounce
+        });
+        if !scene
+            .world
+            .hit(&ray, Interval::from(0.0, Point::INFINITY), &mut rec)
+        {
+            radiance += beta * lambda.illuminant(&scene.environment.radiance(ray.direction()));
+            break;
+        }
+
+        let Some(mat) = rec.mat.clone() else {
+            break;
+        };
+        rec.wavelength = lambda.hero();
+        if mat.is_dispersive() {
+            lambda.terminate_secondary();
+        }
+
+        let wo = -ray.direction().unit_vector();
+        radiance += beta * lambda.illuminant(&mat.emitted(&rec));
+
+        if !mat.is_specular() {
+            let mut sampler = Generator {};
+            radiance += beta
+                * PathIntegrator::direct_lighting(
+                    &rec,
+                    &wo,
+                    mat.as_ref(),
+                    scene,
+                    lambda,
+                    &mut sampler,
+                );
+            return (radiance, Some(VisiblePoint { rec, mat, wo, beta }));
+        }
+
+        let Some(bs) = mat.sample(
+            &wo,
+            &rec,
+            Generator::random_point(),
+            [Generator::random_point(), Generator::random_point()],
+        ) else {
+            break;
+        };
+
+        beta = beta * lambda.reflectance(&bs.f) * (rec.normal.dot(&bs.direction).abs() / bs.pdf);
+        ray = rec.spawn_ray(&bs.direction);
+    }
+
+    (radiance, None)
+}
+
+/// Emits `count` photons from the lights and the environment, storing them on every
+/// non-specular surface they reach after their first bounce. Light arriving straight from the
+/// emitters is left to [`PathIntegrator::direct_lighting`].
+///
+/// Photons are traced in RGB, so they don't disperse.
+///
+/// # Errors
+///
+/// Surfaces can't be sampled, so emissive ones send out no photons. Fails when a photon reaches
+/// one, rather than leave out the light it bounces around the scene.
+pub fn trace_photons(scene: &Scene, count: usize, max_depth: u8) -> Result<Vec<Photon>> {
+    let mut photons = Vec::new();
+    let choice = scene.light_choice_pdf();
+
+    for _ in 0..count {
+        let u = [Generator::random_point(), Generator::random_point()];
+        let emission = match scene.pick_light(Generator::random_point()) {
+            Some(light) => light.sample_le(u, &scene.bounds),
+            None => scene.environment_le(u, [Generator::random_point(), Generator::random_point()]),
+        };
+
+        let Some(emission) = emission else {
+            continue;
+        };
+        if emission.pdf_position == 0.0 || emission.pdf_direction == 0.0 {
+            continue;
+        }
+
+        let mut power =
+            emission.radiance / (choice * emission.pdf_position * emission.pdf_direction);
+        let mut ray = emission.ray;
+
+        for depth in 0..max_depth {
+            let mut rec = Hit::new();
+            stats::ray(RayKind::Light);
+            if !scene
+                .world
+                .hit(&ray, Interval::from(0.0, Point::INFINITY), &mut rec)
+            {
+                break;
+            }
+
+            let Some(mat) = rec.mat.clone() else {
+                break;
+            };
+            if !mat.emitted(&rec).near_zero() {
+                return Err(emissive_surfaces());
+            }
+            rec.transport = Transport::Importance;
+
+            let wo = -ray.direction().unit_vector();
+            if depth > 0 && !mat.is_specular() {
+                photons.push(Photon {
+                    position: rec.p,
+                    direction: wo,
+                    power,
+                    axis: 0,
+                });
+            }
+
+            let Some(bs) = mat.sample(
+                &wo,
+                &rec,
+                Generator::random_point(),
+                [Generator::random_point(), Generator::random_point()],
+            ) else {
+                break;
+            };
+
+            power = power * bs.f * (rec.normal.dot(&bs.direction).abs() / bs.pdf);
+            if power.near_zero() {
+                break;
+            }
+            ray = rec.spawn_ray(&bs.direction);
+        }
+    }
+
+    Ok(photons)
+}
+
+/// Error for scenes lit by emissive surfaces, which photons can't be traced from.
+pub(crate) fn emissive_surfaces() -> Error {
+    Error::Scene(
+        "photons are only emitted from lights and the environment, not emissive surfaces".into(),
+    )
+}
+
+/// Photon mapper after Jensen: direct light is sampled at the first non-specular surface a
+/// camera ray reaches, and light that bounced at least once is estimated from the photons
+/// nearest to it. Caustics come out smooth, at the price of some blur.
+///
+/// Rendering fails on scenes lit by emissive surfaces, see [`trace_photons`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhotonMapper {
+    /// Photons emitted before rendering.
+    pub photon_count: usize,
+    /// Photons gathered by each density estimate.
+    pub nearest: usize,
+    /// Largest radius photons are gathered from.
+    pub max_radius: Point,
+    map: Option<PhotonMap>,
+    /// Set once light is found leaving an emissive surface, which the map leaves out.
+    emissive: bool,
+}
+
+impl Default for PhotonMapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn begin_pass(&mut self, camera: &Camera, scene: &Scene) {
+        if self.map.is_none() {
+            let photons = trace_photons(scene, self.photon_count, camera.max_depth);
+            self.emissive |= photons.is_err();
+            self.map = Some(PhotonMap::new(photons.unwrap_or_default()));
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        lambda: &mut C,
+        film: &mut Film,
+    ) {
+        let (mut radiance, visible) = trace_visible_point(camera, scene, i, j, lambda);
+        self.emissive |= visible.as_ref().is_some_and(VisiblePoint::is_emissive);
+
+        if let (Some(point), Some(map)) = (visible, &self.map) {
+            let (photons, radius_squared) =
+                map.nearest(&point.rec.p, self.nearest, self.max_radius);
+
+            let mut flux = Color::new();
+            for photon in photons {
+                flux += point.flux(photon);
+            }
+
+            let area = PI * radius_squared;
+            radiance +=
+                point.beta * lambda.illuminant(&(flux / (area * self.photon_count as Point)));
+        }
+
+        film.add_sample(i, j, lambda.to_film(&radiance));
+    }
+
+    fn finish(&mut self, _camera: &Camera, _film: &mut Film) -> Result<()> {
+        if self.emissive {
+            return Err(emissive_surfaces());
+        }
+        Ok(())
+    }
+}
+
+impl PhotonMapper {
+    /// Creates a new [`PhotonMapper`].
+    #[must_use]
+    pub const fn new() -> Self {
+        PhotonMapper {
+            photon_count: 200_000,
+            nearest: 100,
+            max_radius: 0.1,
+            map: None,
+            emissive: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        environment::Gradient, hit::HitList, light::LightList, principled::Principled,
+        sphere::Sphere, texture::SolidColor,
+    };
+
+    fn random_photons(count: usize) -> Vec<Photon> {
+        (0..count)
+            .map(|_| Photon {
+                position: Point3::random(),
+                ..Photon::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn within_matches_brute_force() {
+        let photons = random_photons(2000);
+        let map = PhotonMap::new(photons.clone());
+
+        for _ in 0..50 {
+            let p = Point3::random();
+            let radius = 0.1;
+
+            let mut found = 0;
+            map.for_each_within(&p, radius, |_| found += 1);
+            let expected = photons
+                .iter()
+                .filter(|photon| (photon.position - p).lenght() <= radius)
+                .count();
+
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let photons = random_photons(2000);
+        let map = PhotonMap::new(photons.clone());
+
+        for _ in 0..50 {
+            let p = Point3::random();
+            let (found, radius_squared) = map.nearest(&p, 10, 1.0);
+
+            let mut distances: Vec<Point> = photons
+                .iter()
+                .map(|photon| (photon.position - p).lenght_squared())
+                .collect();
+            distances.sort_by(Point::total_cmp);
+
+            assert_eq!(found.len(), 10);
+            for (photon, expected) in found.iter().zip(&distances) {
+                assert!(((photon.position - p).lenght_squared() - expected).abs() < 1e-12);
+            }
+            assert!((radius_squared - distances[9]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn emissive_surfaces_are_rejected() {
+        let mut glowing = Principled::new();
+        glowing.emission = Rc::new(SolidColor::new(Color::from(1.0)));
+        let mut world = HitList::new();
+        world.add(Rc::new(Sphere::new(&Point3::new(), 1.0, Rc::new(glowing))));
+
+        let (lights, environment) = (LightList::new(), Gradient::default());
+        let scene = Scene::new(&world, &lights, &environment);
+
+        assert!(matches!(
+            trace_photons(&scene, 100, 4),
+            Err(Error::Scene(_))
+        ));
+    }
+}
@@ -0,0 +1,338 @@
+use std::rc::Rc;
+
+use crate::{
+    color::Color,
+    hit::Hit,
+    material::{BsdfSample, Dielectric, Material, Transport},
+    microfacet::{reflect, same_hemisphere, TrowbridgeReitz},
+    sampling::{cosine_hemisphere, cosine_hemisphere_pdf},
+    spectrum::Ior,
+    texture::{SolidColor, Texture},
+    vec3::{consts::PI, Point, Vec3},
+};
+
+/// Narrowest roughness the principled lobes use, keeping them out of the perfectly specular case.
+const MIN_ALPHA: Point = 1e-3;
+
+/// Artist friendly uber material after Burley, "Physically Based Shading at Disney".
+///
+/// Scalar parameters are read from the first channel of their texture.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    pub base_color: Rc<dyn Texture>,
+    pub metallic: Rc<dyn Texture>,
+    pub roughness: Rc<dyn Texture>,
+    /// Reflectance of dielectrics at normal incidence, 0.5 maps to 4%.
+    pub specular: Rc<dyn Texture>,
+    /// Tints dielectric reflections towards the base color.
+    pub specular_tint: Rc<dyn Texture>,
+    /// Extra grazing retroreflection for cloth.
+    pub sheen: Rc<dyn Texture>,
+    pub sheen_tint: Rc<dyn Texture>,
+    /// Strength of a second, white specular layer on top.
+    pub clearcoat: Rc<dyn Texture>,
+    pub clearcoat_gloss: Rc<dyn Texture>,
+    /// Blends the dielectric base from diffuse towards glass-like transmission.
+    pub transmission: Rc<dyn Texture>,
+    pub emission: Rc<dyn Texture>,
+    /// Index of refraction used by the transmission lobe.
+    pub ior: Point,
+}
+
+/// Parameters evaluated at a hit point, along with the weight of each lobe.
+struct Lobes {
+    base_color: Color,
+    roughness: Point,
+    specular_f0: Color,
+    sheen_color: Color,
+    clearcoat: Point,
+    clearcoat_alpha: Point,
+    distribution: TrowbridgeReitz,
+    glass: Dielectric,
+    eta: Point,
+    transport: Transport,
+    diffuse_weight: Point,
+    specular_weight: Point,
+    transmission_weight: Point,
+    /// Probabilities of sampling the diffuse, specular, clearcoat and transmission lobes.
+    probabilities: [Point; 4],
+}
+
+impl Principled {
+    /// Creates a new [`Principled`] with a white, rough dielectric base.
+    #[must_use]
+    pub fn new() -> Self {
+        let constant = |c: Point| -> Rc<dyn Texture> { Rc::new(SolidColor::new(Color::from(c))) };
+
+        Principled {
+            base_color: constant(0.8),
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            specular_tint: constant(0.0),
+            sheen: constant(0.0),
+            sheen_tint: constant(0.5),
+            clearcoat: constant(0.0),
+            clearcoat_gloss: constant(1.0),
+            transmission: constant(0.0),
+            emission: constant(0.0),
+            ior: 1.5,
+        }
+    }
+
+    fn lobes(&self, rec: &Hit) -> Lobes {
+        let color = |t: &Rc<dyn Texture>| t.value(rec.u, rec.v, &rec.p);
+        let scalar = |t: &Rc<dyn Texture>| color(t).x().clamp(0.0, 1.0);
+
+        let base_color = color(&self.base_color);
+        let metallic = scalar(&self.metallic);
+        let roughness = scalar(&self.roughness);
+        let transmission = scalar(&self.transmission);
+        let clearcoat = scalar(&self.clearcoat);
+
+        // Hue and saturation of the base color, without its luminance
+        let luminance = base_color.luminance();
+        let tint = if luminance > 0.0 {
+            base_color / luminance
+        } else {
+            Color::WHITE
+        };
+
+        let specular_tint = scalar(&self.specular_tint);
+        let dielectric_f0 = scalar(&self.specular)
+            * 0.08
+            * ((1.0 - specular_tint) * Color::WHITE + specular_tint * tint);
+        let specular_f0 = (1.0 - metallic) * dielectric_f0 + metallic * base_color;
+
+        let sheen_tint = scalar(&self.sheen_tint);
+        let sheen_color =
+            scalar(&self.sheen) * ((1.0 - sheen_tint) * Color::WHITE + sheen_tint * tint);
+
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness).max(MIN_ALPHA);
+        let distribution = TrowbridgeReitz {
+            alpha_x: alpha,
+            alpha_y: alpha,
+        };
+
+        let diffuse_weight = (1.0 - metallic) * (1.0 - transmission);
+        let transmission_weight = (1.0 - metallic) * transmission;
+        let specular_weight = 1.0 - transmission_weight;
+
+        let clearcoat_gloss = scalar(&self.clearcoat_gloss);
+        let probabilities = normalize([
+            diffuse_weight * luminance,
+            specular_weight * specular_f0.luminance().max(0.04),
+            0.25 * clearcoat,
+            transmission_weight,
+        ]);
+
+        Lobes {
+            base_color,
+            roughness,
+            specular_f0,
+            sheen_color,
+            clearcoat,
+            clearcoat_alpha: (1.0 - clearcoat_gloss) * 0.1 + clearcoat_gloss * 0.001,
+            distribution,
+            glass: Dielectric {
+                ior: Ior::Constant(self.ior),
+                distribution,
+            },
+            eta: if rec.front_face {
+                self.ior
+            } else {
+                1.0 / self.ior
+            },
+            transport: rec.transport,
+            diffuse_weight,
+            specular_weight,
+            transmission_weight,
+            probabilities,
+        }
+    }
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lobes {
+    fn eval(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        let mut f = Color::new();
+        let mut coat = Color::new();
+
+        if same_hemisphere(wo, wi) {
+            let cos_theta_o = wo.z().abs();
+            let cos_theta_i = wi.z().abs();
+            let wh = *wo + *wi;
+            if cos_theta_o == 0.0 || cos_theta_i == 0.0 || wh.near_zero() {
+                return f;
+            }
+            let wh = wh.unit_vector();
+            let cos_theta_d = wi.dot(&wh);
+
+            if self.diffuse_weight > 0.0 {
+                let fd90 = 0.5 + 2.0 * self.roughness * cos_theta_d * cos_theta_d;
+                let fd = (1.0 + (fd90 - 1.0) * schlick_weight(cos_theta_i))
+                    * (1.0 + (fd90 - 1.0) * schlick_weight(cos_theta_o));
+                let sheen = self.sheen_color * schlick_weight(cos_theta_d);
+
+                f += self.diffuse_weight * (self.base_color / PI * fd + sheen);
+            }
+
+            let fresnel =
+                self.specular_f0 + (Color::WHITE - self.specular_f0) * schlick_weight(cos_theta_d);
+            let d = self.distribution.d(&wh);
+            let g = self.distribution.g(wo, wi);
+            f += self.specular_weight * fresnel * d * g / (4.0 * cos_theta_o * cos_theta_i);
+
+            if self.clearcoat > 0.0 {
+                let fresnel = 0.04 + 0.96 * schlick_weight(cos_theta_d);
+                let d = gtr1(wh.z(), self.clearcoat_alpha);
+                let g = CLEARCOAT_DISTRIBUTION.g(wo, wi);
+                coat = Color::from(
+                    0.25 * self.clearcoat * fresnel * d * g / (4.0 * cos_theta_o * cos_theta_i),
+                );
+            }
+        }
+
+        if self.transmission_weight > 0.0 {
+            let mut glass = self.glass.eval_local(wo, wi, self.eta, self.transport);
+            if !same_hemisphere(wo, wi) {
+                glass *= self.base_color;
+            }
+            f += self.transmission_weight * glass;
+        }
+
+        // Whatever the clearcoat reflects never reaches the layers below it, on the way in or
+        // on the way out, so it can't add energy on top of them
+        f * self.coat_transmittance(wo.z()) * self.coat_transmittance(wi.z()) + coat
+    }
+
+    fn coat_transmittance(&self, cos_theta: Point) -> Point {
+        1.0 - 0.25 * self.clearcoat * (0.04 + 0.96 * schlick_weight(cos_theta.abs()))
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> Point {
+        let [diffuse, specular, clearcoat, transmission] = self.probabilities;
+        let mut pdf = 0.0;
+
+        if same_hemisphere(wo, wi) {
+            let wh = *wo + *wi;
+            if !wh.near_zero() {
+                let wh = wh.unit_vector();
+                let wo_dot_wh = wo.dot(&wh).abs();
+
+                pdf += diffuse * cosine_hemisphere_pdf(wi.z().abs());
+                pdf += specular * self.distribution.visible_d(wo, &wh) / (4.0 * wo_dot_wh);
+                pdf += clearcoat * gtr1(wh.z(), self.clearcoat_alpha) * wh.z().abs()
+                    / (4.0 * wo_dot_wh);
+            }
+        }
+
+        if transmission > 0.0 {
+            pdf += transmission * self.glass.pdf_local(wo, wi, self.eta);
+        }
+
+        pdf
+    }
+
+    fn sample(&self, wo: &Vec3, uc: Point, u: [Point; 2]) -> Option<Vec3> {
+        let [diffuse, specular, clearcoat, _] = self.probabilities;
+
+        if uc < diffuse {
+            let wi = cosine_hemisphere(u);
+            Some(if wo.z() < 0.0 { -wi } else { wi })
+        } else if uc < diffuse + specular {
+            let wm = self.distribution.sample_wm(wo, u);
+            Some(reflect(wo, &wm))
+        } else if uc < diffuse + specular + clearcoat {
+            let wh = sample_gtr1(u, self.clearcoat_alpha);
+            let wh = if wo.dot(&wh) < 0.0 { -wh } else { wh };
+            Some(reflect(wo, &wh))
+        } else {
+            // Reuse the remainder of the lobe selection sample to pick reflection or transmission
+            let uc = (uc - diffuse - specular - clearcoat) / self.probabilities[3];
+            self.glass
+                .sample_rough(wo, self.eta, uc.min(1.0), u, self.transport)
+                .map(|(wi, _, _)| wi)
+        }
+    }
+}
+
+impl Material for Principled {
+    fn sample(&self, wo: &Vec3, rec: &Hit, uc: Point, u: [Point; 2]) -> Option<BsdfSample> {
+        let frame = rec.shading_frame();
+        let lobes = self.lobes(rec);
+        let wo = frame.to_local(wo);
+
+        let wi = lobes.sample(&wo, uc, u)?;
+        let pdf = lobes.pdf(&wo, &wi);
+        if pdf == 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction: frame.transform(&wi),
+            f: lobes.eval(&wo, &wi),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Color {
+        let frame = rec.shading_frame();
+        self.lobes(rec)
+            .eval(&frame.to_local(wo), &frame.to_local(wi))
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3, rec: &Hit) -> Point {
+        let frame = rec.shading_frame();
+        self.lobes(rec)
+            .pdf(&frame.to_local(wo), &frame.to_local(wi))
+    }
+
+    fn emitted(&self, rec: &Hit) -> Color {
+        self.emission.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn albedo(&self, rec: &Hit) -> Color {
+        self.base_color.value(rec.u, rec.v, &rec.p)
+    }
+}
+
+/// Fixed roughness used for the clearcoat masking-shadowing.
+const CLEARCOAT_DISTRIBUTION: TrowbridgeReitz = TrowbridgeReitz {
+    alpha_x: 0.25,
+    alpha_y: 0.25,
+};
+
+fn schlick_weight(cos_theta: Point) -> Point {
+    (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+/// Generalized Trowbridge-Reitz distribution with `gamma = 1`, used by the clearcoat.
+fn gtr1(cos_theta_h: Point, alpha: Point) -> Point {
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_theta_h * cos_theta_h))
+}
+
+fn sample_gtr1(u: [Point; 2], alpha: Point) -> Vec3 {
+    let a2 = alpha * alpha;
+    let cos_theta = ((1.0 - a2.powf(1.0 - u[0])) / (1.0 - a2)).max(0.0).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u[1];
+
+    Vec3::from_scalars(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+fn normalize(weights: [Point; 4]) -> [Point; 4] {
+    let total: Point = weights.iter().sum();
+    if total > 0.0 {
+        weights.map(|w| w / total)
+    } else {
+        [1.0, 0.0, 0.0, 0.0]
+    }
+}
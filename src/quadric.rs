@@ -0,0 +1,714 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    csg::{Solid, Span},
+    error::{Error, Result},
+    hit::{Hit, Hittable},
+    interval::Interval,
+    material::Material,
+    onb::Onb,
+    ray::{gamma, Ray},
+    stats,
+    vec3::{consts::PI, Point, Point3, Vec3},
+};
+
+/// Placement of a shape modelled about the `z` axis of a frame of its own. Azimuths are measured
+/// from the local `x` axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    origin: Point3,
+    basis: Onb,
+}
+
+impl Frame {
+    /// Creates a new [`Frame`] at `origin` with its `z` axis along `axis`.
+    #[must_use]
+    pub fn new(origin: &Point3, axis: &Vec3) -> Self {
+        Frame {
+            origin: *origin,
+            basis: Onb::new(axis),
+        }
+    }
+
+    /// Creates a new [`Frame`] at `origin` with its `z` axis along `axis` and its `x` axis, where
+    /// azimuths start, towards `tangent`.
+    #[must_use]
+    pub fn from_tangent(origin: &Point3, axis: &Vec3, tangent: &Vec3) -> Self {
+        Frame {
+            origin: *origin,
+            basis: Onb::from_tangent(axis, tangent),
+        }
+    }
+
+    /// `r` seen from the frame, with the same parameterization.
+    #[must_use]
+    pub fn to_local(&self, r: &Ray) -> Ray {
+        Ray::new(
+            self.basis.to_local(&(*r.origin() - self.origin)),
+            self.basis.to_local(r.direction()),
+        )
+    }
+
+    /// Direction in world space of the local `v`.
+    #[must_use]
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.basis.transform(v)
+    }
+
+    /// World space position of the local point `p`, which is off by at most `error`, along with
+    /// a bound on the error of the result.
+    #[must_use]
+    pub fn point(&self, p: &Point3, error: &Vec3) -> (Point3, Vec3) {
+        let world = self.origin + self.basis.transform(p);
+
+        let [u, v, w] = [self.basis.u(), self.basis.v(), self.basis.w()].map(|axis| axis.abs());
+        let local = *error + gamma(3) * p.abs();
+        let error = local.x() * u
+            + local.y() * v
+            + local.z() * w
+            + gamma(1) * (self.origin.abs() + world.abs());
+
+        (world, error)
+    }
+
+    /// World space box enclosing the local box `bbox`.
+    #[must_use]
+    pub fn bound(&self, bbox: &Aabb) -> Aabb {
+        (0..8).fold(Aabb::EMPTY, |world, corner| {
+            let pick = |n: usize| {
+                let axis = bbox.axis(n);
+                if corner & (1 << n) == 0 {
+                    axis.min
+                } else {
+                    axis.max
+                }
+            };
+            let p = self.origin
+                + self
+                    .basis
+                    .transform(&Point3::from_scalars(pick(0), pick(1), pick(2)));
+            Aabb::from_boxes(&world, &Aabb::from_points(&p, &p))
+        })
+    }
+}
+
+/// Azimuth of `p` about the `z` axis, from 0 to 2π.
+#[must_use]
+pub fn azimuth(p: &Point3) -> Point {
+    let phi = p.y().atan2(p.x());
+    if phi < 0.0 {
+        phi + 2.0 * PI
+    } else {
+        phi
+    }
+}
+
+/// Direction of increasing azimuth at `p`, zero on the axis.
+fn around(p: &Point3) -> Vec3 {
+    let tangent = Vec3::from_scalars(-p.y(), p.x(), 0);
+    if tangent.near_zero() {
+        Vec3::new()
+    } else {
+        tangent.unit_vector()
+    }
+}
+
+/// Real roots of `a t² + b t + c`, nearest first, with how many there are. They're computed
+/// without cancellation between `b` and the square root of the discriminant.
+fn quadratic(a: Point, b: Point, c: Point) -> ([Point; 2], usize) {
+    if a == 0.0 {
+        let t = -c / b;
+        return ([t, t], usize::from(t.is_finite()));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return ([0.0; 2], 0);
+    }
+
+    let q = -0.5 * (b + Point::copysign(discriminant.sqrt(), b));
+    let (t0, t1) = (q / a, c / q);
+    if t1.is_nan() {
+        ([t0, t0], 1)
+    } else {
+        ([t0.min(t1), t0.max(t1)], 2)
+    }
+}
+
+/// Shape checked to enclose a volume, which makes it a [`Solid`]. Shapes that can be left open,
+/// like a [`Quadric`] without caps, are wrapped by their `closed`
+/// method once they've been closed off.
+#[derive(Debug, Clone)]
+pub struct Closed<T>(pub(crate) T);
+
+impl<T: Hittable> Hittable for Closed<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        self.0.hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+}
+
+/// Pairs of `crossings` of a closed surface, sorted along a ray, that bound the stretches of it
+/// inside, where `inside` tells whether the point `t` along the ray is. Every gap between two
+/// crossings is tested in its middle, so tangent rays, apexes and crossings found twice never
+/// throw the pairing off. Coincident crossings leave no gap, and stretches either side of them
+/// are joined.
+pub(crate) fn inside_between<C: Copy>(
+    crossings: &[C],
+    t: impl Fn(&C) -> Point,
+    inside: impl Fn(Point) -> bool,
+) -> Vec<(C, C)> {
+    let mut stretches: Vec<(C, C)> = Vec::new();
+    let mut open = false;
+    for pair in crossings.windows(2) {
+        let (t0, t1) = (t(&pair[0]), t(&pair[1]));
+        if t1 <= t0 {
+            continue;
+        }
+
+        open = if inside(0.5 * (t0 + t1)) {
+            match stretches.last_mut() {
+                Some(last) if open => last.1 = pair[1],
+                _ => stretches.push((pair[0], pair[1])),
+            }
+            true
+        } else {
+            false
+        };
+    }
+
+    stretches
+}
+
+/// Part of a [`Quadric`] a ray crosses.
+#[derive(Debug, Default, Clone, Copy)]
+enum Part {
+    #[default]
+    Side,
+    /// Flat cap at the given height.
+    Cap(Point),
+}
+
+/// Where a ray crosses a [`Quadric`].
+#[derive(Debug, Default, Clone, Copy)]
+struct Crossing {
+    t: Point,
+    part: Part,
+}
+
+/// Surface of revolution about the local `z` axis whose squared radius is a polynomial in the
+/// height, `x² + y² = q₂z² + q₁z + q₀`. That covers cylinders, cones, paraboloids and
+/// hyperboloids of one sheet, which have a constructor each.
+///
+/// The surface can be cut off below and above, swept only part of the way around, and closed
+/// with flat caps at the cuts. Its surface coordinates follow the azimuth for `u` and the height
+/// for `v`, and on the caps `v` runs out from the axis.
+#[derive(Debug, Clone)]
+pub struct Quadric {
+    frame: Frame,
+    /// Coefficients of the squared radius, lowest degree first.
+    profile: [Point; 3],
+    z: Interval,
+    phi_max: Point,
+    capped: bool,
+    mat: Rc<dyn Material>,
+}
+
+impl Quadric {
+    fn new(frame: Frame, profile: [Point; 3], z: Interval, mat: Rc<dyn Material>) -> Self {
+        Quadric {
+            frame,
+            profile,
+            z,
+            phi_max: 2.0 * PI,
+            capped: false,
+            mat,
+        }
+    }
+
+    /// Creates a cylinder of `radius` standing `height` tall on the frame origin.
+    pub fn cylinder(frame: Frame, radius: Point, height: Point, mat: Rc<dyn Material>) -> Self {
+        Self::new(
+            frame,
+            [radius * radius, 0.0, 0.0],
+            Interval::from(0.0, height),
+            mat,
+        )
+    }
+
+    /// Creates a cone with a base of `radius` on the frame origin and its apex `height` above.
+    pub fn cone(frame: Frame, radius: Point, height: Point, mat: Rc<dyn Material>) -> Self {
+        let k = (radius / height).powi(2);
+        Self::new(
+            frame,
+            [k * height * height, -2.0 * k * height, k],
+            Interval::from(0.0, height),
+            mat,
+        )
+    }
+
+    /// Creates a paraboloid with its apex on the frame origin, opening up to `radius` at
+    /// `height`.
+    pub fn paraboloid(frame: Frame, radius: Point, height: Point, mat: Rc<dyn Material>) -> Self {
+        Self::new(
+            frame,
+            [0.0, radius * radius / height, 0.0],
+            Interval::from(0.0, height),
+            mat,
+        )
+    }
+
+    /// Creates a hyperboloid of one sheet whose narrowest circle, of radius `waist`, lies on the
+    /// frame origin, widening to `radius` at `height` above and below.
+    pub fn hyperboloid(
+        frame: Frame,
+        waist: Point,
+        radius: Point,
+        height: Point,
+        mat: Rc<dyn Material>,
+    ) -> Self {
+        Self::new(
+            frame,
+            [
+                waist * waist,
+                0.0,
+                (radius * radius - waist * waist) / (height * height),
+            ],
+            Interval::from(-height, height),
+            mat,
+        )
+    }
+
+    /// Cuts the surface off below `z_min` and above `z_max`, in its own frame.
+    #[must_use]
+    pub fn clip(mut self, z_min: Point, z_max: Point) -> Self {
+        self.z = Interval::overlap(&self.z, &Interval::from(z_min, z_max));
+        self
+    }
+
+    /// Sweeps the surface only `phi_max` radians around the axis, from the local `x` axis.
+    #[must_use]
+    pub fn sweep(mut self, phi_max: Point) -> Self {
+        self.phi_max = phi_max.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// Closes the surface with flat caps where it is cut off. Swept all the way around, a capped
+    /// surface can be made [`closed`](Self::closed).
+    #[must_use]
+    pub const fn capped(mut self, capped: bool) -> Self {
+        self.capped = capped;
+        self
+    }
+
+    /// Makes the surface a [`Solid`].
+    ///
+    /// # Errors
+    ///
+    /// Fails unless the surface is capped and swept all the way around.
+    pub fn closed(self) -> Result<Closed<Self>> {
+        if !self.capped || self.phi_max < 2.0 * PI {
+            return Err(Error::InvalidParameter(
+                "only a capped quadric swept all the way around is closed".to_owned(),
+            ));
+        }
+
+        Ok(Closed(self))
+    }
+
+    /// Squared radius of the surface at height `z`.
+    fn radius_squared(&self, z: Point) -> Point {
+        let [q0, q1, q2] = self.profile;
+        (q2 * z + q1) * z + q0
+    }
+
+    /// Whether the local point `p` is inside the surface and between its caps.
+    fn encloses(&self, p: &Point3) -> bool {
+        self.z.contains(p.z()) && p.x() * p.x() + p.y() * p.y() <= self.radius_squared(p.z())
+    }
+
+    /// Gradient of `x² + y² - r(z)²`, pointing out of the surface.
+    fn gradient(&self, p: &Point3) -> Vec3 {
+        let [_, q1, q2] = self.profile;
+        Vec3::from_scalars(2.0 * p.x(), 2.0 * p.y(), -(2.0 * q2 * p.z() + q1))
+    }
+
+    /// Where the line through the local ray `r` crosses the surface and its caps, in order
+    /// along it, with how many there are.
+    #[allow(clippy::many_single_char_names)]
+    fn crossings(&self, r: &Ray) -> ([Crossing; 4], usize) {
+        let (o, d) = (r.origin(), r.direction());
+        let [_, q1, q2] = self.profile;
+        let mut crossings = [Crossing::default(); 4];
+        let mut count = 0;
+
+        let swept = |p: &Point3| azimuth(p) <= self.phi_max;
+
+        let a = d.x() * d.x() + d.y() * d.y() - q2 * d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y() - q2 * o.z() * d.z()) - q1 * d.z();
+        let c = o.x() * o.x() + o.y() * o.y() - self.radius_squared(o.z());
+        let (roots, real) = quadratic(a, b, c);
+        for t in roots.into_iter().take(real) {
+            let p = r.at(t);
+            if self.z.contains(p.z()) && swept(&p) {
+                crossings[count] = Crossing {
+                    t,
+                    part: Part::Side,
+                };
+                count += 1;
+            }
+        }
+
+        if self.capped && d.z() != 0.0 {
+            for height in [self.z.min, self.z.max] {
+                let t = (height - o.z()) / d.z();
+                // A cap shrunk to the apex is crossed on the side already
+                let radius_squared = self.radius_squared(height);
+                let p = r.at(t);
+                if radius_squared > 0.0
+                    && p.x() * p.x() + p.y() * p.y() <= radius_squared
+                    && swept(&p)
+                {
+                    crossings[count] = Crossing {
+                        t,
+                        part: Part::Cap(height),
+                    };
+                    count += 1;
+                }
+            }
+        }
+
+        crossings[..count].sort_by(|a, b| a.t.total_cmp(&b.t));
+        (crossings, count)
+    }
+
+    /// Fills in `rec` for `r`, which is `local` in the frame of the surface, crossing it at
+    /// `crossing`.
+    fn record(&self, r: &Ray, local: &Ray, crossing: Crossing, rec: &mut Hit) {
+        let mut p = local.at(crossing.t);
+        let (normal, error, v) = match crossing.part {
+            Part::Side => {
+                // Cancellation in the coefficients leaves the root off along the ray, so the
+                // point takes a Newton step on x² + y² - r(z)² along the gradient
+                let gradient = self.gradient(&p);
+                let length_squared = gradient.lenght_squared();
+                if length_squared > 0.0 {
+                    let f = p.x() * p.x() + p.y() * p.y() - self.radius_squared(p.z());
+                    p -= (f / length_squared) * gradient;
+                }
+
+                // What's left is the rounding in evaluating the surface, over its slope
+                let [q0, q1, q2] = self.profile;
+                let terms = p.x() * p.x()
+                    + p.y() * p.y()
+                    + (q2 * p.z() * p.z()).abs()
+                    + (q1 * p.z()).abs()
+                    + q0.abs();
+                let off = if length_squared > 0.0 {
+                    gamma(7) * terms / length_squared.sqrt()
+                } else {
+                    0.0
+                };
+                let error = Vec3::from(off) + gamma(4) * p.abs();
+
+                // The gradient vanishes at the apex of a cone, where the axis stands in for it
+                let mut normal = self.gradient(&p);
+                if normal.lenght_squared() == 0.0 {
+                    let upper = p.z() > self.z.min + 0.5 * self.z.size();
+                    normal = Vec3::from_scalars(0, 0, if upper { 1 } else { -1 });
+                }
+
+                let v = (p.z() - self.z.min) / self.z.size();
+                (normal, error, v)
+            }
+            Part::Cap(height) => {
+                p.points[2] = height;
+                let mut error =
+                    gamma(5) * (local.origin().abs() + (crossing.t * *local.direction()).abs());
+                error.points[2] = 0.0;
+
+                let side = if height < self.z.max { -1 } else { 1 };
+                let radius_squared = p.x() * p.x() + p.y() * p.y();
+                let v = (radius_squared / self.radius_squared(height)).sqrt();
+                (Vec3::from_scalars(0, 0, side), error, v)
+            }
+        };
+
+        rec.t = crossing.t;
+        (rec.p, rec.error) = self.frame.point(&p, &error);
+        rec.set_face_normal(r, &self.frame.vector(&normal).unit_vector());
+        rec.tangent = self.frame.vector(&around(&p));
+        (rec.u, rec.v) = (azimuth(&p) / self.phi_max, v);
+        rec.barycentric = None;
+        rec.mat = Some(self.mat.clone());
+    }
+}
+
+impl Hittable for Quadric {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        stats::primitive_test();
+        let local = self.frame.to_local(r);
+        let (crossings, count) = self.crossings(&local);
+
+        match crossings[..count].iter().find(|c| ray_t.surrounds(c.t)) {
+            Some(&crossing) => {
+                self.record(r, &local, crossing, rec);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [_, q1, q2] = self.profile;
+        let mut widest = self
+            .radius_squared(self.z.min)
+            .max(self.radius_squared(self.z.max));
+        if q2 < 0.0 && self.z.surrounds(-q1 / (2.0 * q2)) {
+            widest = widest.max(self.radius_squared(-q1 / (2.0 * q2)));
+        }
+
+        let radius = widest.max(0.0).sqrt();
+        self.frame.bound(&Aabb::from_points(
+            &Point3::from_scalars(-radius, -radius, self.z.min),
+            &Point3::from_scalars(radius, radius, self.z.max),
+        ))
+    }
+}
+
+impl Solid for Closed<Quadric> {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        stats::primitive_test();
+        let quadric = &self.0;
+        let local = quadric.frame.to_local(r);
+        let (crossings, count) = quadric.crossings(&local);
+
+        inside_between(
+            &crossings[..count],
+            |c| c.t,
+            |t| quadric.encloses(&local.at(t)),
+        )
+        .into_iter()
+        .map(|(enter, exit)| {
+            let mut span = Span {
+                enter: Hit::new(),
+                exit: Hit::new(),
+            };
+            quadric.record(r, &local, enter, &mut span.enter);
+            quadric.record(r, &local, exit, &mut span.exit);
+            span
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::Color, csg::Csg, cuboid::Cuboid, helpers::Generator, material::Lambertian};
+
+    const SCALES: [Point; 4] = [1e-3, 1.0, 1e3, 1e5];
+
+    fn hits(object: &dyn Hittable, r: &Ray, rec: &mut Hit) -> bool {
+        object.hit(r, Interval::from(0.0, Point::INFINITY), rec)
+    }
+
+    fn upright() -> Frame {
+        Frame::new(&Point3::new(), &Vec3::from_scalars(0, 0, 1))
+    }
+
+    /// The four kinds, with their axis along `axis` from `origin` and sized by `scale`.
+    fn shapes(origin: &Point3, axis: &Vec3, scale: Point) -> [Quadric; 4] {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let frame = Frame::new(origin, axis);
+        [
+            Quadric::cylinder(frame, scale, 2.0 * scale, material.clone()),
+            Quadric::cone(frame, scale, 2.0 * scale, material.clone()),
+            Quadric::paraboloid(frame, scale, 2.0 * scale, material.clone()),
+            Quadric::hyperboloid(frame, 0.5 * scale, scale, scale, material.clone()),
+        ]
+    }
+
+    #[test]
+    fn hits_lie_on_the_surface() {
+        Generator::seed(17);
+        let tolerance = 1e3 * Point::EPSILON;
+        let origin = Point3::from_scalars(1, -2, 0.5);
+
+        for shape in shapes(&origin, &Vec3::from_scalars(1, 2, 3), 1.0) {
+            let bbox = shape.bounding_box();
+            let (center, radius) = bbox.bounding_sphere();
+            let mut count = 0;
+
+            for _ in 0..2000 {
+                let start = center + 3.0 * radius * Vec3::random_unit_vector();
+                let target =
+                    center + radius * Generator::random_point() * Vec3::random_unit_vector();
+                let r = Ray::new(start, target - start);
+                let mut rec = Hit::new();
+                if !hits(&shape, &r, &mut rec) {
+                    continue;
+                }
+                count += 1;
+
+                let p = shape.frame.basis.to_local(&(rec.p - origin));
+                let residual = p.x() * p.x() + p.y() * p.y() - shape.radius_squared(p.z());
+                assert!(
+                    residual.abs() < tolerance,
+                    "{shape:?}: {p:?} is off by {residual}"
+                );
+                assert!((0..3).all(|n| {
+                    let axis = bbox.axis(n);
+                    axis.min - tolerance <= rec.p[n] && rec.p[n] <= axis.max + tolerance
+                }));
+
+                let normal = shape.frame.basis.to_local(&rec.normal);
+                let gradient = shape.gradient(&p).unit_vector();
+                assert!((normal.dot(&gradient).abs() - 1.0).abs() < tolerance);
+                assert!(rec.normal.dot(r.direction()) <= 0.0);
+                assert!((-tolerance..=1.0 + tolerance).contains(&rec.u));
+                assert!((-tolerance..=1.0 + tolerance).contains(&rec.v));
+            }
+
+            assert!(count > 200, "{shape:?}: only {count} rays hit");
+        }
+    }
+
+    #[test]
+    fn sweep_clip_and_caps() {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let cylinder = Quadric::cylinder(upright(), 1.0, 2.0, material.clone());
+        let across = Ray::new(Point3::from_scalars(0, -5, 1), Vec3::from_scalars(0, 1, 0));
+        let down = Ray::new(Point3::from_scalars(0, 0, 5), Vec3::from_scalars(0, 0, -1));
+        let mut rec = Hit::new();
+
+        // Half swept, the side facing the ray is gone and the far side is seen from inside
+        assert!(hits(&cylinder.clone().sweep(PI), &across, &mut rec));
+        assert!((rec.t - 6.0).abs() < 1e-6);
+        assert!(!rec.front_face);
+        assert!((rec.u - 0.5).abs() < 1e-6);
+
+        assert!(!hits(&cylinder, &down, &mut rec));
+        assert!(hits(&cylinder.clone().capped(true), &down, &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::from_scalars(0, 0, 1)).lenght() < 1e-6);
+
+        let clipped = cylinder.clip(0.5, 1.5).capped(true);
+        assert!(hits(&clipped, &down, &mut rec));
+        assert!((rec.t - 3.5).abs() < 1e-6);
+        assert!(rec.v.abs() < 1e-6);
+    }
+
+    #[test]
+    fn along_the_axis() {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let down = Ray::new(Point3::from_scalars(0, 0, 5), Vec3::from_scalars(0, 0, -1));
+        let up = Ray::new(Point3::from_scalars(0, 0, -5), Vec3::from_scalars(0, 0, 1));
+        let spans = |solid: &Closed<Quadric>, r: &Ray| -> Vec<(Point, Point)> {
+            let spans = solid.spans(r);
+            for span in &spans {
+                assert!(span.enter.front_face && !span.exit.front_face);
+                assert!(span.enter.normal.lenght().is_finite());
+                assert!(span.exit.normal.lenght().is_finite());
+            }
+            spans.iter().map(|s| (s.enter.t, s.exit.t)).collect()
+        };
+        let close = |found: &[(Point, Point)], expected: &[(Point, Point)]| {
+            found.len() == expected.len()
+                && found
+                    .iter()
+                    .zip(expected)
+                    .all(|(a, b)| (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6)
+        };
+        let mut rec = Hit::new();
+
+        // Straight down onto the apex, where the surface has no slope of its own
+        let cone = Quadric::cone(upright(), 1.0, 2.0, material.clone()).capped(true);
+        assert!(hits(&cone, &down, &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::from_scalars(0, 0, 1)).lenght() < 1e-6);
+
+        let cone = cone.closed().unwrap();
+        let found = spans(&cone, &down);
+        assert!(close(&found, &[(3.0, 5.0)]), "{found:?}");
+        let found = spans(&cone, &up);
+        assert!(close(&found, &[(5.0, 7.0)]), "{found:?}");
+
+        let paraboloid = Quadric::paraboloid(upright(), 1.0, 2.0, material.clone()).capped(true);
+        assert!(hits(&paraboloid, &up, &mut rec));
+        assert!((rec.t - 5.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::from_scalars(0, 0, -1)).lenght() < 1e-6);
+
+        let paraboloid = paraboloid.closed().unwrap();
+        let found = spans(&paraboloid, &up);
+        assert!(close(&found, &[(5.0, 7.0)]), "{found:?}");
+        let found = spans(&paraboloid, &down);
+        assert!(close(&found, &[(3.0, 5.0)]), "{found:?}");
+    }
+
+    #[test]
+    fn spawned_rays_leave_convex_quadrics() {
+        Generator::seed(19);
+
+        for scale in SCALES {
+            let origin = scale * Vec3::random_interval(Interval::from(-10, 10));
+            let axis = Vec3::random_unit_vector();
+            // Hyperboloids curve both ways, so rays leaving them can come back
+            for shape in shapes(&origin, &axis, scale).into_iter().take(3) {
+                let (center, radius) = shape.bounding_box().bounding_sphere();
+
+                for _ in 0..500 {
+                    let start = center + 3.0 * radius * Vec3::random_unit_vector();
+                    let target = center + 0.5 * radius * Vec3::random_unit_vector();
+                    let mut rec = Hit::new();
+                    if !hits(&shape, &Ray::new(start, target - start), &mut rec) || !rec.front_face
+                    {
+                        continue;
+                    }
+
+                    let away = rec.normal.random_on_hemisphere();
+                    assert!(!hits(&shape, &rec.spawn_ray(&away), &mut Hit::new()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn drilled_block() {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let block = Rc::new(Cuboid::new(
+            &Point3::from(-1),
+            &Point3::from(1),
+            material.clone(),
+        ));
+        let frame = Frame::new(
+            &Point3::from_scalars(0, 0, -2),
+            &Vec3::from_scalars(0, 0, 1),
+        );
+        let drill = Quadric::cylinder(frame, 0.5, 4.0, material.clone());
+        assert!(drill.clone().closed().is_err());
+        let drill = Rc::new(drill.capped(true).closed().unwrap());
+        let part = Csg::difference(block, drill);
+        let mut rec = Hit::new();
+
+        let down = |x: Point| Ray::new(Point3::from_scalars(x, 0, 5), Vec3::from_scalars(0, 0, -1));
+        assert!(!hits(&part, &down(0.0), &mut rec));
+        assert!(hits(&part, &down(0.75), &mut rec));
+        assert!((rec.t - 4.0).abs() < 1e-6);
+
+        let across = Ray::new(Point3::from_scalars(-5, 0, 0), Vec3::from_scalars(1, 0, 0));
+        let spans = part.spans(&across);
+        assert_eq!(spans.len(), 2);
+        assert!((spans[0].exit.t - 4.5).abs() < 1e-6);
+        assert!((spans[1].enter.t - 5.5).abs() < 1e-6);
+        assert!(spans[1].enter.front_face);
+        assert!((spans[1].enter.normal - Vec3::from_scalars(-1, 0, 0)).lenght() < 1e-6);
+    }
+}
@@ -1,24 +1,154 @@
-use crate::vec3::{Point, Point3, Vec3};
+use crate::vec3::{Point, Point3, Scalar, Vec3};
 
+/// Fraction of the distance to a point that rays testing the way to it stop short of, so the
+/// surface it lies on can't block it.
+pub const SHADOW_EPSILON: Point = 1e-4;
+
+/// Bound on the relative rounding error built up by `n` floating point operations in a row,
+/// the γₙ of Higham's *Accuracy and Stability of Numerical Algorithms*.
+#[must_use]
+pub fn gamma(n: u8) -> Point {
+    let n = n.to_point() * Point::EPSILON * 0.5;
+    n / (1.0 - n)
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
 }
 
 impl Ray {
+    #[must_use]
     pub const fn new(origin: Point3, direction: Vec3) -> Self {
         Ray { origin, direction }
     }
 
+    /// Ray leaving `p` along `direction`, where `p` was computed to within `error` on a surface
+    /// with geometric `normal`. The origin is pushed along the normal, to the side the ray
+    /// leaves by, past every point `p` could stand for, so the ray can't hit the surface it
+    /// starts on.
+    #[must_use]
+    pub fn spawn(p: &Point3, error: &Vec3, normal: &Vec3, direction: &Vec3) -> Self {
+        let distance = normal.abs().dot(error);
+        let offset = if direction.dot(normal) < 0.0 {
+            -distance * *normal
+        } else {
+            distance * *normal
+        };
+
+        // Adding the offset rounds as well, so step one more representable value away
+        let mut origin = *p + offset;
+        for (coordinate, offset) in origin.points.iter_mut().zip(offset.points) {
+            if offset > 0.0 {
+                *coordinate = coordinate.next_up();
+            } else if offset < 0.0 {
+                *coordinate = coordinate.next_down();
+            }
+        }
+
+        Ray::new(origin, *direction)
+    }
+
+    #[must_use]
     pub const fn origin(&self) -> &Point3 {
         &self.origin
     }
 
+    #[must_use]
     pub const fn direction(&self) -> &Vec3 {
         &self.direction
     }
 
+    #[must_use]
     pub fn at(&self, t: Point) -> Point3 {
         self.origin + t * self.direction
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{
+        color::Color,
+        helpers::Generator,
+        hit::{Hit, Hittable},
+        interval::Interval,
+        material::Lambertian,
+        sphere::Sphere,
+        triangle::Triangle,
+    };
+
+    const SCALES: [Point; 4] = [1e-3, 1.0, 1e3, 1e5];
+
+    fn hits(object: &dyn Hittable, r: &Ray, rec: &mut Hit) -> bool {
+        object.hit(r, Interval::from(0.0, Point::INFINITY), rec)
+    }
+
+    #[test]
+    fn spawned_rays_leave_spheres() {
+        Generator::seed(5);
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+
+        for scale in SCALES {
+            let center = scale * Vec3::random_interval(Interval::from(-10, 10));
+            let sphere = Sphere::new(&center, scale, material.clone());
+
+            for _ in 0..1000 {
+                let origin = center + 3.0 * scale * Vec3::random_unit_vector();
+                let mut rec = Hit::new();
+                assert!(hits(&sphere, &Ray::new(origin, center - origin), &mut rec));
+
+                let away = rec.normal.random_on_hemisphere();
+                assert!(!hits(&sphere, &rec.spawn_ray(&away), &mut Hit::new()));
+
+                // Through a point well inside, so the far side is at least half a radius away
+                let inside = center + 0.5 * scale * Vec3::random_unit_vector() - rec.p;
+                let mut far = Hit::new();
+                assert!(hits(&sphere, &rec.spawn_ray(&inside), &mut far));
+                assert!((far.p - rec.p).lenght() > 0.4 * scale);
+            }
+        }
+    }
+
+    #[test]
+    fn spawned_rays_leave_triangles() {
+        Generator::seed(9);
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+
+        for scale in SCALES {
+            let center = scale * Vec3::random_interval(Interval::from(-10, 10));
+            let vertices =
+                [0, 1, 2].map(|_| center + scale * Vec3::random_interval(Interval::from(-1, 1)));
+            let triangle =
+                Triangle::new(&vertices[0], &vertices[1], &vertices[2], material.clone());
+
+            for _ in 0..1000 {
+                let origin = center + 3.0 * scale * Vec3::random_unit_vector();
+                let mut rec = Hit::new();
+                let (u, v) = (Generator::random_point(), Generator::random_point());
+                let (u, v) = if u + v > 1.0 {
+                    (1.0 - u, 1.0 - v)
+                } else {
+                    (u, v)
+                };
+                let target =
+                    vertices[0] + u * (vertices[1] - vertices[0]) + v * (vertices[2] - vertices[0]);
+                if !hits(&triangle, &Ray::new(origin, target - origin), &mut rec) {
+                    continue;
+                }
+
+                for _ in 0..10 {
+                    let direction = Vec3::random_unit_vector();
+                    assert!(!hits(
+                        &triangle,
+                        &rec.spawn_ray(&direction),
+                        &mut Hit::new()
+                    ));
+                }
+            }
+        }
+    }
+}
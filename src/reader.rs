@@ -0,0 +1,392 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    color::Color,
+    error::{Error, Result},
+    vec3::Point,
+};
+
+/// Linear, high dynamic range image stored row by row from the top.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl HdrImage {
+    /// Loads a Radiance `.hdr`, a `.pfm` or a `.ppm` image, picking the decoder from the
+    /// extension.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read, has another extension or can't be decoded.
+    pub fn open(path: &str) -> Result<Self> {
+        let path = Path::new(path);
+        let bytes = fs::read(path).map_err(|source| Error::io(path, source))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("hdr") => Self::decode_hdr(&bytes),
+            Some(ext) if ext.eq_ignore_ascii_case("pfm") => Self::decode_pfm(&bytes),
+            Some(ext) if ext.eq_ignore_ascii_case("ppm") => Self::decode_ppm(&bytes),
+            _ => Err(invalid(
+                "unsupported image extension, expected .hdr, .pfm or .ppm",
+            )),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Decodes a Radiance RGBE image, with or without run length encoded scanlines.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `bytes` isn't a well formed RGBE image.
+    pub fn decode_hdr(bytes: &[u8]) -> Result<Self> {
+        let mut pos = 0;
+
+        let magic = next_line(bytes, &mut pos)?;
+        if !magic.starts_with("#?") {
+            return Err(invalid("missing Radiance header"));
+        }
+
+        loop {
+            let line = next_line(bytes, &mut pos)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(invalid("only 32-bit_rle_rgbe is supported"));
+                }
+            }
+        }
+
+        let resolution = next_line(bytes, &mut pos)?;
+        let tokens: Vec<&str> = resolution.split_whitespace().collect();
+        let (height, width) = match tokens.as_slice() {
+            ["-Y", h, "+X", w] => (parse_dim(h)?, parse_dim(w)?),
+            _ => return Err(invalid("only -Y +X oriented images are supported")),
+        };
+
+        // A run packs at most 127 pixels of a channel into two bytes, so no more pixels than
+        // this can follow the header
+        let most = (bytes.len() - pos).saturating_mul(64);
+        if width.checked_mul(height).is_none_or(|size| size > most) {
+            return Err(invalid("image dimensions larger than its data"));
+        }
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut scanline = vec![[0u8; 4]; width];
+
+        for _ in 0..height {
+            read_scanline(bytes, &mut pos, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|rgbe| rgbe_to_color(*rgbe)));
+        }
+
+        Ok(HdrImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes a portable float map, either RGB (`PF`) or greyscale (`Pf`).
+    ///
+    /// # Errors
+    ///
+    /// Fails when `bytes` isn't a well formed float map.
+    pub fn decode_pfm(bytes: &[u8]) -> Result<Self> {
+        let mut pos = 0;
+
+        let channels = match next_token(bytes, &mut pos)? {
+            "PF" => 3,
+            "Pf" => 1,
+            _ => return Err(invalid("missing PFM header")),
+        };
+        let width = parse_dim(next_token(bytes, &mut pos)?)?;
+        let height = parse_dim(next_token(bytes, &mut pos)?)?;
+        let scale: f32 = next_token(bytes, &mut pos)?
+            .parse()
+            .map_err(|_| invalid("invalid PFM scale"))?;
+        // A single whitespace character separates the header from the data
+        pos += 1;
+
+        let little_endian = scale < 0.0;
+        let data = data(bytes, pos, [width, height, channels, 4])
+            .ok_or_else(|| invalid("truncated PFM data"))?;
+
+        let floats: Vec<Point> = data
+            .chunks_exact(4)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                Point::from(if little_endian {
+                    f32::from_le_bytes(b)
+                } else {
+                    f32::from_be_bytes(b)
+                })
+            })
+            .collect();
+
+        // Rows are stored from the bottom of the image up
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in floats.chunks_exact(width * channels).rev() {
+            pixels.extend(row.chunks_exact(channels).map(|c| {
+                if channels == 3 {
+                    Color::from_slice([c[0], c[1], c[2]])
+                } else {
+                    Color::from(c[0])
+                }
+            }));
+        }
+
+        Ok(HdrImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Decodes a portable pixmap, either plain (`P3`) or binary (`P6`). Values are taken to be
+    /// gamma 2 encoded, as the writer stores them, and each byte to stand for the middle of the
+    /// range it was rounded from.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `bytes` isn't a well formed pixmap.
+    pub fn decode_ppm(bytes: &[u8]) -> Result<Self> {
+        let mut pos = 0;
+
+        let binary = match next_ppm_token(bytes, &mut pos)? {
+            "P3" => false,
+            "P6" => true,
+            _ => return Err(invalid("missing PPM header")),
+        };
+        let width = parse_dim(next_ppm_token(bytes, &mut pos)?)?;
+        let height = parse_dim(next_ppm_token(bytes, &mut pos)?)?;
+        let max: u16 = match next_ppm_token(bytes, &mut pos)?.parse() {
+            Ok(max) if max > 0 => max,
+            _ => return Err(invalid("invalid PPM maximum value")),
+        };
+
+        let values: Vec<u16> = if binary {
+            // A single whitespace character separates the header from the data
+            pos += 1;
+            let size = if max < 256 { 1 } else { 2 };
+            let data = data(bytes, pos, [width, height, 3, size])
+                .ok_or_else(|| invalid("truncated PPM data"))?;
+            if size == 1 {
+                data.iter().copied().map(u16::from).collect()
+            } else {
+                data.chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                    .collect()
+            }
+        } else {
+            // Every value but the last is followed by at least one whitespace character
+            let count = width
+                .checked_mul(height)
+                .and_then(|size| size.checked_mul(3))
+                .filter(|&count| count <= (bytes.len() - pos).div_ceil(2))
+                .ok_or_else(|| invalid("truncated PPM data"))?;
+            (0..count)
+                .map(|_| {
+                    next_token(bytes, &mut pos)
+                        .map_err(|_| invalid("truncated PPM data"))?
+                        .parse()
+                        .map_err(|_| invalid("invalid PPM value"))
+                })
+                .collect::<Result<_>>()?
+        };
+
+        if values.iter().any(|&v| v > max) {
+            return Err(invalid("PPM value above the maximum"));
+        }
+
+        let levels = f64::from(max) + 1.0;
+        let decode = |v: u16| ((f64::from(v) + 0.5) / levels).powi(2);
+        let pixels = values
+            .chunks_exact(3)
+            .map(|c| Color::from_scalars(decode(c[0]), decode(c[1]), decode(c[2])))
+            .collect();
+
+        Ok(HdrImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Decode(message.to_owned())
+}
+
+/// The `sizes.product()` bytes of `bytes` from `pos`, unless the product overflows or runs past
+/// the end.
+fn data(bytes: &[u8], pos: usize, sizes: [usize; 4]) -> Option<&[u8]> {
+    let size = sizes.into_iter().try_fold(1_usize, usize::checked_mul)?;
+    bytes.get(pos..pos.checked_add(size)?)
+}
+
+fn parse_dim(token: &str) -> Result<usize> {
+    match token.parse() {
+        Ok(0) | Err(_) => Err(invalid("invalid image dimensions")),
+        Ok(dim) => Ok(dim),
+    }
+}
+
+fn next_line<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<&'a str> {
+    let rest = bytes.get(*pos..).unwrap_or_default();
+    let end = rest
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| invalid("unexpected end of header"))?;
+    *pos += end + 1;
+
+    std::str::from_utf8(&rest[..end])
+        .map(str::trim)
+        .map_err(|_| invalid("header is not valid text"))
+}
+
+fn next_token<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<&'a str> {
+    while bytes.get(*pos).is_some_and(u8::is_ascii_whitespace) {
+        *pos += 1;
+    }
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+
+    std::str::from_utf8(&bytes[start..*pos])
+        .ok()
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| invalid("unexpected end of header"))
+}
+
+/// Like [`next_token`], also skipping `#` comments, which pixmap headers may contain.
+fn next_ppm_token<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<&'a str> {
+    loop {
+        while bytes.get(*pos).is_some_and(u8::is_ascii_whitespace) {
+            *pos += 1;
+        }
+        if bytes.get(*pos) != Some(&b'#') {
+            return next_token(bytes, pos);
+        }
+        while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+            *pos += 1;
+        }
+    }
+}
+
+fn next_byte(bytes: &[u8], pos: &mut usize) -> Result<u8> {
+    let byte = *bytes
+        .get(*pos)
+        .ok_or_else(|| invalid("truncated pixel data"))?;
+    *pos += 1;
+    Ok(byte)
+}
+
+fn read_scanline(bytes: &[u8], pos: &mut usize, scanline: &mut [[u8; 4]]) -> Result<()> {
+    let width = scanline.len();
+    let header = bytes.get(*pos..*pos + 4).unwrap_or_default();
+
+    let is_rle = (8..0x8000).contains(&width)
+        && header.len() == 4
+        && header[0] == 2
+        && header[1] == 2
+        && header[2] & 0x80 == 0;
+
+    if !is_rle {
+        for pixel in scanline.iter_mut() {
+            for channel in pixel.iter_mut() {
+                *channel = next_byte(bytes, pos)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if (usize::from(header[2]) << 8 | usize::from(header[3])) != width {
+        return Err(invalid("scanline width mismatch"));
+    }
+    *pos += 4;
+
+    // Each channel is stored separately as a sequence of runs and literal spans
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next_byte(bytes, pos)?;
+            if count > 128 {
+                let run = usize::from(count - 128);
+                let value = next_byte(bytes, pos)?;
+                for pixel in scanline
+                    .get_mut(x..x + run)
+                    .ok_or_else(|| invalid("run overflows scanline"))?
+                {
+                    pixel[channel] = value;
+                }
+                x += run;
+            } else {
+                let span = usize::from(count);
+                if span == 0 || x + span > width {
+                    return Err(invalid("invalid literal span"));
+                }
+                for pixel in &mut scanline[x..x + span] {
+                    pixel[channel] = next_byte(bytes, pos)?;
+                }
+                x += span;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new();
+    }
+
+    let f = (f64::from(rgbe[3]) - 136.0).exp2();
+    Color::from_scalars(
+        (f64::from(rgbe[0]) + 0.5) * f,
+        (f64::from(rgbe[1]) + 0.5) * f,
+        (f64::from(rgbe[2]) + 0.5) * f,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn oversized_headers_fail_to_decode() {
+        let hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 99999999 +X 99999999\n\x02\x02";
+        assert!(matches!(HdrImage::decode_hdr(hdr), Err(Error::Decode(_))));
+
+        let huge = usize::MAX / 2;
+        let pfm = format!("PF\n{huge} {huge}\n-1.0\n");
+        assert!(matches!(
+            HdrImage::decode_pfm(pfm.as_bytes()),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            HdrImage::decode_pfm(b"PF\n2 2\n-1.0\n\0\0\0\0"),
+            Err(Error::Decode(_))
+        ));
+
+        for ppm in [
+            format!("P6\n{huge} {huge}\n255\n"),
+            format!("P3\n{huge} 1\n255\n0 0 0\n"),
+        ] {
+            assert!(matches!(
+                HdrImage::decode_ppm(ppm.as_bytes()),
+                Err(Error::Decode(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{helpers::Generator, vec3::Point};
+
+/// Source of the uniform numbers a path is built from, so integrators can be driven by
+/// something other than independent random numbers.
+pub trait Sampler {
+    /// Next number in `[0, 1)`.
+    fn get_1d(&mut self) -> Point;
+
+    fn get_2d(&mut self) -> [Point; 2] {
+        [self.get_1d(), self.get_1d()]
+    }
+}
+
+impl Sampler for Generator {
+    fn get_1d(&mut self) -> Point {
+        Self::random_point()
+    }
+}
@@ -0,0 +1,240 @@
+use crate::vec3::{consts::PI, Point, Vec3};
+
+/// Piecewise-constant 1D distribution built from a tabulated function.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Distribution1D {
+    func: Vec<Point>,
+    cdf: Vec<Point>,
+    func_int: Point,
+}
+
+impl Distribution1D {
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn new(func: &[Point]) -> Self {
+        let n = func.len();
+        let func: Vec<Point> = func.iter().map(|f| f.abs()).collect();
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as Point;
+        }
+
+        let func_int = cdf[n];
+        if func_int > 0.0 {
+            for c in &mut cdf[1..] {
+                *c /= func_int;
+            }
+        } else {
+            // Degenerate function, fall back to a uniform distribution
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as Point / n as Point;
+            }
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    #[must_use]
+    pub const fn func_int(&self) -> Point {
+        self.func_int
+    }
+
+    /// Samples a point in `[0, 1)`, returning it with its density and the index of the bucket it fell in.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn sample_continuous(&self, u: Point) -> (Point, Point, usize) {
+        let n = self.count();
+        let offset = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(n - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let pdf = if self.func_int > 0.0 {
+            self.func[offset] / self.func_int
+        } else {
+            1.0
+        };
+
+        ((offset as Point + du) / n as Point, pdf, offset)
+    }
+}
+
+/// Piecewise-constant 2D distribution over `[0, 1)^2`, sampled as a marginal over rows and a conditional per row.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Builds the distribution from `func`, stored row by row with `nu` columns and `nv` rows.
+    pub fn new(func: &[Point], nu: usize, nv: usize) -> Self {
+        let conditional: Vec<Distribution1D> = func
+            .chunks_exact(nu)
+            .take(nv)
+            .map(Distribution1D::new)
+            .collect();
+
+        let marginal_func: Vec<Point> = conditional.iter().map(Distribution1D::func_int).collect();
+
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    #[must_use]
+    pub fn sample_continuous(&self, u: [Point; 2]) -> ([Point; 2], Point) {
+        let (d1, pdf1, v) = self.marginal.sample_continuous(u[1]);
+        let (d0, pdf0, _) = self.conditional[v].sample_continuous(u[0]);
+
+        ([d0, d1], pdf0 * pdf1)
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    #[must_use]
+    pub fn pdf(&self, p: [Point; 2]) -> Point {
+        let nu = self.conditional[0].count();
+        let nv = self.marginal.count();
+
+        let iu = ((p[0] * nu as Point).max(0.0) as usize).min(nu - 1);
+        let iv = ((p[1] * nv as Point).max(0.0) as usize).min(nv - 1);
+
+        if self.marginal.func_int() > 0.0 {
+            self.conditional[iv].func[iu] / self.marginal.func_int()
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Point on the unit disk, sampled uniformly with the polar mapping.
+#[must_use]
+pub fn uniform_disk_polar(u: [Point; 2]) -> [Point; 2] {
+    let r = u[0].sqrt();
+    let theta = 2.0 * PI * u[1];
+
+    [r * theta.cos(), r * theta.sin()]
+}
+
+/// Point on the unit disk, sampled uniformly with Shirley and Chiu's concentric mapping.
+#[must_use]
+pub fn uniform_disk_concentric(u: [Point; 2]) -> [Point; 2] {
+    let offset = [2.0 * u[0] - 1.0, 2.0 * u[1] - 1.0];
+    if offset[0] == 0.0 && offset[1] == 0.0 {
+        return [0.0, 0.0];
+    }
+
+    let (r, theta) = if offset[0].abs() > offset[1].abs() {
+        (offset[0], PI / 4.0 * (offset[1] / offset[0]))
+    } else {
+        (offset[1], PI / 2.0 - PI / 4.0 * (offset[0] / offset[1]))
+    };
+
+    [r * theta.cos(), r * theta.sin()]
+}
+
+/// Direction on the `+z` hemisphere, sampled proportionally to the cosine with the normal.
+#[must_use]
+pub fn cosine_hemisphere(u: [Point; 2]) -> Vec3 {
+    let d = uniform_disk_concentric(u);
+    let z = (1.0 - d[0] * d[0] - d[1] * d[1]).max(0.0).sqrt();
+
+    Vec3::from_scalars(d[0], d[1], z)
+}
+
+#[must_use]
+pub fn cosine_hemisphere_pdf(cos_theta: Point) -> Point {
+    cos_theta.max(0.0) / PI
+}
+
+/// Direction inside a cone of half angle `acos(cos_max)` around `+z`, sampled uniformly.
+#[must_use]
+pub fn uniform_cone(u: [Point; 2], cos_max: Point) -> Vec3 {
+    let cos_theta = 1.0 - u[0] * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u[1];
+
+    Vec3::from_scalars(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+#[must_use]
+pub fn uniform_cone_pdf(cos_max: Point) -> Point {
+    1.0 / (2.0 * PI * (1.0 - cos_max))
+}
+
+/// Direction on the unit sphere, sampled uniformly.
+#[must_use]
+pub fn uniform_sphere(u: [Point; 2]) -> Vec3 {
+    let z = 1.0 - 2.0 * u[0];
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u[1];
+
+    Vec3::from_scalars(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub const UNIFORM_SPHERE_PDF: Point = 1.0 / (4.0 * PI);
+
+/// Multiple importance sampling weight for a sample drawn from `f_pdf` when `g_pdf` could also have produced it.
+#[must_use]
+pub fn power_heuristic(f_pdf: Point, g_pdf: Point) -> Point {
+    let f = f_pdf * f_pdf;
+    let g = g_pdf * g_pdf;
+
+    if f + g > 0.0 {
+        f / (f + g)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Distribution1D, Distribution2D, Point};
+
+    #[test]
+    fn distribution_1d() {
+        let d = Distribution1D::new(&[1.0, 3.0]);
+
+        let (x, pdf, offset) = d.sample_continuous(0.1);
+        assert_eq!(offset, 0);
+        assert!((x - 0.2).abs() < 1e-12);
+        assert!((pdf - 0.5).abs() < 1e-12);
+
+        let (x, pdf, offset) = d.sample_continuous(0.625);
+        assert_eq!(offset, 1);
+        assert!((x - 0.75).abs() < 1e-12);
+        assert!((pdf - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn distribution_2d_pdf_matches_sample() {
+        let d = Distribution2D::new(&[1.0, 2.0, 0.0, 5.0, 3.0, 1.0], 3, 2);
+
+        for u in [[0.1, 0.2], [0.7, 0.9], [0.5, 0.5]] {
+            let (p, pdf) = d.sample_continuous(u);
+            assert!((d.pdf(p) - pdf).abs() < 1e4 * Point::EPSILON);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! AVX versions of the packet kernels in [`packet`](crate::packet), chosen at runtime when the
+//! processor supports them. With the `simd-vec3` feature the arithmetic of
+//! [`Vec3`](crate::vec3::Vec3) uses SSE2 as well, which is part of x86-64 and needs no check.
+
+// Four lanes either way: doubles fill an AVX register and singles the lower half of one
+#[cfg(feature = "f32")]
+use std::arch::x86_64::{
+    __m128 as Lanes, _mm_add_ps as add_lanes, _mm_and_ps as and, _mm_blendv_ps as blend,
+    _mm_cmp_ps as compare, _mm_div_ps as div, _mm_loadu_ps as load_unaligned, _mm_max_ps as max,
+    _mm_min_ps as min, _mm_movemask_ps as movemask, _mm_mul_ps as mul_lanes, _mm_or_ps as or,
+    _mm_set1_ps as splat, _mm_setzero_ps as zero, _mm_sqrt_ps as sqrt,
+    _mm_storeu_ps as store_unaligned, _mm_sub_ps as sub_lanes,
+};
+#[cfg(not(feature = "f32"))]
+use std::arch::x86_64::{
+    __m256d as Lanes, _mm256_add_pd as add_lanes, _mm256_and_pd as and, _mm256_blendv_pd as blend,
+    _mm256_cmp_pd as compare, _mm256_div_pd as div, _mm256_loadu_pd as load_unaligned,
+    _mm256_max_pd as max, _mm256_min_pd as min, _mm256_movemask_pd as movemask,
+    _mm256_mul_pd as mul_lanes, _mm256_or_pd as or, _mm256_set1_pd as splat,
+    _mm256_setzero_pd as zero, _mm256_sqrt_pd as sqrt, _mm256_storeu_pd as store_unaligned,
+    _mm256_sub_pd as sub_lanes,
+};
+use std::arch::x86_64::{_CMP_GE_OQ, _CMP_GT_OQ, _CMP_LE_OQ, _CMP_LT_OQ};
+
+#[cfg(all(feature = "simd-vec3", feature = "f32"))]
+use std::arch::x86_64::{__m128 as Wide, _mm_setr_ps, _mm_storeu_ps};
+#[cfg(all(feature = "simd-vec3", not(feature = "f32")))]
+use std::arch::x86_64::{__m128d as Wide, _mm_load_sd, _mm_loadu_pd, _mm_store_sd, _mm_storeu_pd};
+#[cfg(all(feature = "simd-vec3", not(feature = "f32")))]
+use std::arch::x86_64::{_mm_add_pd as add_wide, _mm_mul_pd as mul_wide, _mm_sub_pd as sub_wide};
+#[cfg(all(feature = "simd-vec3", feature = "f32"))]
+use std::arch::x86_64::{_mm_add_ps as add_wide, _mm_mul_ps as mul_wide, _mm_sub_ps as sub_wide};
+
+#[cfg(feature = "simd-vec3")]
+use crate::vec3::Vec3;
+use crate::{
+    aabb::Aabb,
+    packet::{Mask, RayPacket, LANES},
+    vec3::{Point, Point3},
+};
+
+/// Whether the processor running the program supports AVX. The answer is cached after the
+/// first call.
+#[must_use]
+pub fn avx() -> bool {
+    std::arch::is_x86_feature_detected!("avx")
+}
+
+/// [`packet::slab`](crate::packet::slab), four lanes at a time.
+///
+/// # Panics
+///
+/// Panics when the processor doesn't support AVX.
+#[must_use]
+pub fn slab(bbox: &Aabb, packet: &RayPacket) -> Mask {
+    assert!(avx());
+    // SAFETY: AVX is available, checked above
+    unsafe { slab_avx(bbox, packet) }
+}
+
+/// [`packet::sphere`](crate::packet::sphere), four lanes at a time.
+///
+/// # Panics
+///
+/// Panics when the processor doesn't support AVX.
+#[must_use]
+pub fn sphere(center: &Point3, radius: Point, packet: &RayPacket) -> ([Point; LANES], Mask) {
+    assert!(avx());
+    // SAFETY: AVX is available, checked above
+    unsafe { sphere_avx(center, radius, packet) }
+}
+
+#[target_feature(enable = "avx")]
+fn slab_avx(bbox: &Aabb, packet: &RayPacket) -> Mask {
+    let mut t_min = load(&packet.t_min);
+    let mut t_max = load(&packet.t_max);
+
+    for n in 0..3 {
+        let axis = bbox.axis(n);
+        let origin = load(&packet.origin[n]);
+        let inverse = load(&packet.inverse[n]);
+        let t0 = mul_lanes(sub_lanes(splat(axis.min), origin), inverse);
+        let t1 = mul_lanes(sub_lanes(splat(axis.max), origin), inverse);
+
+        // The bounds go second so a NaN from a ray lying in a slab plane leaves them alone
+        t_min = max(min(t0, t1), t_min);
+        t_max = min(max(t0, t1), t_max);
+    }
+
+    mask(compare::<_CMP_LE_OQ>(t_min, t_max)) & packet.active
+}
+
+#[target_feature(enable = "avx")]
+fn sphere_avx(center: &Point3, radius: Point, packet: &RayPacket) -> ([Point; LANES], Mask) {
+    let oc = [0, 1, 2].map(|n| sub_lanes(splat(center[n]), load(&packet.origin[n])));
+    let d = [0, 1, 2].map(|n| load(&packet.direction[n]));
+    let dot = |u: &[Lanes; 3], v: &[Lanes; 3]| {
+        add_lanes(
+            add_lanes(mul_lanes(u[0], v[0]), mul_lanes(u[1], v[1])),
+            mul_lanes(u[2], v[2]),
+        )
+    };
+
+    let a = dot(&d, &d);
+    let h = dot(&d, &oc);
+    let c = sub_lanes(dot(&oc, &oc), splat(radius * radius));
+    let discriminant = sub_lanes(mul_lanes(h, h), mul_lanes(a, c));
+    let real = compare::<_CMP_GE_OQ>(discriminant, zero());
+
+    let sqrtd = sqrt(max(discriminant, zero()));
+    let near = div(sub_lanes(h, sqrtd), a);
+    let far = div(add_lanes(h, sqrtd), a);
+
+    let (t_min, t_max) = (load(&packet.t_min), load(&packet.t_max));
+    let inside = |t: Lanes| {
+        and(
+            compare::<_CMP_GT_OQ>(t, t_min),
+            compare::<_CMP_LT_OQ>(t, t_max),
+        )
+    };
+    let (near_inside, far_inside) = (inside(near), inside(far));
+
+    let mut roots = [0.0; LANES];
+    // SAFETY: the array has room for the four values written
+    unsafe { store_unaligned(roots.as_mut_ptr(), blend(far, near, near_inside)) };
+    let hit = and(real, or(near_inside, far_inside));
+
+    (roots, mask(hit) & packet.active)
+}
+
+#[target_feature(enable = "avx")]
+fn load(values: &[Point; LANES]) -> Lanes {
+    // SAFETY: the array holds exactly the four values read
+    unsafe { load_unaligned(values.as_ptr()) }
+}
+
+#[target_feature(enable = "avx")]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn mask(comparison: Lanes) -> Mask {
+    movemask(comparison) as Mask
+}
+
+/// Applies `op` to the coordinates of `a` and `b`, all three in one register.
+#[cfg(all(feature = "simd-vec3", feature = "f32"))]
+#[inline]
+fn elementwise(a: &Vec3, b: &Vec3, op: impl Fn(Wide, Wide) -> Wide) -> Vec3 {
+    // SAFETY: SSE is part of x86-64
+    let wide = |v: &Vec3| unsafe { _mm_setr_ps(v[0], v[1], v[2], 0.0) };
+    let mut lanes = [0.0; 4];
+    // SAFETY: the array has room for the four values written
+    unsafe { _mm_storeu_ps(lanes.as_mut_ptr(), op(wide(a), wide(b))) };
+
+    Vec3::from_slice([lanes[0], lanes[1], lanes[2]])
+}
+
+/// Applies `op` to the coordinates of `a` and `b`, the first two in one register and the third
+/// in the low half of another.
+#[cfg(all(feature = "simd-vec3", not(feature = "f32")))]
+#[inline]
+fn elementwise(a: &Vec3, b: &Vec3, op: impl Fn(Wide, Wide) -> Wide) -> Vec3 {
+    let mut result = Vec3::new();
+    let (a, b, out) = (
+        a.points.as_ptr(),
+        b.points.as_ptr(),
+        result.points.as_mut_ptr(),
+    );
+
+    // SAFETY: the pointers cover the three coordinates of each vector, the only ones accessed
+    unsafe {
+        _mm_storeu_pd(out, op(_mm_loadu_pd(a), _mm_loadu_pd(b)));
+        _mm_store_sd(out.add(2), op(_mm_load_sd(a.add(2)), _mm_load_sd(b.add(2))));
+    }
+
+    result
+}
+
+#[cfg(feature = "simd-vec3")]
+#[inline]
+#[must_use]
+pub fn add(a: &Vec3, b: &Vec3) -> Vec3 {
+    // SAFETY: SSE2 is part of x86-64
+    elementwise(a, b, |a, b| unsafe { add_wide(a, b) })
+}
+
+#[cfg(feature = "simd-vec3")]
+#[inline]
+#[must_use]
+pub fn sub(a: &Vec3, b: &Vec3) -> Vec3 {
+    // SAFETY: SSE2 is part of x86-64
+    elementwise(a, b, |a, b| unsafe { sub_wide(a, b) })
+}
+
+#[cfg(feature = "simd-vec3")]
+#[inline]
+#[must_use]
+pub fn mul(a: &Vec3, b: &Vec3) -> Vec3 {
+    // SAFETY: SSE2 is part of x86-64
+    elementwise(a, b, |a, b| unsafe { mul_wide(a, b) })
+}
+
+#[cfg(all(test, feature = "simd-vec3"))]
+mod test {
+    use super::*;
+    use crate::{helpers::Generator, interval::Interval};
+
+    // Both sides round the same operations, so they agree exactly
+    #[allow(clippy::float_cmp)]
+    #[test]
+    fn vec3_arithmetic_matches_scalar() {
+        Generator::seed(5);
+        for _ in 0..1000 {
+            let a = Vec3::random_interval(Interval::from(-10, 10));
+            let b = Vec3::random_interval(Interval::from(-10, 10));
+            let each = |op: fn(Point, Point) -> Point| [0, 1, 2].map(|n| op(a[n], b[n]));
+
+            assert_eq!(add(&a, &b).points, each(|x, y| x + y));
+            assert_eq!(sub(&a, &b).points, each(|x, y| x - y));
+            assert_eq!(mul(&a, &b).points, each(|x, y| x * y));
+        }
+    }
+}
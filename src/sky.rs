@@ -0,0 +1,249 @@
+use crate::{
+    color::Color,
+    environment::{EnvSample, Environment},
+    helpers::degrees_to_radians,
+    onb::Onb,
+    sampling::{uniform_cone, uniform_cone_pdf, uniform_sphere, UNIFORM_SPHERE_PDF},
+    vec3::{consts::PI, Point, Scalar, Vec3},
+};
+
+/// Solar illuminance above the atmosphere, in kilolux.
+const SUN_ILLUMINANCE: Point = 128.0;
+
+/// Wavelengths, in micrometers, standing in for the red, green and blue channels.
+const CHANNEL_WAVELENGTHS: [Point; 3] = [0.61, 0.55, 0.465];
+
+/// Coefficients of the Perez et al. sky luminance distribution.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Perez([Point; 5]);
+
+impl Perez {
+    #[allow(clippy::many_single_char_names)]
+    fn eval(&self, cos_theta: Point, gamma: Point) -> Point {
+        let [a, b, c, d, e] = self.0;
+        let cos_gamma = gamma.cos();
+
+        (1.0 + a * (b / cos_theta).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Analytic daylight from Preetham et al., "A Practical Analytic Model for Daylight",
+/// with an explicitly sampled sun disk and a diffuse ground below the horizon.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SunSky {
+    /// Scale from photometric units (kcd/m²) to scene radiance.
+    pub intensity: Point,
+    sun_direction: Vec3,
+    sun_frame: Onb,
+    cos_sun_max: Point,
+    sun_radiance: Color,
+    /// Perez coefficients for luminance and the two chromaticities.
+    perez: [Perez; 3],
+    /// Zenith value of each channel divided by its Perez function at the zenith.
+    scale: [Point; 3],
+    ground: Color,
+    sun_probability: Point,
+}
+
+impl SunSky {
+    /// Creates a new [`SunSky`] lit by a sun of `sun_diameter` degrees towards `sun_direction`.
+    /// `turbidity` ranges from 2 (very clear) to 10 (hazy).
+    #[must_use]
+    pub fn new(
+        sun_direction: &Vec3,
+        turbidity: Point,
+        ground_albedo: Color,
+        sun_diameter: Point,
+    ) -> Self {
+        let t = turbidity.clamp(1.7, 10.0);
+        let sun_direction = sun_direction.unit_vector();
+
+        // The model breaks down past the horizon, keep the sun just above it
+        let theta_s = sun_direction
+            .y()
+            .clamp(-1.0, 1.0)
+            .acos()
+            .min(PI / 2.0 - 1e-3);
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let powers = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let poly = |coefficients: [[Point; 4]; 3]| -> Point {
+            let [t2, t1, t0] =
+                coefficients.map(|c| c.iter().zip(powers).map(|(c, p)| c * p).sum::<Point>());
+            t * t * t2 + t * t1 + t0
+        };
+        let zenith_x = poly([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = poly([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let scale = [0, 1, 2].map(|i| zenith[i] / perez[i].eval(1.0, theta_s));
+
+        let cos_sun_max = degrees_to_radians(sun_diameter / 2.0).cos();
+        let sun_radiance = if sun_direction.y() > 0.0 {
+            Self::sun_transmittance(theta_s, t) * SUN_ILLUMINANCE / (2.0 * PI * (1.0 - cos_sun_max))
+        } else {
+            Color::new()
+        };
+
+        let mut sky = SunSky {
+            intensity: 0.05,
+            sun_direction,
+            sun_frame: Onb::new(&sun_direction),
+            cos_sun_max,
+            sun_radiance,
+            perez,
+            scale,
+            ground: Color::new(),
+            sun_probability: 0.0,
+        };
+
+        // Integrate the sky over the upper hemisphere to light the ground and pick sampling weights
+        let (sky_irradiance, sky_power) = sky.integrate_sky();
+        let sun_irradiance =
+            sun_radiance * (2.0 * PI * (1.0 - cos_sun_max)) * sun_direction.y().max(0.0);
+        sky.ground = ground_albedo * (sky_irradiance + sun_irradiance) / PI;
+
+        let sun_power = sun_radiance.luminance() * 2.0 * PI * (1.0 - cos_sun_max);
+        let total = sun_power + sky_power + sky.ground.luminance() * 2.0 * PI;
+        sky.sun_probability = if sun_power > 0.0 {
+            (sun_power / total).clamp(0.1, 0.9)
+        } else {
+            0.0
+        };
+
+        sky
+    }
+
+    /// Fraction of sunlight reaching the ground through Rayleigh and aerosol scattering.
+    fn sun_transmittance(theta_s: Point, turbidity: Point) -> Color {
+        let relative_mass =
+            1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.046_08 * turbidity - 0.045_86;
+
+        Color::from_slice(CHANNEL_WAVELENGTHS.map(|lambda| {
+            let rayleigh = (-0.008_735 * lambda.powf(-4.08) * relative_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * relative_mass).exp();
+            rayleigh * aerosol
+        }))
+    }
+
+    fn integrate_sky(&self) -> (Color, Point) {
+        const THETA_STEPS: u32 = 32;
+        const PHI_STEPS: u32 = 64;
+        let d_theta = PI / 2.0 / THETA_STEPS.to_point();
+        let d_phi = 2.0 * PI / PHI_STEPS.to_point();
+
+        let mut irradiance = Color::new();
+        let mut power = 0.0;
+
+        for i in 0..THETA_STEPS {
+            let theta = (i.to_point() + 0.5) * d_theta;
+            for j in 0..PHI_STEPS {
+                let phi = (j.to_point() + 0.5) * d_phi;
+                let direction = Vec3::from_scalars(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+
+                let radiance = self.sky_radiance(&direction);
+                let d_omega = theta.sin() * d_theta * d_phi;
+                irradiance += radiance * theta.cos() * d_omega;
+                power += radiance.luminance() * d_omega;
+            }
+        }
+
+        (irradiance, power)
+    }
+
+    /// Radiance of the sky alone, before scaling by the intensity.
+    fn sky_radiance(&self, direction: &Vec3) -> Color {
+        if direction.y() <= 0.0 {
+            return self.ground;
+        }
+
+        let cos_theta = direction.y().max(1e-3);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+        let [luminance, x, y] =
+            [0, 1, 2].map(|i| self.scale[i] * self.perez[i].eval(cos_theta, gamma));
+
+        let color = Color::from_xyz(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        Color::from_slice(color.points.map(|c| c.max(0.0)))
+    }
+}
+
+impl Environment for SunSky {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let direction = direction.unit_vector();
+        let mut radiance = self.sky_radiance(&direction);
+
+        if direction.dot(&self.sun_direction) >= self.cos_sun_max {
+            radiance += self.sun_radiance;
+        }
+
+        radiance * self.intensity
+    }
+
+    fn sample(&self, u: [Point; 2]) -> Option<EnvSample> {
+        // Pick the sun disk or the whole sphere, reusing the first dimension for the chosen strategy
+        let direction = if u[0] < self.sun_probability {
+            let u0 = u[0] / self.sun_probability;
+            self.sun_frame
+                .transform(&uniform_cone([u0, u[1]], self.cos_sun_max))
+        } else {
+            let u0 = (u[0] - self.sun_probability) / (1.0 - self.sun_probability);
+            uniform_sphere([u0, u[1]])
+        };
+
+        Some(EnvSample {
+            direction,
+            radiance: self.radiance(&direction),
+            pdf: self.pdf(&direction),
+        })
+    }
+
+    fn pdf(&self, direction: &Vec3) -> Point {
+        let mut pdf = (1.0 - self.sun_probability) * UNIFORM_SPHERE_PDF;
+
+        if direction.unit_vector().dot(&self.sun_direction) >= self.cos_sun_max {
+            pdf += self.sun_probability * uniform_cone_pdf(self.cos_sun_max);
+        }
+
+        pdf
+    }
+}
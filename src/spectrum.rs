@@ -0,0 +1,743 @@
+use std::ops::{Add, AddAssign, Div, Mul};
+use std::sync::OnceLock;
+
+use crate::{
+    color::Color,
+    vec3::{Point, Vec3},
+};
+
+/// Shortest wavelength in nanometers that paths are traced at.
+pub const LAMBDA_MIN: Point = 360.0;
+/// Longest wavelength in nanometers that paths are traced at.
+pub const LAMBDA_MAX: Point = 830.0;
+
+/// Number of wavelengths carried by each path.
+pub const SPECTRUM_SAMPLES: usize = 4;
+
+/// Integral of the color matching function `y` over the traced range, for the fit below.
+const CIE_Y_INTEGRAL: Point = 106.922_090;
+
+/// CIE standard illuminant D65 from 360 to 830 nm in steps of 10 nm.
+const D65: [Point; 48] = [
+    46.6383, 52.0891, 49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008,
+    117.812, 114.861, 115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405, 104.046, 100.0,
+    96.3342, 95.788, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146,
+    82.2778, 78.2842, 69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054,
+    63.3828, 64.304, 59.4519, 51.959, 57.4406, 60.3125,
+];
+
+/// Scales [`D65`] to unit luminance.
+const D65_NORMALIZATION: Point = 1.0 / 98.851_061;
+
+/// Resolution per axis of the RGB to sigmoid coefficient table.
+const TABLE_RESOLUTION: usize = 16;
+
+/// Spacing in nanometers of the wavelengths used to fit sigmoid coefficients.
+const FIT_STEP: Point = 5.0;
+
+/// CIE 1931 color matching functions, using the multi-lobe fit from Wyman et al.,
+/// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions".
+#[must_use]
+pub fn cie_xyz(lambda: Point) -> Vec3 {
+    let g = |mu: Point, sigma_low: Point, sigma_high: Point| {
+        let sigma = if lambda < mu { sigma_low } else { sigma_high };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+
+    Vec3::from_scalars(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Spectral power of D65 at `lambda`, normalized to unit luminance.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+#[must_use]
+pub fn d65(lambda: Point) -> Point {
+    let t = ((lambda - LAMBDA_MIN) / 10.0).clamp(0.0, (D65.len() - 1) as Point);
+    let i = (t as usize).min(D65.len() - 2);
+    let f = t - i as Point;
+
+    ((1.0 - f) * D65[i] + f * D65[i + 1]) * D65_NORMALIZATION
+}
+
+/// Set of wavelengths traced together along a path, the first one being the hero wavelength.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SampledWavelengths {
+    lambda: [Point; SPECTRUM_SAMPLES],
+    pdf: [Point; SPECTRUM_SAMPLES],
+}
+
+impl SampledWavelengths {
+    /// Samples wavelengths equally spaced from `u`, importance sampling the visible range.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn sample_visible(u: Point) -> Self {
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+        let mut pdf = [0.0; SPECTRUM_SAMPLES];
+
+        for i in 0..SPECTRUM_SAMPLES {
+            let up = (u + i as Point / SPECTRUM_SAMPLES as Point).fract();
+            lambda[i] = sample_visible_wavelength(up);
+            pdf[i] = visible_wavelength_pdf(lambda[i]);
+        }
+
+        SampledWavelengths { lambda, pdf }
+    }
+
+    #[must_use]
+    pub const fn get(&self, i: usize) -> Point {
+        self.lambda[i]
+    }
+
+    #[must_use]
+    pub const fn pdf(&self, i: usize) -> Point {
+        self.pdf[i]
+    }
+
+    #[must_use]
+    pub fn secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&p| p == 0.0)
+    }
+
+    /// Keeps only the hero wavelength, once the path took a direction that depends on it.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn terminate_secondary(&mut self) {
+        if self.secondary_terminated() {
+            return;
+        }
+
+        for p in &mut self.pdf[1..] {
+            *p = 0.0;
+        }
+        self.pdf[0] /= SPECTRUM_SAMPLES as Point;
+    }
+}
+
+/// Values of a spectrum at the wavelengths of a [`SampledWavelengths`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SampledSpectrum {
+    pub values: [Point; SPECTRUM_SAMPLES],
+}
+
+impl SampledSpectrum {
+    #[must_use]
+    pub const fn from_slice(values: [Point; SPECTRUM_SAMPLES]) -> Self {
+        SampledSpectrum { values }
+    }
+
+    /// Monte Carlo estimate of the CIE XYZ tristimulus values of the spectrum.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn to_xyz(self, lambda: &SampledWavelengths) -> Vec3 {
+        let mut xyz = Vec3::new();
+        for i in 0..SPECTRUM_SAMPLES {
+            if lambda.pdf(i) > 0.0 {
+                xyz += cie_xyz(lambda.get(i)) * (self.values[i] / lambda.pdf(i));
+            }
+        }
+
+        xyz / (SPECTRUM_SAMPLES as Point * CIE_Y_INTEGRAL)
+    }
+}
+
+impl From<Point> for SampledSpectrum {
+    fn from(value: Point) -> Self {
+        SampledSpectrum {
+            values: [value; SPECTRUM_SAMPLES],
+        }
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn add(self, rhs: SampledSpectrum) -> Self::Output {
+        SampledSpectrum {
+            values: std::array::from_fn(|i| self.values[i] + rhs.values[i]),
+        }
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, rhs: SampledSpectrum) {
+        *self = *self + rhs;
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn mul(self, rhs: SampledSpectrum) -> Self::Output {
+        SampledSpectrum {
+            values: std::array::from_fn(|i| self.values[i] * rhs.values[i]),
+        }
+    }
+}
+
+impl Mul<Point> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn mul(self, rhs: Point) -> Self::Output {
+        self * SampledSpectrum::from(rhs)
+    }
+}
+
+impl Div<Point> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn div(self, rhs: Point) -> Self::Output {
+        self * (1.0 / rhs)
+    }
+}
+
+/// Channels a path carries light in, converting the RGB values materials and lights return.
+pub trait Channels {
+    type Spectrum: Copy
+        + Default
+        + PartialEq
+        + From<Point>
+        + AddAssign
+        + Mul<Output = Self::Spectrum>
+        + Mul<Point, Output = Self::Spectrum>
+        + Div<Point, Output = Self::Spectrum>;
+
+    /// Converts a reflectance or BSDF value, which may exceed one.
+    fn reflectance(&self, rgb: &Color) -> Self::Spectrum;
+
+    /// Converts emitted radiance.
+    fn illuminant(&self, rgb: &Color) -> Self::Spectrum;
+
+    /// Converts a path's estimate to what the film stores, linear sRGB or CIE XYZ.
+    fn to_film(&self, value: &Self::Spectrum) -> Vec3;
+
+    /// Wavelength in nanometers that dispersive materials should use, if any.
+    fn hero(&self) -> Option<Point>;
+
+    /// Called when the path scatters in a wavelength dependent direction.
+    fn terminate_secondary(&mut self);
+
+    /// Channels for a new path, drawn from `u` where they are sampled at all.
+    fn from_sample(u: Point) -> Self;
+
+    /// Largest channel of `value`, what Russian roulette compares a path's throughput by.
+    fn max_value(&self, value: &Self::Spectrum) -> Point;
+}
+
+/// Traces paths in the three RGB channels directly.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rgb;
+
+impl Channels for Rgb {
+    type Spectrum = Color;
+
+    fn reflectance(&self, rgb: &Color) -> Color {
+        *rgb
+    }
+
+    fn illuminant(&self, rgb: &Color) -> Color {
+        *rgb
+    }
+
+    fn to_film(&self, value: &Color) -> Vec3 {
+        *value
+    }
+
+    fn hero(&self) -> Option<Point> {
+        None
+    }
+
+    fn terminate_secondary(&mut self) {}
+
+    fn from_sample(_u: Point) -> Self {
+        Rgb
+    }
+
+    fn max_value(&self, value: &Color) -> Point {
+        value.x().max(value.y()).max(value.z())
+    }
+}
+
+impl Channels for SampledWavelengths {
+    type Spectrum = SampledSpectrum;
+
+    fn reflectance(&self, rgb: &Color) -> SampledSpectrum {
+        let rgb = clamp_zero(rgb);
+        let max = rgb.x().max(rgb.y()).max(rgb.z());
+
+        // Reflectances up to one have a sigmoid of their own, brighter values are scaled down into range
+        if max <= 1.0 {
+            let sigmoid = RgbSigmoid::from_rgb(&rgb);
+            SampledSpectrum {
+                values: self.lambda.map(|l| sigmoid.eval(l)),
+            }
+        } else {
+            unbounded(&rgb, max, &self.lambda)
+        }
+    }
+
+    fn illuminant(&self, rgb: &Color) -> SampledSpectrum {
+        let rgb = clamp_zero(rgb);
+        let max = rgb.x().max(rgb.y()).max(rgb.z());
+
+        unbounded(&rgb, max, &self.lambda) * SampledSpectrum::from_slice(self.lambda.map(d65))
+    }
+
+    fn to_film(&self, value: &SampledSpectrum) -> Vec3 {
+        value.to_xyz(self)
+    }
+
+    fn hero(&self) -> Option<Point> {
+        Some(self.lambda[0])
+    }
+
+    fn terminate_secondary(&mut self) {
+        SampledWavelengths::terminate_secondary(self);
+    }
+
+    fn from_sample(u: Point) -> Self {
+        Self::sample_visible(u)
+    }
+
+    fn max_value(&self, value: &SampledSpectrum) -> Point {
+        value.values.iter().copied().fold(0.0, Point::max)
+    }
+}
+
+/// Wavelength dependent index of refraction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ior {
+    Constant(Point),
+    /// `n = a + b / λ²`, with the wavelength in micrometers.
+    Cauchy {
+        a: Point,
+        b: Point,
+    },
+    /// `n² = 1 + Σ bᵢ λ² / (λ² - cᵢ)`, with the wavelength in micrometers.
+    Sellmeier {
+        b: [Point; 3],
+        c: [Point; 3],
+    },
+}
+
+impl Ior {
+    /// Wavelength of the sodium D line, where catalogs quote the nominal index.
+    pub const REFERENCE_WAVELENGTH: Point = 589.3;
+
+    /// Schott N-BK7, a common crown glass.
+    pub const BK7: Ior = Ior::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+
+    /// Schott SF11, a dense flint glass with strong dispersion.
+    pub const SF11: Ior = Ior::Sellmeier {
+        b: [1.737_596_95, 0.313_747_346, 1.898_781_01],
+        c: [0.013_188_707, 0.062_306_814_2, 155.236_29],
+    };
+
+    /// Index at `lambda` nanometers, or at [`Ior::REFERENCE_WAVELENGTH`] when tracing RGB.
+    #[must_use]
+    pub fn at(&self, lambda: Option<Point>) -> Point {
+        let micrometers = lambda.unwrap_or(Self::REFERENCE_WAVELENGTH) / 1000.0;
+        let l2 = micrometers * micrometers;
+
+        match *self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => (1.0
+                + b.iter()
+                    .zip(c.iter())
+                    .map(|(b, c)| b * l2 / (l2 - c))
+                    .sum::<Point>())
+            .sqrt(),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_dispersive(&self) -> bool {
+        !matches!(self, Ior::Constant(_))
+    }
+}
+
+impl Default for Ior {
+    fn default() -> Self {
+        Ior::Constant(1.0)
+    }
+}
+
+/// Smooth, bounded reflectance spectrum after Jakob and Hanika, "A Low-Dimensional Function
+/// Space for Efficient Spectral Upsampling": a sigmoid of a quadratic in the wavelength.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RgbSigmoid {
+    /// Quadratic coefficients, over the wavelength remapped to `[0, 1]` across the traced range.
+    coefficients: [Point; 3],
+}
+
+impl RgbSigmoid {
+    /// Looks up the spectrum reproducing `rgb`, whose components must lie in `[0, 1]`.
+    #[allow(clippy::float_cmp)]
+    #[must_use]
+    pub fn from_rgb(rgb: &Color) -> Self {
+        let (r, g, b) = (rgb.x(), rgb.y(), rgb.z());
+        if r == g && g == b {
+            return RgbSigmoid {
+                coefficients: [0.0, 0.0, (r - 0.5) / (r * (1.0 - r)).sqrt()],
+            };
+        }
+
+        sigmoid_table().lookup(rgb)
+    }
+
+    #[must_use]
+    pub fn eval(&self, lambda: Point) -> Point {
+        let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+        let [c0, c1, c2] = self.coefficients;
+
+        sigmoid((c0 * t + c1) * t + c2)
+    }
+}
+
+fn sigmoid(x: Point) -> Point {
+    if x.is_infinite() {
+        return if x > 0.0 { 1.0 } else { 0.0 };
+    }
+
+    0.5 + x / (2.0 * (1.0 + x * x).sqrt())
+}
+
+fn clamp_zero(rgb: &Color) -> Color {
+    Color::from_scalars(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0))
+}
+
+/// Spectrum of an RGB value with components above one, as a sigmoid scaled by twice the maximum.
+fn unbounded(rgb: &Color, max: Point, lambda: &[Point; SPECTRUM_SAMPLES]) -> SampledSpectrum {
+    if max == 0.0 {
+        return SampledSpectrum::default();
+    }
+
+    let scale = 2.0 * max;
+    let sigmoid = RgbSigmoid::from_rgb(&(*rgb / scale));
+    SampledSpectrum {
+        values: lambda.map(|l| scale * sigmoid.eval(l)),
+    }
+}
+
+fn sample_visible_wavelength(u: Point) -> Point {
+    538.0 - 138.888_889 * (0.856_910_62 - 1.827_501_97 * u).atanh()
+}
+
+fn visible_wavelength_pdf(lambda: Point) -> Point {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0;
+    }
+
+    0.003_939_804_2 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+/// Sigmoid coefficients tabulated over RGB, indexed by the largest component, its value, and the
+/// other two components relative to it.
+#[derive(Debug, Clone, PartialEq)]
+struct SigmoidTable {
+    /// Values of the largest component at each slice, denser towards the ends.
+    scale: Vec<Point>,
+    coefficients: Vec<[Point; 3]>,
+}
+
+/// Table shared by every lookup, fitted the first time it's needed.
+fn sigmoid_table() -> &'static SigmoidTable {
+    static TABLE: OnceLock<SigmoidTable> = OnceLock::new();
+    TABLE.get_or_init(|| SigmoidTable::new(TABLE_RESOLUTION))
+}
+
+impl SigmoidTable {
+    #[allow(clippy::cast_precision_loss)]
+    fn new(resolution: usize) -> Self {
+        let fit = Fit::new();
+        let smooth_step = |x: Point| x * x * (3.0 - 2.0 * x);
+        let scale: Vec<Point> = (0..resolution)
+            .map(|k| smooth_step(smooth_step(k as Point / (resolution - 1) as Point)))
+            .collect();
+
+        let mut coefficients = vec![[0.0; 3]; 3 * resolution * resolution * resolution];
+        let index = |l: usize, k: usize, j: usize, i: usize| {
+            ((l * resolution + k) * resolution + j) * resolution + i
+        };
+
+        for l in 0..3 {
+            for j in 0..resolution {
+                let y = j as Point / (resolution - 1) as Point;
+                for i in 0..resolution {
+                    let x = i as Point / (resolution - 1) as Point;
+
+                    let solve = |k: usize, guess: &mut [Point; 3]| {
+                        let mut rgb = [0.0; 3];
+                        rgb[l] = scale[k];
+                        rgb[(l + 1) % 3] = x * scale[k];
+                        rgb[(l + 2) % 3] = y * scale[k];
+                        let rgb = Color::from_slice(rgb);
+
+                        // Warm starts can run into the coefficient limit on saturated colors
+                        let error = fit.solve(&rgb, guess);
+                        if error > 1.0 {
+                            let mut cold = [0.0; 3];
+                            if fit.solve(&rgb, &mut cold) < error {
+                                *guess = cold;
+                            }
+                        }
+                        *guess
+                    };
+
+                    // Walk outwards from a moderate brightness, warm starting from the neighbour
+                    let start = resolution / 5;
+                    let mut guess = [0.0; 3];
+                    for k in start..resolution {
+                        coefficients[index(l, k, j, i)] = solve(k, &mut guess);
+                    }
+                    let mut guess = [0.0; 3];
+                    for k in (0..start).rev() {
+                        coefficients[index(l, k, j, i)] = solve(k, &mut guess);
+                    }
+                }
+            }
+        }
+
+        SigmoidTable {
+            scale,
+            coefficients,
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss,
+        clippy::many_single_char_names
+    )]
+    fn lookup(&self, rgb: &Color) -> RgbSigmoid {
+        let resolution = self.scale.len();
+        let last = (resolution - 1) as Point;
+
+        let l = if rgb.x() >= rgb.y() && rgb.x() >= rgb.z() {
+            0
+        } else if rgb.y() >= rgb.z() {
+            1
+        } else {
+            2
+        };
+        let z = rgb[l];
+        let x = rgb[(l + 1) % 3] * last / z;
+        let y = rgb[(l + 2) % 3] * last / z;
+
+        let xi = (x as usize).min(resolution - 2);
+        let yi = (y as usize).min(resolution - 2);
+        let zi = self
+            .scale
+            .partition_point(|&s| s <= z)
+            .saturating_sub(1)
+            .min(resolution - 2);
+
+        let dx = x - xi as Point;
+        let dy = y - yi as Point;
+        let dz = (z - self.scale[zi]) / (self.scale[zi + 1] - self.scale[zi]);
+
+        let mut coefficients = [0.0; 3];
+        for (corner, weight) in [
+            ((0, 0, 0), (1.0 - dz) * (1.0 - dy) * (1.0 - dx)),
+            ((0, 0, 1), (1.0 - dz) * (1.0 - dy) * dx),
+            ((0, 1, 0), (1.0 - dz) * dy * (1.0 - dx)),
+            ((0, 1, 1), (1.0 - dz) * dy * dx),
+            ((1, 0, 0), dz * (1.0 - dy) * (1.0 - dx)),
+            ((1, 0, 1), dz * (1.0 - dy) * dx),
+            ((1, 1, 0), dz * dy * (1.0 - dx)),
+            ((1, 1, 1), dz * dy * dx),
+        ] {
+            let (k, j, i) = corner;
+            let index = ((l * resolution + zi + k) * resolution + yi + j) * resolution + xi + i;
+            for (c, v) in coefficients.iter_mut().zip(self.coefficients[index]) {
+                *c += weight * v;
+            }
+        }
+
+        RgbSigmoid { coefficients }
+    }
+}
+
+/// Gauss-Newton fit of sigmoid coefficients, minimizing the CIELAB difference under D65.
+struct Fit {
+    /// Remapped wavelengths with the XYZ response of a unit reflectance at each of them.
+    samples: Vec<(Point, Vec3)>,
+    white: Vec3,
+}
+
+impl Fit {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn new() -> Self {
+        let count = ((LAMBDA_MAX - LAMBDA_MIN) / FIT_STEP) as usize + 1;
+        let mut samples: Vec<(Point, Vec3)> = (0..count)
+            .map(|i| {
+                let lambda = LAMBDA_MIN + i as Point * FIT_STEP;
+                let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+                (t, cie_xyz(lambda) * d65(lambda))
+            })
+            .collect();
+
+        let white = samples.iter().fold(Vec3::new(), |acc, (_, xyz)| acc + *xyz);
+        for (_, xyz) in &mut samples {
+            *xyz = *xyz / white.y();
+        }
+
+        Fit {
+            samples,
+            white: white / white.y(),
+        }
+    }
+
+    fn residual(&self, coefficients: &[Point; 3], target: &Vec3) -> Vec3 {
+        let [c0, c1, c2] = *coefficients;
+        let xyz = self.samples.iter().fold(Vec3::new(), |acc, (t, xyz)| {
+            acc + *xyz * sigmoid((c0 * t + c1) * t + c2)
+        });
+
+        self.lab(target) - self.lab(&xyz)
+    }
+
+    fn lab(&self, xyz: &Vec3) -> Vec3 {
+        let f = |t: Point| {
+            const DELTA: Point = 6.0 / 29.0;
+            if t > DELTA.powi(3) {
+                t.cbrt()
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        };
+
+        let fx = f(xyz.x() / self.white.x());
+        let fy = f(xyz.y() / self.white.y());
+        let fz = f(xyz.z() / self.white.z());
+
+        Vec3::from_scalars(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// Refines `coefficients` in place so their sigmoid reproduces `rgb`, returning the
+    /// remaining CIELAB difference.
+    fn solve(&self, rgb: &Color, coefficients: &mut [Point; 3]) -> Point {
+        // Balances truncation against rounding error in the central differences
+        let epsilon = Point::EPSILON.cbrt();
+        let target = rgb.to_xyz();
+
+        for _ in 0..15 {
+            let residual = self.residual(coefficients, &target);
+
+            // Jacobian by central differences, one column per coefficient
+            let columns: [Vec3; 3] = std::array::from_fn(|i| {
+                let mut low = *coefficients;
+                let mut high = *coefficients;
+                low[i] -= epsilon;
+                high[i] += epsilon;
+                (self.residual(&high, &target) - self.residual(&low, &target)) / (2.0 * epsilon)
+            });
+
+            let Some(step) = solve_3x3(&columns, &residual) else {
+                break;
+            };
+            for (c, s) in coefficients.iter_mut().zip(step.points) {
+                *c -= s;
+            }
+
+            // Keep the sigmoid from degenerating into a step
+            let max = coefficients.iter().fold(0.0, |m: Point, c| m.max(c.abs()));
+            if max > 200.0 {
+                for c in coefficients.iter_mut() {
+                    *c *= 200.0 / max;
+                }
+            }
+
+            if residual.lenght_squared() < 1e-12 {
+                break;
+            }
+        }
+
+        self.residual(coefficients, &target).lenght()
+    }
+}
+
+/// Solves `A x = b` by Cramer's rule, `A` given by its columns.
+fn solve_3x3(columns: &[Vec3; 3], b: &Vec3) -> Option<Vec3> {
+    let det = |a: &Vec3, b: &Vec3, c: &Vec3| a.dot(&b.cross(c));
+
+    let d = det(&columns[0], &columns[1], &columns[2]);
+    if d.abs() < 1e-15 {
+        return None;
+    }
+
+    Some(Vec3::from_scalars(
+        det(b, &columns[1], &columns[2]) / d,
+        det(&columns[0], b, &columns[2]) / d,
+        det(&columns[0], &columns[1], b) / d,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Channels, Fit, Ior, RgbSigmoid, SampledWavelengths, SPECTRUM_SAMPLES};
+    use crate::{color::Color, vec3::Scalar};
+
+    #[test]
+    fn sellmeier_matches_catalog() {
+        let n = Ior::BK7.at(Some(587.6));
+        assert!((n - 1.5168).abs() < 1e-4);
+        assert!(Ior::BK7.at(Some(450.0)) > Ior::BK7.at(Some(650.0)));
+    }
+
+    #[test]
+    fn sigmoid_fit_reproduces_rgb() {
+        let fit = Fit::new();
+
+        for rgb in [
+            Color::from_scalars(0.8, 0.2, 0.1),
+            Color::from_scalars(0.1, 0.6, 0.3),
+            Color::from_scalars(0.2, 0.3, 0.9),
+        ] {
+            let mut coefficients = [0.0; 3];
+            fit.solve(&rgb, &mut coefficients);
+            let residual = fit.residual(&coefficients, &rgb.to_xyz());
+            assert!(residual.lenght() < 0.1, "{rgb:?} {residual:?}");
+        }
+    }
+
+    #[test]
+    fn grey_reflectance_is_flat() {
+        let grey = RgbSigmoid::from_rgb(&Color::from(0.25));
+        let lambda = SampledWavelengths::sample_visible(0.3);
+
+        for i in 0..SPECTRUM_SAMPLES {
+            assert!((grey.eval(lambda.get(i)) - 0.25).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn white_illuminant_is_white() {
+        let count = 1000;
+        let mut rgb = Color::new();
+        for i in 0..count {
+            let lambda =
+                SampledWavelengths::sample_visible((i.to_point() + 0.5) / count.to_point());
+            let xyz = lambda.illuminant(&Color::WHITE).to_xyz(&lambda);
+            rgb += Color::from_xyz(xyz.x(), xyz.y(), xyz.z()) / count;
+        }
+
+        for c in rgb.points {
+            assert!((c - 1.0).abs() < 0.02, "{rgb:?}");
+        }
+    }
+}
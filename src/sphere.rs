@@ -1,29 +1,43 @@
+use std::rc::Rc;
+
 use crate::{
+    aabb::Aabb,
+    csg::{Solid, Span},
     hit::{Hit, Hittable},
     interval::Interval,
-    ray,
-    vec3::Point3,
+    material::Material,
+    packet::{self, Mask, RayPacket, LANES},
+    ray::{self, gamma},
+    stats,
+    vec3::{consts::PI, Point, Point3, Vec3},
 };
 
-#[derive(Debug, Default, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub struct Sphere {
     center: Point3,
-    radius: f64,
-    rec: Hit,
+    radius: Point,
+    mat: Rc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: &Point3, radius: f64) -> Self {
+    pub fn new(center: &Point3, radius: Point, mat: Rc<dyn Material>) -> Self {
         Sphere {
             center: *center,
-            radius: f64::max(0.0, radius),
-            rec: Hit::new(),
+            radius: Point::max(0.0, radius),
+            mat,
         }
     }
-}
 
-impl Hittable for Sphere {
-    fn hit(&self, r: &ray::Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+    /// Maps a point on the unit sphere to `u` around the Y axis from X = -1 and `v` from Y = -1 to Y = +1.
+    fn get_sphere_uv(p: &Point3) -> (Point, Point) {
+        let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Distances along the line through `r` to where it meets the sphere, nearest first.
+    fn roots(&self, r: &ray::Ray) -> Option<(Point, Point)> {
         let oc = self.center - *r.origin();
 
         let a = r.direction().lenght_squared();
@@ -33,26 +47,88 @@ impl Hittable for Sphere {
         let discriminant = h.powi(2) - a * c;
 
         if discriminant < 0.0 {
-            return false;
+            return None;
         }
 
         let sqrtd = discriminant.sqrt();
+        Some(((h - sqrtd) / a, (h + sqrtd) / a))
+    }
+
+    /// Fills in `rec` for `r` meeting the sphere at `root`.
+    fn record(&self, r: &ray::Ray, root: Point, rec: &mut Hit) {
+        rec.t = root;
+
+        // The root carries the error of the whole quadratic, so the point is moved back onto
+        // the surface, which leaves only the rounding of the move itself
+        let offset = r.at(rec.t) - self.center;
+        let offset = offset * (self.radius / offset.lenght());
+        rec.p = self.center + offset;
+        rec.error = gamma(5) * offset.abs() + gamma(1) * rec.p.abs();
+
+        let outward_normal = offset / self.radius;
+        rec.set_face_normal(r, &outward_normal);
+        rec.tangent = Vec3::from_scalars(-outward_normal.z(), 0, outward_normal.x());
+        (rec.u, rec.v) = Self::get_sphere_uv(&outward_normal);
+        rec.barycentric = None;
+        rec.mat = Some(self.mat.clone());
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, r: &ray::Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        stats::primitive_test();
+        let Some((near, far)) = self.roots(r) else {
+            return false;
+        };
 
-        let mut root = (h - sqrtd) / a;
+        let mut root = near;
         if !ray_t.surrounds(root) {
-            root = (h + sqrtd) / a;
+            root = far;
             if !ray_t.surrounds(root) {
                 return false;
             }
         }
 
-        rec.t = root;
-        rec.p = r.at(rec.t);
-        rec.normal = (rec.p - self.center) / self.radius;
+        self.record(r, root, rec);
+        true
+    }
 
-        let outward_normal = (rec.p - self.center) / self.radius;
-        rec.set_face_normal(r, &outward_normal);
+    fn hit_packet(&self, packet: &mut RayPacket, recs: &mut [Hit; LANES]) -> Mask {
+        for _ in packet.lanes() {
+            stats::primitive_test();
+        }
+        let (roots, hits) = packet::sphere(&self.center, self.radius, packet);
 
-        true
+        for (lane, rec) in recs.iter_mut().enumerate() {
+            if hits & (1 << lane) != 0 {
+                self.record(&packet.ray(lane), roots[lane], rec);
+                packet.t_max[lane] = roots[lane];
+            }
+        }
+
+        hits
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::from(self.radius);
+        Aabb::from_points(&(self.center - extent), &(self.center + extent))
+    }
+}
+
+impl Solid for Sphere {
+    fn spans(&self, r: &ray::Ray) -> Vec<Span> {
+        stats::primitive_test();
+        let Some((near, far)) = self.roots(r).filter(|(near, far)| near < far) else {
+            return Vec::new();
+        };
+
+        let mut span = Span {
+            enter: Hit::new(),
+            exit: Hit::new(),
+        };
+        self.record(r, near, &mut span.enter);
+        self.record(r, far, &mut span.exit);
+
+        vec![span]
     }
 }
@@ -0,0 +1,140 @@
+use crate::{
+    camera::Camera,
+    color::Color,
+    error::Result,
+    film::Film,
+    integrator::{Integrator, Scene},
+    photon::{emissive_surfaces, trace_photons, trace_visible_point, PhotonMap, VisiblePoint},
+    spectrum::Channels,
+    vec3::{consts::PI, Point, Scalar, Vec3},
+};
+
+/// Stochastic progressive photon mapping after Hachisuka and Jensen. Every pass traces a fresh
+/// set of photons and gathers them at a new visible point per pixel, shrinking the pixel's
+/// radius as photons accumulate so the estimate converges as passes are added.
+///
+/// Rendering fails on scenes lit by emissive surfaces, see [`trace_photons`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sppm {
+    /// Photons emitted before each pass.
+    pub photons_per_pass: usize,
+    /// Gather radius every pixel starts with.
+    pub initial_radius: Point,
+    /// Fraction of the photons found in a pass that are kept, the paper's α.
+    pub alpha: Point,
+    map: PhotonMap,
+    pixels: Vec<PixelStats>,
+    /// Set once light is found leaving an emissive surface, which the maps leave out.
+    emissive: bool,
+}
+
+/// Progressive estimate of the light arriving through one pixel from bounced photons.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PixelStats {
+    radius: Point,
+    /// Photons kept so far, fractional after the radius reductions.
+    photons: Point,
+    /// Flux gathered within the current radius, in film space.
+    flux: Vec3,
+}
+
+impl Default for Sppm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integrator for Sppm {
+    fn begin_pass(&mut self, camera: &Camera, scene: &Scene) {
+        if self.pixels.is_empty() {
+            let stats = PixelStats {
+                radius: self.initial_radius,
+                ..PixelStats::default()
+            };
+            let size = camera.image_width as usize * camera.image_height() as usize;
+            self.pixels = vec![stats; size];
+        }
+
+        let photons = trace_photons(scene, self.photons_per_pass, camera.max_depth);
+        self.emissive |= photons.is_err();
+        self.map = PhotonMap::new(photons.unwrap_or_default());
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sample_pixel<C: Channels>(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        i: u32,
+        j: u32,
+        lambda: &mut C,
+        film: &mut Film,
+    ) {
+        let (radiance, visible) = trace_visible_point(camera, scene, i, j, lambda);
+        film.add_sample(i, j, lambda.to_film(&radiance));
+        self.emissive |= visible.as_ref().is_some_and(VisiblePoint::is_emissive);
+
+        let Some(point) = visible else {
+            return;
+        };
+        let stats = &mut self.pixels[j as usize * camera.image_width as usize + i as usize];
+
+        let mut found = 0;
+        let mut flux = Color::new();
+        self.map
+            .for_each_within(&point.rec.p, stats.radius, |photon| {
+                found += 1;
+                flux += point.flux(photon);
+            });
+        if found == 0 {
+            return;
+        }
+
+        // Keep a fraction of the new photons and shrink the disk so its density stays the same
+        let found = found.to_point();
+        let photons = stats.photons + self.alpha * found;
+        let radius = stats.radius * (photons / (stats.photons + found)).sqrt();
+        let flux = lambda.to_film(&(point.beta * lambda.illuminant(&flux)));
+
+        stats.flux = (stats.flux + flux) * (radius * radius / (stats.radius * stats.radius));
+        stats.photons = photons;
+        stats.radius = radius;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn finish(&mut self, camera: &Camera, film: &mut Film) -> Result<()> {
+        if self.emissive {
+            return Err(emissive_surfaces());
+        }
+
+        let passes = camera.samples_per_pixel.to_point();
+        let emitted = passes * self.photons_per_pass as Point;
+
+        for j in 0..film.height {
+            for i in 0..film.width {
+                let stats = &self.pixels[j as usize * film.width as usize + i as usize];
+                let radiance = stats.flux / (emitted * PI * stats.radius * stats.radius);
+
+                // The film averages over the passes, the estimate already covers all of them
+                film.add_sample(i, j, radiance * passes);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Sppm {
+    /// Creates a new [`Sppm`].
+    #[must_use]
+    pub fn new() -> Self {
+        Sppm {
+            photons_per_pass: 100_000,
+            initial_radius: 0.1,
+            alpha: 2.0 / 3.0,
+            map: PhotonMap::default(),
+            pixels: Vec::new(),
+            emissive: false,
+        }
+    }
+}
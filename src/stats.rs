@@ -0,0 +1,424 @@
+use std::{
+    cell::Cell,
+    fmt::{self, Display, Write},
+    fs,
+    ops::AddAssign,
+    time::Duration,
+};
+
+use crate::{
+    color::Color,
+    error::{Error, Result},
+    image::Image,
+    vec3::{Point, Scalar},
+};
+
+thread_local! {
+    static COUNTS: Cell<TraversalCounts> = const { Cell::new(TraversalCounts::new()) };
+    static RAYS: Cell<RayCounts> = const { Cell::new(RayCounts::new()) };
+}
+
+/// Why a ray was traced through the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayKind {
+    /// First segment of a path leaving the camera.
+    Camera,
+    /// Any later segment of a camera path.
+    Bounce,
+    /// Segment of a path leaving a light, for BDPT light subpaths and photons.
+    Light,
+    /// Visibility test between two points or towards a light, or a ray looking for light at a
+    /// single bounce.
+    Shadow,
+}
+
+/// Rays traced, by kind.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RayCounts {
+    pub camera: u64,
+    pub bounce: u64,
+    pub light: u64,
+    pub shadow: u64,
+}
+
+impl RayCounts {
+    #[must_use]
+    pub const fn new() -> Self {
+        RayCounts {
+            camera: 0,
+            bounce: 0,
+            light: 0,
+            shadow: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.camera + self.bounce + self.light + self.shadow
+    }
+}
+
+/// Work done finding ray intersections.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TraversalCounts {
+    /// Rays tested against a primitive.
+    pub primitive_tests: u64,
+    /// Acceleration structure nodes whose bounds a ray was tested against.
+    pub node_visits: u64,
+}
+
+impl TraversalCounts {
+    #[must_use]
+    pub const fn new() -> Self {
+        TraversalCounts {
+            primitive_tests: 0,
+            node_visits: 0,
+        }
+    }
+}
+
+impl AddAssign for TraversalCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.primitive_tests += rhs.primitive_tests;
+        self.node_visits += rhs.node_visits;
+    }
+}
+
+/// Records a ray tested against a primitive.
+pub fn primitive_test() {
+    COUNTS.with(|counts| {
+        let mut value = counts.get();
+        value.primitive_tests += 1;
+        counts.set(value);
+    });
+}
+
+/// Records a ray tested against the bounds of an acceleration structure node.
+pub fn node_visit() {
+    COUNTS.with(|counts| {
+        let mut value = counts.get();
+        value.node_visits += 1;
+        counts.set(value);
+    });
+}
+
+/// Counts recorded on this thread since the last call, resetting them.
+pub fn take() -> TraversalCounts {
+    COUNTS.with(Cell::take)
+}
+
+/// Records a ray about to be traced through the scene.
+pub fn ray(kind: RayKind) {
+    RAYS.with(|rays| {
+        let mut value = rays.get();
+        match kind {
+            RayKind::Camera => value.camera += 1,
+            RayKind::Bounce => value.bounce += 1,
+            RayKind::Light => value.light += 1,
+            RayKind::Shadow => value.shadow += 1,
+        }
+        rays.set(value);
+    });
+}
+
+/// Rays recorded on this thread since the last call, resetting them.
+pub fn take_rays() -> RayCounts {
+    RAYS.with(Cell::take)
+}
+
+/// Wall clock time spent in each phase of a run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Timings {
+    /// Building the scene description and reading its files.
+    pub load: Duration,
+    /// Building acceleration structures.
+    pub build: Duration,
+    pub render: Duration,
+    /// Writing the images out.
+    pub write: Duration,
+}
+
+/// What a render did and how long it took.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    pub rays: RayCounts,
+    pub traversal: TraversalCounts,
+    pub timings: Timings,
+}
+
+impl RenderStats {
+    /// Segments per camera path, counting the camera ray. Light and photon paths aren't
+    /// included, so the number compares across integrators.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn average_path_length(&self) -> Point {
+        if self.rays.camera == 0 {
+            return 0.0;
+        }
+        (self.rays.camera + self.rays.bounce) as Point / self.rays.camera as Point
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.timings.render.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.rays.total() as f64 / seconds
+    }
+
+    /// The statistics as a JSON object, with times in seconds.
+    #[must_use]
+    pub fn to_json(self) -> String {
+        let timings = &self.timings;
+        format!(
+            concat!(
+                "{{\n",
+                "  \"rays\": {{\"camera\": {}, \"bounce\": {}, \"light\": {}, \"shadow\": {}, \"total\": {}}},\n",
+                "  \"primitive_tests\": {},\n",
+                "  \"node_visits\": {},\n",
+                "  \"average_path_length\": {},\n",
+                "  \"time\": {{\"load\": {}, \"build\": {}, \"render\": {}, \"write\": {}}},\n",
+                "  \"rays_per_second\": {}\n",
+                "}}\n"
+            ),
+            self.rays.camera,
+            self.rays.bounce,
+            self.rays.light,
+            self.rays.shadow,
+            self.rays.total(),
+            self.traversal.primitive_tests,
+            self.traversal.node_visits,
+            self.average_path_length(),
+            timings.load.as_secs_f64(),
+            timings.build.as_secs_f64(),
+            timings.render.as_secs_f64(),
+            timings.write.as_secs_f64(),
+            self.rays_per_second(),
+        )
+    }
+}
+
+impl Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timings = &self.timings;
+
+        writeln!(f, "Rays")?;
+        writeln!(f, "  camera            {:>14}", self.rays.camera)?;
+        writeln!(f, "  bounce            {:>14}", self.rays.bounce)?;
+        writeln!(f, "  light             {:>14}", self.rays.light)?;
+        writeln!(f, "  shadow            {:>14}", self.rays.shadow)?;
+        writeln!(f, "  total             {:>14}", self.rays.total())?;
+        writeln!(f, "Intersection tests")?;
+        writeln!(
+            f,
+            "  primitives        {:>14}",
+            self.traversal.primitive_tests
+        )?;
+        writeln!(f, "  nodes             {:>14}", self.traversal.node_visits)?;
+        writeln!(
+            f,
+            "Average path length {:>14.2}",
+            self.average_path_length()
+        )?;
+        writeln!(f, "Time")?;
+        writeln!(
+            f,
+            "  load              {:>13.3}s",
+            timings.load.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "  build             {:>13.3}s",
+            timings.build.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "  render            {:>13.3}s",
+            timings.render.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "  write             {:>13.3}s",
+            timings.write.as_secs_f64()
+        )?;
+        write!(f, "Rays per second     {:>14.0}", self.rays_per_second())
+    }
+}
+
+/// Traversal cost of every pixel, summed over its samples.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Heatmap {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<TraversalCounts>,
+}
+
+impl Heatmap {
+    /// Creates a new [`Heatmap`] with no work recorded.
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> Self {
+        Heatmap {
+            width,
+            height,
+            pixels: vec![TraversalCounts::new(); width as usize * height as usize],
+        }
+    }
+
+    pub fn add(&mut self, i: u32, j: u32, counts: TraversalCounts) {
+        self.pixels[j as usize * self.width as usize + i as usize] += counts;
+    }
+
+    /// Writes false colour images of the primitive tests and node visits per sample, named
+    /// after `prefix`, along with a histogram of both.
+    ///
+    /// # Errors
+    ///
+    /// Fails when any of the files can't be written.
+    pub fn write(&self, prefix: &str, samples_per_pixel: u32) -> Result<()> {
+        let samples = samples_per_pixel.to_point();
+        let primitives = self.per_sample(samples, |c| c.primitive_tests);
+        let nodes = self.per_sample(samples, |c| c.node_visits);
+
+        self.write_image(&format!("{prefix}_primitives.ppm"), &primitives)?;
+        self.write_image(&format!("{prefix}_nodes.ppm"), &nodes)?;
+
+        let mut summary = String::new();
+        writeln!(summary, "Primitive tests per sample")?;
+        histogram(&mut summary, &primitives)?;
+        writeln!(summary, "\nNode visits per sample")?;
+        histogram(&mut summary, &nodes)?;
+        let path = format!("{prefix}_histogram.txt");
+        fs::write(&path, summary).map_err(|source| Error::io(&path, source))?;
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn per_sample(&self, samples: Point, count: impl Fn(&TraversalCounts) -> u64) -> Vec<Point> {
+        self.pixels
+            .iter()
+            .map(|c| count(c) as Point / samples)
+            .collect()
+    }
+
+    /// Maps costs to colours on a log scale, so a few expensive pixels don't wash out the rest.
+    fn write_image(&self, path: &str, costs: &[Point]) -> Result<()> {
+        let max = costs.iter().copied().fold(0.0, Point::max);
+        let pixels = costs
+            .iter()
+            .map(|cost| {
+                let t = if max > 0.0 {
+                    cost.ln_1p() / max.ln_1p()
+                } else {
+                    0.0
+                };
+                Color::false_colour(t)
+            })
+            .collect();
+
+        Image::from_pixels(self.width, self.height, pixels)?.write(path)
+    }
+}
+
+/// Appends ten equal width bins of `costs` with a bar for the pixels in each.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn histogram(out: &mut String, costs: &[Point]) -> Result<()> {
+    const BINS: usize = 10;
+    const BAR: usize = 40;
+
+    let max = costs.iter().copied().fold(0.0, Point::max);
+    let mean = costs.iter().sum::<Point>() / costs.len().max(1) as Point;
+    let width = if max > 0.0 { max / BINS as Point } else { 1.0 };
+
+    let mut bins = [0_usize; BINS];
+    for cost in costs {
+        bins[((cost / width) as usize).min(BINS - 1)] += 1;
+    }
+    let largest = bins.iter().copied().max().unwrap_or(0).max(1);
+
+    writeln!(out, "mean {mean:.1}, max {max:.1}")?;
+    for (k, count) in bins.iter().enumerate() {
+        let start = k as Point * width;
+        writeln!(
+            out,
+            "{start:10.1} - {:10.1} | {:<BAR$} {count}",
+            start + width,
+            "#".repeat(count * BAR / largest),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::{
+        bdpt::Bdpt,
+        camera::Camera,
+        color::Color,
+        environment::Gradient,
+        hit::HitList,
+        light::{LightList, PointLight},
+        material::Lambertian,
+        path::PathIntegrator,
+        sphere::Sphere,
+        sppm::Sppm,
+        vec3::Point3,
+    };
+
+    #[test]
+    fn path_length_counts_camera_paths_only() {
+        let mut world = HitList::new();
+        let diffuse = Rc::new(Lambertian::new(Color::from(0.5)));
+        world.add(Rc::new(Sphere::new(
+            &Point3::from_scalars(0, 0, -1.5),
+            0.5,
+            diffuse.clone(),
+        )));
+        world.add(Rc::new(Sphere::new(
+            &Point3::from_scalars(0, -100.5, -1.5),
+            100.0,
+            diffuse,
+        )));
+        let mut lights = LightList::new();
+        lights.add(Rc::new(PointLight::new(
+            Point3::from_scalars(0, 1, -1),
+            Color::from(2.0),
+        )));
+
+        let camera = || {
+            Camera::builder()
+                .image_width(16)
+                .samples_per_pixel(16)
+                .max_depth(4)
+                .environment(Rc::new(Gradient::default()))
+                .seed(3)
+                .build()
+                .unwrap()
+        };
+        let (_, path) = camera()
+            .render_image(&mut PathIntegrator, &world, &lights)
+            .unwrap();
+        let (_, bdpt) = camera()
+            .render_image(&mut Bdpt::new(), &world, &lights)
+            .unwrap();
+        let mut sppm = Sppm::new();
+        sppm.photons_per_pass = 1_000;
+        let (_, sppm) = camera().render_image(&mut sppm, &world, &lights).unwrap();
+
+        assert!(bdpt.rays.light > 0);
+        let ratio = bdpt.average_path_length() / path.average_path_length();
+        assert!((ratio - 1.0).abs() < 0.1, "{bdpt:?} against {path:?}");
+
+        // Visible points stop at the first diffuse surface, however many photons bounce
+        assert!(sppm.rays.light > sppm.rays.camera);
+        assert!((sppm.average_path_length() - 1.0).abs() < 1e-12);
+    }
+}
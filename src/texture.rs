@@ -0,0 +1,106 @@
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::{
+    color::Color,
+    error::Result,
+    reader::HdrImage,
+    vec3::{Point, Point3},
+};
+
+/// Spatially varying value looked up with surface coordinates `(u, v)` and the hit point.
+pub trait Texture: Debug {
+    fn value(&self, u: Point, v: Point, p: &Point3) -> Color;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    #[must_use]
+    pub const fn new(albedo: Color) -> Self {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: Point, _v: Point, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// 3D checker pattern alternating between two textures.
+#[derive(Debug, Clone)]
+pub struct Checker {
+    inv_scale: Point,
+    even: Rc<dyn Texture>,
+    odd: Rc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(scale: Point, even: Rc<dyn Texture>, odd: Rc<dyn Texture>) -> Self {
+        Checker {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for Checker {
+    #[allow(clippy::cast_possible_truncation)]
+    fn value(&self, u: Point, v: Point, p: &Point3) -> Color {
+        let sum: i64 = p
+            .points
+            .iter()
+            .map(|c| (self.inv_scale * c).floor() as i64)
+            .sum();
+
+        if sum % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Linear image looked up with the surface coordinates, `v` going up.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImageTexture {
+    image: HdrImage,
+}
+
+impl ImageTexture {
+    #[must_use]
+    pub const fn new(image: HdrImage) -> Self {
+        ImageTexture { image }
+    }
+
+    /// Loads an `.hdr` or `.pfm` image as a texture.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read or decoded.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Self::new(HdrImage::open(path)?))
+    }
+}
+
+impl Texture for ImageTexture {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn value(&self, u: Point, v: Point, _p: &Point3) -> Color {
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        let x = ((u * self.image.width as Point) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as Point) as usize).min(self.image.height - 1);
+
+        self.image.get(x, y)
+    }
+}
@@ -0,0 +1,476 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    csg::{Solid, Span},
+    error::{Error, Result},
+    hit::{Hit, Hittable},
+    interval::Interval,
+    material::Material,
+    quadric::{azimuth, inside_between, Closed, Frame},
+    ray::{gamma, Ray},
+    stats,
+    vec3::{consts::PI, Point, Point3, Scalar, Vec3},
+};
+
+/// Most steps taken closing in on a root of a polynomial.
+const MAX_ITERATIONS: usize = 100;
+
+/// Value and slope of the polynomial with `coefficients`, lowest degree first, at `x`.
+fn evaluate(coefficients: &[f64], x: f64) -> (f64, f64) {
+    coefficients
+        .iter()
+        .rev()
+        .fold((0.0, 0.0), |(value, slope), &c| {
+            (value * x + c, slope * x + value)
+        })
+}
+
+/// Real roots of the polynomial with `coefficients`, lowest degree first, between `lo` and `hi`
+/// in increasing order. The range is split at the roots of the derivative, found the same way,
+/// so the polynomial is monotonic on every piece and holds at most one root there, bracketed by
+/// a change of sign.
+fn polynomial_roots(coefficients: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    if let [c0, c1] = coefficients {
+        let x = -c0 / c1;
+        return if (lo..=hi).contains(&x) {
+            vec![x]
+        } else {
+            Vec::new()
+        };
+    }
+    if coefficients.len() < 2 {
+        return Vec::new();
+    }
+
+    let derivative: Vec<f64> = coefficients[1..]
+        .iter()
+        .zip(1..)
+        .map(|(c, power)| f64::from(power) * c)
+        .collect();
+
+    let mut bounds = vec![lo];
+    bounds.extend(polynomial_roots(&derivative, lo, hi));
+    bounds.push(hi);
+
+    let mut roots: Vec<f64> = bounds
+        .windows(2)
+        .filter_map(|piece| bracketed_root(coefficients, piece[0], piece[1]))
+        .collect();
+    // A root on the boundary between two pieces is found from both
+    roots.dedup();
+    roots
+}
+
+/// Root of a polynomial that is monotonic between `lo` and `hi`, by Newton steps that fall back
+/// to bisection whenever they would leave the bracket.
+fn bracketed_root(coefficients: &[f64], mut lo: f64, mut hi: f64) -> Option<f64> {
+    let (f_lo, _) = evaluate(coefficients, lo);
+    let (f_hi, _) = evaluate(coefficients, hi);
+    if f_lo == 0.0 {
+        return Some(lo);
+    }
+    if f_hi == 0.0 {
+        return Some(hi);
+    }
+    if f_lo.signum() == f_hi.signum() {
+        return None;
+    }
+
+    let mut x = 0.5 * (lo + hi);
+    for _ in 0..MAX_ITERATIONS {
+        let (f, slope) = evaluate(coefficients, x);
+        if f == 0.0 {
+            break;
+        }
+        if f.signum() == f_lo.signum() {
+            lo = x;
+        } else {
+            hi = x;
+        }
+
+        let newton = x - f / slope;
+        let next = if lo < newton && newton < hi {
+            newton
+        } else {
+            0.5 * (lo + hi)
+        };
+        if (next - x).abs() <= f64::EPSILON * x.abs() {
+            break;
+        }
+        x = next;
+    }
+
+    Some(x)
+}
+
+/// Ring shaped tube of `minor` radius swept around a circle of `major` radius, about the local `z`
+/// axis. Like a [`Quadric`](crate::quadric::Quadric) it can be cut off below and above and swept
+/// only part of the way around.
+///
+/// Its surface coordinates follow the azimuth for `u` and go around the tube for `v`, starting
+/// from its outer equator and heading up.
+#[derive(Debug, Clone)]
+pub struct Torus {
+    frame: Frame,
+    major: Point,
+    minor: Point,
+    z: Interval,
+    phi_max: Point,
+    mat: Rc<dyn Material>,
+}
+
+impl Torus {
+    /// Creates a new [`Torus`] whose tube circles the frame origin.
+    pub fn new(frame: Frame, major: Point, minor: Point, mat: Rc<dyn Material>) -> Self {
+        Torus {
+            frame,
+            major,
+            minor,
+            z: Interval::from(-minor, minor),
+            phi_max: 2.0 * PI,
+            mat,
+        }
+    }
+
+    /// Cuts the torus off below `z_min` and above `z_max`, in its own frame.
+    #[must_use]
+    pub fn clip(mut self, z_min: Point, z_max: Point) -> Self {
+        self.z = Interval::overlap(&self.z, &Interval::from(z_min, z_max));
+        self
+    }
+
+    /// Sweeps the torus only `phi_max` radians around the axis, from the local `x` axis.
+    #[must_use]
+    pub fn sweep(mut self, phi_max: Point) -> Self {
+        self.phi_max = phi_max.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// Makes the torus a [`Solid`].
+    ///
+    /// # Errors
+    ///
+    /// Fails when the torus is clipped or swept only part of the way around.
+    pub fn closed(self) -> Result<Closed<Self>> {
+        if self.phi_max < 2.0 * PI || self.z.min > -self.minor || self.z.max < self.minor {
+            return Err(Error::InvalidParameter(
+                "only a torus neither clipped nor swept part of the way is closed".to_owned(),
+            ));
+        }
+
+        Ok(Closed(self))
+    }
+
+    /// Whether the local point `p` is inside the tube.
+    fn encloses(&self, p: &Point3) -> bool {
+        let radial = p.x().hypot(p.y()) - self.major;
+        radial.hypot(p.z()) <= self.minor
+    }
+
+    /// Where the line through the local ray `r` crosses the surface, in order along it, with how
+    /// many crossings there are.
+    ///
+    /// The quartic is solved in double precision whatever [`Point`] is, starting from where the
+    /// line enters the sphere around the torus so the coefficients stay close to the size of the
+    /// torus even for rays from far away. Rounding in the expanded quartic still grows with the
+    /// distance from there, so every root is then polished against the torus equation at the
+    /// point itself.
+    // Widening to double precision does nothing unless `Point` is single precision
+    #[allow(clippy::many_single_char_names, clippy::useless_conversion)]
+    fn crossings(&self, r: &Ray) -> ([Point; 4], usize) {
+        let wide = |v: &Vec3| v.points.map(f64::from);
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let (o, d) = (wide(r.origin()), wide(r.direction()));
+        let (major, minor) = (f64::from(self.major), f64::from(self.minor));
+
+        let mut crossings = [0.0; 4];
+        let mut count = 0;
+
+        let bound = major + minor;
+        let (a, h, c) = (dot(d, d), dot(d, o), dot(o, o) - bound * bound);
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            return (crossings, 0);
+        }
+        let start = (-h - discriminant.sqrt()) / a;
+        let length = 2.0 * discriminant.sqrt() / a;
+        let entry = [0, 1, 2].map(|n| o[n] + start * d[n]);
+
+        // (|p|² + R² - r²)² = 4R²(x² + y²) along the ray, expanded in powers of t
+        let g = dot(d, d);
+        let h = 2.0 * dot(entry, d);
+        let i = dot(entry, entry) + major * major - minor * minor;
+        let ring = 4.0 * major * major;
+        let planar = [
+            entry[0] * entry[0] + entry[1] * entry[1],
+            2.0 * (entry[0] * d[0] + entry[1] * d[1]),
+            d[0] * d[0] + d[1] * d[1],
+        ];
+        let coefficients = [
+            i * i - ring * planar[0],
+            2.0 * h * i - ring * planar[1],
+            h * h + 2.0 * g * i - ring * planar[2],
+            2.0 * g * h,
+            g * g,
+        ];
+
+        let margin = 1e-9 * length;
+        let polish = |mut t: f64| {
+            for _ in 0..2 {
+                let p = [0, 1, 2].map(|n| o[n] + t * d[n]);
+                let sum = dot(p, p) + major * major - minor * minor;
+                let value = sum * sum - ring * (p[0] * p[0] + p[1] * p[1]);
+                let slope = 4.0 * sum * dot(p, d) - 2.0 * ring * (p[0] * d[0] + p[1] * d[1]);
+
+                // Steps that far are off to another root, near a tangency
+                let step = value / slope;
+                if step.is_nan() || step.abs() > margin {
+                    break;
+                }
+                t -= step;
+            }
+            t
+        };
+
+        for s in polynomial_roots(&coefficients, -margin, length + margin) {
+            let t = polish(start + s).to_point();
+            let p = r.at(t);
+            if count < crossings.len() && self.z.contains(p.z()) && azimuth(&p) <= self.phi_max {
+                crossings[count] = t;
+                count += 1;
+            }
+        }
+
+        (crossings, count)
+    }
+
+    /// Fills in `rec` for `r`, which is `local` in the frame of the torus, crossing it at `t`.
+    fn record(&self, r: &Ray, local: &Ray, t: Point, rec: &mut Hit) {
+        let p = local.at(t);
+
+        // Moved back onto the surface, out from the circle through the middle of the tube
+        let radial = p.x().hypot(p.y());
+        let center = if radial > 0.0 {
+            Vec3::from_scalars(p.x(), p.y(), 0) * (self.major / radial)
+        } else {
+            Vec3::new()
+        };
+        let outward_normal = (p - center).unit_vector();
+        let p = center + self.minor * outward_normal;
+        let error = gamma(8) * (p.abs() + Vec3::from(self.major + self.minor));
+
+        rec.t = t;
+        (rec.p, rec.error) = self.frame.point(&p, &error);
+        rec.set_face_normal(r, &self.frame.vector(&outward_normal));
+        rec.tangent = self
+            .frame
+            .vector(&Vec3::from_scalars(-p.y(), p.x(), 0).unit_vector());
+
+        let theta = p.z().atan2(radial - self.major);
+        let theta = if theta < 0.0 { theta + 2.0 * PI } else { theta };
+        (rec.u, rec.v) = (azimuth(&p) / self.phi_max, theta / (2.0 * PI));
+        rec.barycentric = None;
+        rec.mat = Some(self.mat.clone());
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        stats::primitive_test();
+        let local = self.frame.to_local(r);
+        let (crossings, count) = self.crossings(&local);
+
+        match crossings[..count].iter().find(|&&t| ray_t.surrounds(t)) {
+            Some(&t) => {
+                self.record(r, &local, t, rec);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = self.major + self.minor;
+        self.frame.bound(&Aabb::from_points(
+            &Point3::from_scalars(-radius, -radius, self.z.min),
+            &Point3::from_scalars(radius, radius, self.z.max),
+        ))
+    }
+}
+
+impl Solid for Closed<Torus> {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        stats::primitive_test();
+        let torus = &self.0;
+        let local = torus.frame.to_local(r);
+        let (crossings, count) = torus.crossings(&local);
+
+        inside_between(
+            &crossings[..count],
+            |&t| t,
+            |t| torus.encloses(&local.at(t)),
+        )
+        .into_iter()
+        .map(|(enter, exit)| {
+            let mut span = Span {
+                enter: Hit::new(),
+                exit: Hit::new(),
+            };
+            torus.record(r, &local, enter, &mut span.enter);
+            torus.record(r, &local, exit, &mut span.exit);
+            span
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{color::Color, helpers::Generator, material::Lambertian};
+
+    const SCALES: [Point; 4] = [1e-3, 1.0, 1e3, 1e5];
+
+    fn hits(object: &dyn Hittable, r: &Ray, rec: &mut Hit) -> bool {
+        object.hit(r, Interval::from(0.0, Point::INFINITY), rec)
+    }
+
+    #[test]
+    fn first_hit_matches_a_dense_search() {
+        Generator::seed(23);
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let origin = Point3::from_scalars(-1, 0.5, 2);
+        let axis = Vec3::from_scalars(0.3, -1, 0.4).unit_vector();
+        let torus = Torus::new(Frame::new(&origin, &axis), 2.0, 0.5, material.clone());
+        let steps = 20_000;
+
+        for _ in 0..300 {
+            let start = origin + 8.0 * Vec3::random_unit_vector();
+            let target = origin + 2.5 * Vec3::random_unit_vector();
+            let r = Ray::new(start, (target - start).unit_vector());
+
+            // Implicit function of the torus in its frame, sampled along the ray
+            let local = torus.frame.to_local(&r);
+            let f = |t: f64| {
+                let p = local.at(t.to_point()).points.map(f64::from);
+                let length_squared = p[0] * p[0] + p[1] * p[1] + p[2] * p[2];
+                (length_squared + 4.0 - 0.25).powi(2) - 16.0 * (p[0] * p[0] + p[1] * p[1])
+            };
+            let step = 16.0 / f64::from(steps);
+            let first = (0..steps)
+                .map(|k| f64::from(k) * step)
+                .find(|&t| f(t) * f(t + step) <= 0.0);
+
+            let mut rec = Hit::new();
+            match (hits(&torus, &r, &mut rec), first) {
+                (true, found) => {
+                    let q = rec.p - origin;
+                    let height = q.dot(&axis);
+                    let tube = ((q - height * axis).lenght() - 2.0).hypot(height);
+                    assert!((tube - 0.5).abs() < 1e-4);
+                    // The search can step over two roots close together, never the hit over one
+                    if let Some(t) = found {
+                        assert!(
+                            rec.t <= (t + step + 1e-4).to_point(),
+                            "hit at {}, search found {t}",
+                            rec.t
+                        );
+                    }
+                }
+                (false, Some(t)) => panic!("{r:?} missed, search found a crossing at {t}"),
+                (false, None) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn sweep_and_clip() {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let torus = Torus::new(
+            Frame::new(&Point3::new(), &Vec3::from_scalars(0, 0, 1)),
+            2.0,
+            0.5,
+            material.clone(),
+        );
+        let down = |x: Point| Ray::new(Point3::from_scalars(x, 0, 5), Vec3::from_scalars(0, 0, -1));
+        let mut rec = Hit::new();
+
+        assert!(hits(&torus, &down(2.0), &mut rec));
+        assert!((rec.t - 4.5).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert!((rec.v - 0.25).abs() < 1e-6);
+        assert!(!hits(&torus, &down(0.0), &mut rec));
+
+        // With the top cut away the ray meets the bottom of the tube from inside
+        assert!(hits(&torus.clone().clip(-1.0, 0.0), &down(2.0), &mut rec));
+        assert!((rec.t - 5.5).abs() < 1e-6);
+        assert!(!rec.front_face);
+
+        let quarter = torus.sweep(PI / 2.0);
+        assert!(!hits(&quarter, &down(-2.0), &mut rec));
+        assert!(hits(&quarter, &down(2.0), &mut rec));
+    }
+
+    #[test]
+    fn spans_through_the_tube() {
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+        let torus = Torus::new(
+            Frame::new(&Point3::new(), &Vec3::from_scalars(0, 0, 1)),
+            2.0,
+            0.5,
+            material.clone(),
+        );
+        assert!(torus.clone().clip(-1.0, 0.0).closed().is_err());
+        assert!(torus.clone().sweep(PI).closed().is_err());
+        let torus = torus.closed().unwrap();
+
+        let across = Ray::new(Point3::from_scalars(-5, 0, 0), Vec3::from_scalars(1, 0, 0));
+        let spans = torus.spans(&across);
+        assert_eq!(spans.len(), 2);
+        for (span, (enter, exit)) in spans.iter().zip([(2.5, 3.5), (6.5, 7.5)]) {
+            assert!((span.enter.t - enter).abs() < 1e-6 && span.enter.front_face);
+            assert!((span.exit.t - exit).abs() < 1e-6 && !span.exit.front_face);
+        }
+
+        // Grazing the top of the tube touches it without going in
+        let grazing = Ray::new(
+            Point3::from_scalars(2, -5, 0.5),
+            Vec3::from_scalars(0, 1, 0),
+        );
+        assert!(torus
+            .spans(&grazing)
+            .iter()
+            .all(|s| s.exit.t - s.enter.t < 1e-3));
+    }
+
+    #[test]
+    fn spawned_rays_leave_the_surface() {
+        Generator::seed(29);
+        let material = Rc::new(Lambertian::new(Color::from(0.5)));
+
+        for scale in SCALES {
+            let origin = scale * Vec3::random_interval(Interval::from(-10, 10));
+            let frame = Frame::new(&origin, &Vec3::random_unit_vector());
+            let torus = Torus::new(frame, 2.0 * scale, 0.5 * scale, material.clone());
+
+            for _ in 0..500 {
+                let start = origin + 8.0 * scale * Vec3::random_unit_vector();
+                let target = origin + 2.5 * scale * Vec3::random_unit_vector();
+                let mut rec = Hit::new();
+                if !hits(&torus, &Ray::new(start, target - start), &mut rec) {
+                    continue;
+                }
+
+                // Rays can come back to a torus, but not right where they left it
+                for _ in 0..10 {
+                    let away = rec.normal.random_on_hemisphere();
+                    let mut next = Hit::new();
+                    if hits(&torus, &rec.spawn_ray(&away), &mut next) {
+                        assert!((next.p - rec.p).lenght() > 1e-5 * scale);
+                    }
+                }
+            }
+        }
+    }
+}
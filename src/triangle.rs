@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::Aabb,
+    hit::{Hit, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::{gamma, Ray},
+    stats,
+    vec3::{Point3, Vec3},
+};
+
+/// Flat triangle, hit with the Möller-Trumbore test. Its surface coordinates are the
+/// barycentric weights of the second and third vertex.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    vertices: [Point3; 3],
+    mat: Rc<dyn Material>,
+}
+
+impl Triangle {
+    /// Creates a new [`Triangle`], front facing where `a`, `b`, `c` wind counterclockwise.
+    pub fn new(a: &Point3, b: &Point3, c: &Point3, mat: Rc<dyn Material>) -> Self {
+        Triangle {
+            vertices: [*a, *b, *c],
+            mat,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut Hit) -> bool {
+        stats::primitive_test();
+        let [a, b, c] = self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = r.direction().cross(&edge2);
+        let det = edge1.dot(&p);
+        if det.abs() < 1e-12 {
+            return false;
+        }
+
+        let offset = *r.origin() - a;
+        let u = offset.dot(&p) / det;
+        if !(0.0..=1.0).contains(&u) {
+            return false;
+        }
+
+        let q = offset.cross(&edge1);
+        let v = r.direction().dot(&q) / det;
+        if v < 0.0 || u + v > 1.0 {
+            return false;
+        }
+
+        let t = edge2.dot(&q) / det;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        // Interpolating the vertices is far more accurate than following the ray
+        let weighted = [(1.0 - u - v) * a, u * b, v * c];
+        rec.t = t;
+        rec.p = weighted[0] + weighted[1] + weighted[2];
+        rec.error = gamma(7) * (weighted[0].abs() + weighted[1].abs() + weighted[2].abs());
+        rec.set_face_normal(r, &edge1.cross(&edge2).unit_vector());
+        rec.tangent = edge1.unit_vector();
+        (rec.u, rec.v) = (u, v);
+        rec.barycentric = Some(Vec3::from_scalars(1.0 - u - v, u, v));
+        rec.mat = Some(self.mat.clone());
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.vertices;
+        Aabb::from_boxes(&Aabb::from_points(&a, &b), &Aabb::from_points(&a, &c))
+    }
+}
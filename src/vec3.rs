@@ -5,7 +5,44 @@ use std::{
 
 use crate::{helpers::Generator, interval::Interval};
 
+/// Floating point type of all scene math, `f32` with the `f32` feature and `f64` otherwise.
+#[cfg(not(feature = "f32"))]
 pub type Point = f64;
+#[cfg(feature = "f32")]
+pub type Point = f32;
+
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+/// Mathematical constants at the precision of [`Point`].
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+/// Number that converts to a [`Point`], rounded to the nearest one when the precision of
+/// [`Point`] can't hold it exactly.
+pub trait Scalar: Copy {
+    fn to_point(self) -> Point;
+}
+
+macro_rules! impl_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                #[inline]
+                #[allow(
+                    clippy::cast_precision_loss,
+                    clippy::cast_possible_truncation,
+                    clippy::cast_lossless,
+                    clippy::unnecessary_cast
+                )]
+                fn to_point(self) -> Point {
+                    self as Point
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(i8, u8, i16, u16, i32, u32, i64, u64, usize, f32, f64);
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Vec3 {
@@ -16,6 +53,7 @@ pub type Point3 = Vec3;
 
 impl Vec3 {
     /// Generates a Vec3 with all values initialized to zero
+    #[must_use]
     pub const fn new() -> Vec3 {
         Vec3 {
             points: [0.0, 0.0, 0.0],
@@ -24,50 +62,61 @@ impl Vec3 {
 
     pub fn from_scalars<T, U, V>(s1: T, s2: U, s3: V) -> Vec3
     where
-        T: Into<Point> + Copy,
-        U: Into<Point> + Copy,
-        V: Into<Point> + Copy,
+        T: Scalar,
+        U: Scalar,
+        V: Scalar,
     {
         Vec3 {
-            points: [s1.into(), s2.into(), s3.into()],
+            points: [s1.to_point(), s2.to_point(), s3.to_point()],
         }
     }
 
     /// .Recieves a slice and returns a Vec3
-    pub fn from_slice<T: Into<Point> + Copy>(values: [T; 3]) -> Vec3 {
+    pub fn from_slice<T: Scalar>(values: [T; 3]) -> Vec3 {
         Vec3 {
-            points: [values[0].into(), values[1].into(), values[2].into()],
+            points: [
+                values[0].to_point(),
+                values[1].to_point(),
+                values[2].to_point(),
+            ],
         }
     }
 
+    #[must_use]
     pub const fn x(&self) -> Point {
         self.points[0]
     }
 
+    #[must_use]
     pub const fn y(&self) -> Point {
         self.points[1]
     }
 
+    #[must_use]
     pub const fn z(&self) -> Point {
         self.points[2]
     }
 
-    pub fn lenght(&self) -> f64 {
+    #[must_use]
+    pub fn lenght(&self) -> Point {
         self.lenght_squared().sqrt()
     }
 
-    pub const fn lenght_squared(&self) -> f64 {
+    #[must_use]
+    pub const fn lenght_squared(&self) -> Point {
         self.points[0] * self.points[0]
             + self.points[1] * self.points[1]
             + self.points[2] * self.points[2]
     }
 
+    #[must_use]
     pub const fn dot(&self, other: &Vec3) -> Point {
         self.points[0] * other.points[0]
             + self.points[1] * other.points[1]
             + self.points[2] * other.points[2]
     }
 
+    #[must_use]
     pub const fn cross(&self, other: &Vec3) -> Vec3 {
         Vec3 {
             points: [
@@ -78,25 +127,43 @@ impl Vec3 {
         }
     }
 
+    /// Absolute value of every coordinate.
+    #[must_use]
+    pub fn abs(&self) -> Vec3 {
+        Vec3 {
+            points: self.points.map(Point::abs),
+        }
+    }
+
+    /// Returns true if the vector is close to zero in all dimensions.
+    #[must_use]
+    pub fn near_zero(&self) -> bool {
+        let s = 1e-8;
+        self.points.iter().all(|p| p.abs() < s)
+    }
+
     #[inline]
+    #[must_use]
     pub fn unit_vector(&self) -> Vec3 {
         *self / self.lenght()
     }
 
     #[inline]
+    #[must_use]
     pub fn random() -> Vec3 {
         let gen = Generator::random_points(3);
         Vec3::from_scalars(gen[0], gen[1], gen[2])
     }
 
     #[inline]
+    #[must_use]
     pub fn random_interval(interval: Interval) -> Vec3 {
         let gen = Generator::random_points_interval(interval, 3);
         Vec3::from_scalars(gen[0], gen[1], gen[2])
     }
 
-    #[allow(dead_code)]
     #[inline]
+    #[must_use]
     pub fn random_unit_vector() -> Vec3 {
         loop {
             let p = Self::random_interval(Interval::from(-1, 1));
@@ -108,6 +175,7 @@ impl Vec3 {
     }
 
     #[inline]
+    #[must_use]
     pub fn random_on_hemisphere(&self) -> Vec3 {
         let on_unit_sphere = Self::random_unit_vector();
         if self.dot(&on_unit_sphere) > 0.0 {
@@ -120,13 +188,13 @@ impl Vec3 {
 
 impl<T> From<T> for Vec3
 where
-    T: Into<Point> + Copy,
+    T: Scalar,
 {
     /// Takes a single scalar value and creates a new Vec3 with repeating values.
     #[inline]
     fn from(value: T) -> Self {
         Vec3 {
-            points: [value.into(), value.into(), value.into()],
+            points: [value.to_point(), value.to_point(), value.to_point()],
         }
     }
 }
@@ -163,6 +231,10 @@ where
     #[inline]
     fn add(self, rhs: T) -> Self::Output {
         let rhs = rhs.into();
+        #[cfg(all(feature = "simd-vec3", target_arch = "x86_64"))]
+        return crate::simd::add(&self, &rhs);
+
+        #[cfg(not(all(feature = "simd-vec3", target_arch = "x86_64")))]
         Vec3 {
             points: [self[0] + rhs[0], self[1] + rhs[1], self[2] + rhs[2]],
         }
@@ -178,6 +250,10 @@ where
     #[inline]
     fn sub(self, rhs: T) -> Self::Output {
         let rhs = rhs.into();
+        #[cfg(all(feature = "simd-vec3", target_arch = "x86_64"))]
+        return crate::simd::sub(&self, &rhs);
+
+        #[cfg(not(all(feature = "simd-vec3", target_arch = "x86_64")))]
         Vec3 {
             points: [self[0] - rhs[0], self[1] - rhs[1], self[2] - rhs[2]],
         }
@@ -193,6 +269,10 @@ where
     #[inline]
     fn mul(self, rhs: T) -> Self::Output {
         let rhs = rhs.into();
+        #[cfg(all(feature = "simd-vec3", target_arch = "x86_64"))]
+        return crate::simd::mul(&self, &rhs);
+
+        #[cfg(not(all(feature = "simd-vec3", target_arch = "x86_64")))]
         Vec3 {
             points: [self[0] * rhs[0], self[1] * rhs[1], self[2] * rhs[2]],
         }
@@ -201,13 +281,13 @@ where
 
 impl<T> Div<T> for Vec3
 where
-    T: Into<Point> + Copy,
+    T: Scalar,
 {
     type Output = Vec3;
 
     #[inline]
     fn div(self, rhs: T) -> Self::Output {
-        self * (1.0 / rhs.into())
+        self * (1.0 / rhs.to_point())
     }
 }
 
@@ -2,42 +2,78 @@ use std::fmt::Write;
 use std::fs;
 use std::path::Path;
 
-use crate::color::Color;
+use crate::{
+    error::{Error, Result},
+    image::Image,
+};
 
+/// Encodes an [`Image`] in the format named by the extension of its path: a float map for
+/// `.pfm`, a plain gamma encoded PPM for anything else.
 pub struct Writer<'a> {
-    data: String,
     path: &'a Path,
 }
 
 const MAX_COLOR: u8 = 255;
 
 impl<'a> Writer<'a> {
-    pub fn new(path: &'a str, width: u32, height: u32) -> Self {
-        let mut temp = String::with_capacity(width as usize * height as usize * 12 + 15);
-        temp.push_str(&Self::metadata(width, height));
-
+    #[must_use]
+    pub fn new(path: &'a str) -> Self {
         Writer {
-            data: temp,
             path: Path::new(path),
         }
     }
 
-    pub fn add(&mut self, color: Color) {
-        writeln!(self.data, "{color}").unwrap();
+    /// Writes `image` out, creating the directory it goes in if needed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the image can't be encoded or the directory or file can't be written.
+    pub fn write(&self, image: &Image) -> Result<()> {
+        let data = match self.path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("pfm") => Self::encode_pfm(image),
+            _ => Self::encode_ppm(image)?.into_bytes(),
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+        }
+        fs::write(self.path, data).map_err(|source| Error::io(self.path, source))?;
+        Ok(())
     }
 
-    pub fn write(self) {
-        fs::create_dir_all(self.path.parent().unwrap()).unwrap();
-        fs::write(self.path, self.data).unwrap();
+    /// Plain text PPM, gamma encoded and clamped to eight bits per channel.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a pixel can't be formatted.
+    pub fn encode_ppm(image: &Image) -> Result<String> {
+        let mut data =
+            String::with_capacity(image.width as usize * image.height as usize * 12 + 15);
+        write!(data, "P3\n{} {}\n{MAX_COLOR}\n", image.width, image.height)?;
+
+        for color in image.pixels() {
+            writeln!(data, "{color}")?;
+        }
+
+        Ok(data)
     }
 
-    fn metadata(width: u32, height: u32) -> String {
-        let mut buf = String::new();
+    /// Little endian RGB portable float map, keeping the linear values as they are.
+    #[allow(clippy::cast_possible_truncation)]
+    #[must_use]
+    pub fn encode_pfm(image: &Image) -> Vec<u8> {
+        let mut data = format!("PF\n{} {}\n-1.0\n", image.width, image.height).into_bytes();
 
-        writeln!(buf, "P3").unwrap();
-        writeln!(buf, "{width} {height}").unwrap();
-        writeln!(buf, "{MAX_COLOR}").unwrap();
+        // Rows are stored from the bottom of the image up
+        let width = (image.width as usize).max(1);
+        for row in image.pixels().chunks_exact(width).rev() {
+            for color in row {
+                for k in 0..3 {
+                    data.extend_from_slice(&(color[k] as f32).to_le_bytes());
+                }
+            }
+        }
 
-        buf
+        data
     }
 }
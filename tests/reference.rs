@@ -0,0 +1,381 @@
+//! Renders tiny canonical scenes with fixed seeds and holds them to known answers: the furnace
+//! test to its albedo, the other integrators to the path tracer and the rest to references in
+//! `tests/references` rendered with many more samples. After a change that is meant to alter
+//! these images, regenerate the references with
+//!
+//! ```text
+//! cargo test --release --test reference -- --ignored
+//! ```
+
+use std::rc::Rc;
+
+use raytracer::internal::bdpt::Bdpt;
+use raytracer::internal::color::Color;
+use raytracer::internal::compare::compare;
+use raytracer::internal::environment::{Environment, Gradient};
+use raytracer::internal::integrator::Integrator;
+use raytracer::internal::light::{LightList, PointLight};
+use raytracer::internal::material::{Conductor, Dielectric, Lambertian, Material};
+use raytracer::internal::path::PathIntegrator;
+use raytracer::internal::sppm::Sppm;
+use raytracer::internal::triangle::Triangle;
+use raytracer::{Camera, HitList, Hittable, Image, Point, Point3, Scalar, Sphere};
+
+const REFERENCES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/references");
+const WIDTH: u32 = 32;
+const SAMPLES: u32 = 64;
+const REFERENCE_SAMPLES: u32 = 4096;
+const SEED: u64 = 1;
+const REFERENCE_SEED: u64 = 2;
+
+struct Scene {
+    world: HitList<dyn Hittable>,
+    lights: LightList,
+    environment: Rc<dyn Environment>,
+}
+
+fn render(scene: &Scene, samples: u32, seed: u64, spectral: bool) -> Image {
+    render_with(&mut PathIntegrator, scene, samples, seed, spectral)
+}
+
+fn render_with(
+    integrator: &mut impl Integrator,
+    scene: &Scene,
+    samples: u32,
+    seed: u64,
+    spectral: bool,
+) -> Image {
+    let camera = Camera::builder()
+        .image_width(WIDTH)
+        .samples_per_pixel(samples)
+        .max_depth(8)
+        .environment(scene.environment.clone())
+        .spectral(spectral)
+        .seed(seed)
+        .build()
+        .unwrap();
+
+    let (image, _) = camera
+        .render_image(integrator, &scene.world, &scene.lights)
+        .unwrap();
+    image
+}
+
+fn uniform(radiance: Point) -> Rc<dyn Environment> {
+    Rc::new(Gradient {
+        bottom: Color::from(radiance),
+        top: Color::from(radiance),
+    })
+}
+
+/// Diffuse sphere inside a uniform white environment. Light leaving a convex object never
+/// meets it again, so every path off the sphere reflects the environment exactly once.
+fn furnace(albedo: Point) -> Scene {
+    let mut world: HitList<dyn Hittable> = HitList::new();
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0, 0, -1.5),
+        0.5,
+        Rc::new(Lambertian::new(Color::from(albedo))),
+    )));
+
+    Scene {
+        world,
+        lights: LightList::new(),
+        environment: uniform(1.0),
+    }
+}
+
+/// Open fronted box with red and green side walls, lit by a point light under the ceiling.
+///
+/// The usual Cornell box hangs an emissive square from the ceiling instead, but the lights the
+/// path tracer samples directly are all delta lights, and emissive surfaces are only found when
+/// a bounce happens to hit them. At the sample counts here that would leave the box little but
+/// noise, too much to hold it to a reference.
+fn cornell_box() -> Scene {
+    let white: Rc<dyn Material> = Rc::new(Lambertian::new(Color::from(0.73)));
+    let red: Rc<dyn Material> = Rc::new(Lambertian::new(Color::from_scalars(0.65, 0.05, 0.05)));
+    let green: Rc<dyn Material> = Rc::new(Lambertian::new(Color::from_scalars(0.12, 0.45, 0.15)));
+
+    let corner = |x: Point, y: Point, z: Point| Point3::from_scalars(x, y, z);
+    let mut world: HitList<dyn Hittable> = HitList::new();
+    let mut quad = |a: Point3, b: Point3, c: Point3, d: Point3, mat: &Rc<dyn Material>| {
+        world.add(Rc::new(Triangle::new(&a, &b, &c, mat.clone())));
+        world.add(Rc::new(Triangle::new(&a, &c, &d, mat.clone())));
+    };
+
+    let (near, far) = (-1.0, -3.0);
+    quad(
+        corner(-1.0, -1.0, near),
+        corner(1.0, -1.0, near),
+        corner(1.0, -1.0, far),
+        corner(-1.0, -1.0, far),
+        &white,
+    );
+    quad(
+        corner(-1.0, 1.0, near),
+        corner(-1.0, 1.0, far),
+        corner(1.0, 1.0, far),
+        corner(1.0, 1.0, near),
+        &white,
+    );
+    quad(
+        corner(-1.0, -1.0, far),
+        corner(1.0, -1.0, far),
+        corner(1.0, 1.0, far),
+        corner(-1.0, 1.0, far),
+        &white,
+    );
+    quad(
+        corner(-1.0, -1.0, near),
+        corner(-1.0, -1.0, far),
+        corner(-1.0, 1.0, far),
+        corner(-1.0, 1.0, near),
+        &red,
+    );
+    quad(
+        corner(1.0, -1.0, near),
+        corner(1.0, 1.0, near),
+        corner(1.0, 1.0, far),
+        corner(1.0, -1.0, far),
+        &green,
+    );
+
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(-0.4, -0.6, -2.2),
+        0.4,
+        white.clone(),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0.45, -0.7, -1.7),
+        0.3,
+        Rc::new(Conductor::gold(0.3)),
+    )));
+
+    let mut lights = LightList::new();
+    lights.add(Rc::new(PointLight::new(
+        Point3::from_scalars(0, 0.9, -2),
+        Color::from(3.0),
+    )));
+
+    Scene {
+        world,
+        lights,
+        environment: uniform(0.0),
+    }
+}
+
+/// Smooth glass sphere resting above a diffuse ground under the sky gradient.
+fn glass_sphere() -> Scene {
+    let mut world: HitList<dyn Hittable> = HitList::new();
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0, 0, -1.5),
+        0.5,
+        Rc::new(Dielectric::new(1.5, 0.0)),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0, -100.5, -1.5),
+        100.0,
+        Rc::new(Lambertian::new(Color::from(0.5))),
+    )));
+
+    Scene {
+        world,
+        lights: LightList::new(),
+        environment: Rc::new(Gradient::default()),
+    }
+}
+
+/// Diffuse spheres on a diffuse floor, lit both by a point light and a uniform environment so
+/// every light strategy has something to find.
+fn diffuse_spheres() -> Scene {
+    let mut world: HitList<dyn Hittable> = HitList::new();
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(-0.3, 0, -1.5),
+        0.5,
+        Rc::new(Lambertian::new(Color::from_scalars(0.7, 0.3, 0.2))),
+    )));
+    world.add(Rc::new(Sphere::new(
+        &Point3::from_scalars(0.5, -0.25, -1.2),
+        0.25,
+        Rc::new(Lambertian::new(Color::from(0.8))),
+    )));
+
+    // A small floor rather than a huge ground sphere, so photons from the environment, spread
+    // over the bounds of the scene, aren't mostly lost
+    let ground: Rc<dyn Material> = Rc::new(Lambertian::new(Color::from(0.5)));
+    let corners = [(-2.0, 0.0), (2.0, 0.0), (2.0, -3.0), (-2.0, -3.0)]
+        .map(|(x, z)| Point3::from_scalars(x, -0.5, z));
+    world.add(Rc::new(Triangle::new(
+        &corners[0],
+        &corners[1],
+        &corners[2],
+        ground.clone(),
+    )));
+    world.add(Rc::new(Triangle::new(
+        &corners[0],
+        &corners[2],
+        &corners[3],
+        ground,
+    )));
+
+    let mut lights = LightList::new();
+    lights.add(Rc::new(PointLight::new(
+        Point3::from_scalars(1, 1.5, -0.5),
+        Color::from(2.0),
+    )));
+
+    Scene {
+        world,
+        lights,
+        environment: uniform(0.5),
+    }
+}
+
+fn reference_path(name: &str) -> String {
+    format!("{REFERENCES}/{name}.pfm")
+}
+
+fn mean_luminance(image: &Image) -> Point {
+    let pixels = image.pixels();
+    pixels.iter().map(Color::luminance).sum::<Point>() / (WIDTH * WIDTH).to_point()
+}
+
+/// Holds a low sample render to its reference: the noise has to stay near what the sample
+/// count allows, and the overall brightness can't drift by more than four standard errors of
+/// that noise.
+fn check_reference(name: &str, scene: &Scene, samples: u32, tolerance: Point) {
+    let test = render(scene, samples, SEED, false);
+    let reference = Image::open(&reference_path(name)).unwrap();
+    let metrics = compare(&test, &reference).unwrap();
+
+    assert!(
+        metrics.relative_mse < tolerance,
+        "{name}: relMSE {} over {tolerance}",
+        metrics.relative_mse
+    );
+
+    let (test_mean, reference_mean) = (mean_luminance(&test), mean_luminance(&reference));
+    let drift = 4.0 * (metrics.relative_mse / (WIDTH * WIDTH).to_point()).sqrt();
+    assert!(
+        (test_mean / reference_mean - 1.0).abs() < drift,
+        "{name}: mean luminance {test_mean} against {reference_mean}"
+    );
+}
+
+/// Checks that the mean of `pixels` is `expected` in every channel, to within four standard
+/// errors of the pixels' own spread.
+#[allow(clippy::cast_precision_loss)]
+fn check_mean(what: &str, pixels: &[Color], expected: Point) {
+    let n = pixels.len() as Point;
+    for k in 0..3 {
+        let mean = pixels.iter().map(|p| p[k]).sum::<Point>() / n;
+        let variance = pixels.iter().map(|p| (p[k] - mean).powi(2)).sum::<Point>() / (n - 1.0);
+        let tolerance = 4.0 * (variance / n).sqrt() + 1e-9;
+
+        assert!(
+            (mean - expected).abs() < tolerance,
+            "{what}: channel {k} averages {mean}, expected {expected} within {tolerance}"
+        );
+    }
+}
+
+fn check_furnace(spectral: bool) {
+    let albedo = 0.5;
+    let image = render(&furnace(albedo), SAMPLES, SEED, spectral);
+
+    // The sphere covers the middle, the top and bottom rows see only the environment
+    let sphere: Vec<Color> = (13..19)
+        .flat_map(|y| (13..19).map(move |x| (x, y)))
+        .map(|(x, y)| image.get(x, y))
+        .collect();
+    let environment: Vec<Color> = [0, WIDTH - 1]
+        .into_iter()
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| image.get(x, y))
+        .collect();
+
+    check_mean("sphere", &sphere, albedo);
+    check_mean("environment", &environment, 1.0);
+}
+
+#[test]
+fn furnace_matches_albedo() {
+    check_furnace(false);
+}
+
+#[test]
+fn spectral_furnace_matches_albedo() {
+    check_furnace(true);
+}
+
+/// The tolerance is the mean relMSE over seeds plus four standard deviations, well below what
+/// losing either sphere or recoloring a wall costs.
+#[test]
+fn cornell_box_matches_reference() {
+    check_reference("cornell_box", &cornell_box(), 4 * SAMPLES, 0.062);
+}
+
+/// Measures a relMSE of 0.0018 at 64 samples.
+#[test]
+fn glass_sphere_matches_reference() {
+    check_reference("glass_sphere", &glass_sphere(), SAMPLES, 0.005);
+}
+
+/// The bidirectional and photon integrators held to the path tracer on a scene all three
+/// should render the same, with the relMSE each allows measured over a few seeds and doubled.
+/// The mean covers the MIS weights and light strategies, which skew the brightness when wrong.
+#[test]
+fn integrators_agree() {
+    let scene = diffuse_spheres();
+    let path = render(&scene, 4 * SAMPLES, SEED, false);
+
+    let mut sppm = Sppm::new();
+    sppm.photons_per_pass = 2_000;
+    for (name, image, tolerance) in [
+        (
+            "BDPT",
+            render_with(&mut Bdpt::new(), &scene, SAMPLES, SEED, false),
+            0.003,
+        ),
+        (
+            "SPPM",
+            render_with(&mut sppm, &scene, SAMPLES, SEED, false),
+            0.007,
+        ),
+    ] {
+        let metrics = compare(&image, &path).unwrap();
+        assert!(
+            metrics.relative_mse < tolerance,
+            "{name}: relMSE {} over {tolerance}",
+            metrics.relative_mse
+        );
+
+        let (mean, expected) = (mean_luminance(&image), mean_luminance(&path));
+        assert!(
+            (mean / expected - 1.0).abs() < 0.01,
+            "{name}: mean luminance {mean} against {expected}"
+        );
+    }
+}
+
+#[test]
+fn seeded_renders_repeat() {
+    let scene = glass_sphere();
+    assert_eq!(
+        render(&scene, 4, SEED, false),
+        render(&scene, 4, SEED, false)
+    );
+}
+
+#[test]
+#[ignore = "rewrites the stored references"]
+fn update_references() {
+    for (name, scene) in [
+        ("cornell_box", cornell_box()),
+        ("glass_sphere", glass_sphere()),
+    ] {
+        render(&scene, REFERENCE_SAMPLES, REFERENCE_SEED, false)
+            .write(&reference_path(name))
+            .unwrap();
+    }
+}
@@ -0,0 +1,379 @@
+//! Checks every scattering model numerically: that it reflects and transmits no more energy
+//! than arrives, that reflection is reciprocal, that `sample` agrees with `eval` and `pdf`, and
+//! that the directions it samples follow the density it claims, by a chi-square test.
+
+use std::rc::Rc;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use raytracer::internal::color::Color;
+use raytracer::internal::material::{Conductor, Dielectric, Lambertian, Material, Transport};
+use raytracer::internal::principled::Principled;
+use raytracer::internal::texture::SolidColor;
+use raytracer::internal::vec3::consts::PI;
+use raytracer::{Hit, Point, Vec3};
+
+/// Cosines of the outgoing directions every model is tested at, from head on to grazing.
+const COS_THETA_O: [Point; 4] = [0.95, 0.7, 0.4, 0.15];
+/// Quadrature cells along the polar angle and azimuth of the incident direction.
+const GRID: [usize; 2] = [256, 256];
+const SAMPLES: usize = 100_000;
+/// Histogram bins along the polar angle and azimuth for the chi-square test.
+const BINS: [usize; 2] = [10, 20];
+/// Probability of failing a model with a correct sampling routine, over all the tests it gets.
+const SIGNIFICANCE: f64 = 0.01;
+/// Relative difference allowed between values a model computes two different ways.
+const CONSISTENCY: Point = 1e-6 + 1000.0 * Point::EPSILON;
+
+fn surface(front_face: bool, transport: Transport) -> Hit {
+    let mut rec = Hit::new();
+    rec.normal = Vec3::from_scalars(0, 0, 1);
+    rec.tangent = Vec3::from_scalars(1, 0, 0);
+    rec.front_face = front_face;
+    rec.transport = transport;
+    rec
+}
+
+fn direction(cos_theta: Point, phi: Point) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    Vec3::from_scalars(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// Cell of the `[theta, phi]` grid of the sphere that `w` falls in.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn cell(w: &Vec3, [n_theta, n_phi]: [usize; 2]) -> usize {
+    let theta = w.z().clamp(-1.0, 1.0).acos();
+    let phi = w.y().atan2(w.x()).rem_euclid(2.0 * PI);
+    let i = (theta / PI * n_theta as Point) as usize;
+    let j = (phi / (2.0 * PI) * n_phi as Point) as usize;
+    i.min(n_theta - 1) * n_phi + j.min(n_phi - 1)
+}
+
+/// Visits the cells of a `[theta, phi]` grid of the sphere with the direction through the
+/// middle of each and its solid angle, for the midpoint rule. Cells shrink towards the poles,
+/// so lobes pointing straight up or down are still resolved.
+#[allow(clippy::cast_precision_loss)]
+fn for_each_cell([n_theta, n_phi]: [usize; 2], mut visit: impl FnMut(&Vec3, Point)) {
+    let (d_theta, d_phi) = (PI / n_theta as Point, 2.0 * PI / n_phi as Point);
+    for i in 0..n_theta {
+        let theta = (i as Point + 0.5) * d_theta;
+        let area = theta.sin() * d_theta * d_phi;
+        for j in 0..n_phi {
+            let phi = (j as Point + 0.5) * d_phi;
+            visit(&direction(theta.cos(), phi), area);
+        }
+    }
+}
+
+/// Fraction of incident light scattered towards `wo`, integrating `eval` over the sphere.
+fn albedo(material: &dyn Material, wo: &Vec3, rec: &Hit) -> Color {
+    let mut total = Color::new();
+    for_each_cell(GRID, |wi, area| {
+        total += material.eval(wo, wi, rec) * (wi.z().abs() * area);
+    });
+    total
+}
+
+/// The same as [`albedo`], estimated from `sample` instead.
+#[allow(clippy::cast_precision_loss)]
+fn sampled_albedo(material: &dyn Material, wo: &Vec3, rec: &Hit, rng: &mut StdRng) -> Color {
+    let mut total = Color::new();
+    for _ in 0..SAMPLES {
+        let u = [rng.gen(), rng.gen()];
+        if let Some(bs) = material.sample(wo, rec, rng.gen(), u) {
+            total += bs.f * (bs.direction.z().abs() / bs.pdf);
+        }
+    }
+    total / SAMPLES as Point
+}
+
+/// Integrates `f` over `[a, b]` with Simpson's rule, halving the intervals where it doesn't
+/// converge, so narrow peaks get resolved without refining everywhere.
+fn adaptive_simpson(f: &dyn Fn(Point) -> Point, a: Point, b: Point) -> Point {
+    const TOLERANCE: Point = 1e-6;
+    const MAX_DEPTH: u32 = 6;
+
+    fn refine(
+        f: &dyn Fn(Point) -> Point,
+        [a, b]: [Point; 2],
+        [fa, fm, fb]: [Point; 3],
+        whole: Point,
+        tolerance: Point,
+        depth: u32,
+    ) -> Point {
+        let m = 0.5 * (a + b);
+        let (lm, rm) = (0.5 * (a + m), 0.5 * (m + b));
+        let (flm, frm) = (f(lm), f(rm));
+        let left = (m - a) / 6.0 * (fa + 4.0 * flm + fm);
+        let right = (b - m) / 6.0 * (fm + 4.0 * frm + fb);
+        let delta = left + right - whole;
+
+        if depth == 0 || delta.abs() <= 15.0 * tolerance {
+            return left + right + delta / 15.0;
+        }
+        refine(f, [a, m], [fa, flm, fm], left, tolerance / 2.0, depth - 1)
+            + refine(f, [m, b], [fm, frm, fb], right, tolerance / 2.0, depth - 1)
+    }
+
+    let (fa, fm, fb) = (f(a), f(0.5 * (a + b)), f(b));
+    let whole = (b - a) / 6.0 * (fa + 4.0 * fm + fb);
+    refine(f, [a, b], [fa, fm, fb], whole, TOLERANCE, MAX_DEPTH)
+}
+
+/// `x` in double precision, whichever precision the renderer was built with. The statistics
+/// are computed in double precision either way.
+fn wide(x: impl Into<f64>) -> f64 {
+    x.into()
+}
+
+/// Log of the gamma function, by the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut series = 1.000_000_000_190_015;
+    for (k, c) in (1..).zip(COEFFICIENTS) {
+        series += c / (x + f64::from(k));
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x)`.
+#[allow(clippy::many_single_char_names)]
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let front = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        // Series for the lower function
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        while term.abs() > sum.abs() * 1e-15 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+        }
+        1.0 - sum * front
+    } else {
+        // Lentz's continued fraction for the upper function
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let i = f64::from(i);
+            let an = -i * (i - a);
+            b += 2.0;
+            d = an * d + b;
+            d = if d.abs() < tiny { tiny } else { d };
+            c = b + an / c;
+            c = if c.abs() < tiny { tiny } else { c };
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        front * h
+    }
+}
+
+/// Tests that directions sampled towards `wo` are distributed as `pdf` says, returning the
+/// probability of a discrepancy at least this large from a correct routine.
+#[allow(clippy::cast_precision_loss)]
+fn chi_square(material: &dyn Material, wo: &Vec3, rec: &Hit, rng: &mut StdRng) -> f64 {
+    const MIN_EXPECTED: Point = 5.0;
+    let [n_theta, n_phi] = BINS;
+
+    let mut observed = vec![0.0; n_theta * n_phi];
+    for _ in 0..SAMPLES {
+        let u = [rng.gen(), rng.gen()];
+        if let Some(bs) = material.sample(wo, rec, rng.gen(), u) {
+            assert!(!bs.specular, "{material:?} sampled a specular lobe");
+            observed[cell(&bs.direction, BINS)] += 1.0;
+        }
+    }
+
+    let (d_theta, d_phi) = (PI / n_theta as Point, 2.0 * PI / n_phi as Point);
+    let expected: Vec<Point> = (0..n_theta * n_phi)
+        .map(|k| {
+            let theta = (k / n_phi) as Point * d_theta;
+            let phi = (k % n_phi) as Point * d_phi;
+            let over_phi = |t: Point| {
+                let pdf = |p: Point| material.pdf(wo, &direction(t.cos(), p), rec);
+                adaptive_simpson(&pdf, phi, phi + d_phi) * t.sin()
+            };
+            adaptive_simpson(&over_phi, theta, theta + d_theta) * SAMPLES as Point
+        })
+        .collect();
+
+    // Bins expected to see few samples are pooled, where the statistic isn't reliable
+    let mut order: Vec<usize> = (0..expected.len()).collect();
+    order.sort_by(|&a, &b| expected[a].total_cmp(&expected[b]));
+    let (mut statistic, mut dof) = (0.0, -1.0);
+    let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+    for k in order {
+        if expected[k] == 0.0 {
+            assert!(
+                observed[k] == 0.0,
+                "{material:?} sampled where its pdf is zero"
+            );
+        } else if expected[k] < MIN_EXPECTED {
+            pooled_observed += observed[k];
+            pooled_expected += expected[k];
+        } else {
+            statistic += (observed[k] - expected[k]).powi(2) / expected[k];
+            dof += 1.0;
+        }
+    }
+    if pooled_expected > 0.0 {
+        statistic += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+        dof += 1.0;
+    }
+
+    gamma_q(wide(dof) / 2.0, wide(statistic) / 2.0)
+}
+
+/// Runs every check on `material` for light arriving at the side of the surface given by
+/// `front_face`.
+#[allow(clippy::cast_precision_loss)]
+fn check(material: &dyn Material, front_face: bool, transport: Transport) {
+    let rec = surface(front_face, transport);
+    let mut rng = StdRng::seed_from_u64(7);
+    let tests = COS_THETA_O.len() as f64;
+    let significance = 1.0 - (1.0 - SIGNIFICANCE).powf(1.0 / tests);
+
+    for cos_o in COS_THETA_O {
+        let wo = direction(cos_o, 0.3);
+
+        let albedo = albedo(material, &wo, &rec);
+        for k in 0..3 {
+            assert!(
+                albedo[k] <= 1.0 + 1e-3,
+                "{material:?} at cos {cos_o}: albedo {albedo:?} gains energy"
+            );
+        }
+
+        let sampled = sampled_albedo(material, &wo, &rec, &mut rng);
+        for k in 0..3 {
+            assert!(
+                (sampled[k] - albedo[k]).abs() < 0.02 + 0.02 * albedo[k],
+                "{material:?} at cos {cos_o}: sampled albedo {sampled:?}, integrated {albedo:?}"
+            );
+        }
+
+        for _ in 0..100 {
+            let u = [rng.gen(), rng.gen()];
+            let Some(bs) = material.sample(&wo, &rec, rng.gen(), u) else {
+                continue;
+            };
+            let f = material.eval(&wo, &bs.direction, &rec);
+            let pdf = material.pdf(&wo, &bs.direction, &rec);
+            assert!(
+                (f - bs.f).lenght() <= CONSISTENCY * (1.0 + f.lenght()),
+                "{material:?}: sample gave f {:?}, eval {f:?}",
+                bs.f
+            );
+            assert!(
+                (pdf - bs.pdf).abs() <= CONSISTENCY * (1.0 + pdf),
+                "{material:?}: sample gave pdf {}, pdf {pdf}",
+                bs.pdf
+            );
+
+            // Reflection is the same both ways, transmission scales with the indices
+            if bs.direction.z() * wo.z() > 0.0 {
+                let reverse = material.eval(&bs.direction, &wo, &rec);
+                assert!(
+                    (f - reverse).lenght() <= CONSISTENCY * (1.0 + f.lenght()),
+                    "{material:?}: f {f:?} one way, {reverse:?} the other"
+                );
+            }
+        }
+
+        let p = chi_square(material, &wo, &rec, &mut rng);
+        assert!(
+            p > significance,
+            "{material:?} at cos {cos_o}: sampling doesn't match its pdf, p = {p}"
+        );
+    }
+}
+
+#[test]
+fn lambertian() {
+    check(
+        &Lambertian::new(Color::from(0.9)),
+        true,
+        Transport::Radiance,
+    );
+}
+
+#[test]
+fn rough_conductor() {
+    check(&Conductor::gold(0.5), true, Transport::Radiance);
+}
+
+#[test]
+fn anisotropic_conductor() {
+    let aluminium = Conductor::aluminium(0.0);
+    let material = Conductor::new(aluminium.eta, aluminium.k, 0.4, 0.7);
+    check(&material, true, Transport::Radiance);
+}
+
+#[test]
+fn rough_dielectric_from_outside() {
+    check(&Dielectric::new(1.5, 0.5), true, Transport::Radiance);
+}
+
+#[test]
+fn rough_dielectric_from_inside() {
+    // Radiance concentrates entering the denser side, carried importance doesn't
+    check(&Dielectric::new(1.5, 0.5), false, Transport::Importance);
+}
+
+#[test]
+fn principled() {
+    let mut material = Principled::new();
+    material.clearcoat = Rc::new(SolidColor::new(Color::from(1.0)));
+    // A glossier clearcoat is too close to a mirror for the quadrature to resolve
+    material.clearcoat_gloss = Rc::new(SolidColor::new(Color::from(0.0)));
+    check(&material, true, Transport::Radiance);
+}
+
+#[test]
+fn principled_metal() {
+    let mut material = Principled::new();
+    material.metallic = Rc::new(SolidColor::new(Color::from(1.0)));
+    material.roughness = Rc::new(SolidColor::new(Color::from(0.3)));
+    check(&material, true, Transport::Radiance);
+}
+
+#[test]
+fn principled_glass() {
+    let mut material = Principled::new();
+    material.transmission = Rc::new(SolidColor::new(Color::from(1.0)));
+    material.roughness = Rc::new(SolidColor::new(Color::from(0.4)));
+    check(&material, true, Transport::Radiance);
+}
+
+#[test]
+fn gamma_q_matches_known_values() {
+    // Chi-square survival function with 2 degrees of freedom is exp(-x / 2)
+    for x in [0.5, 2.0, 9.0] {
+        assert!((gamma_q(1.0, x / 2.0) - (-x / 2.0).exp()).abs() < 1e-10);
+    }
+    // 95th percentile of chi-square with 10 degrees of freedom
+    assert!((gamma_q(5.0, 18.307 / 2.0) - 0.05).abs() < 1e-4);
+}